@@ -0,0 +1,112 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use otp_core::mode::AuthMode;
+use otp_core::secret::{self, SecretString};
+
+use crate::offline::{self, DEFAULT_SLOT};
+use crate::profile::{Profile, PROFILE_FILE};
+use crate::{derive_bs2, parse_datetime, read_text_file, sheet_font, write_text_file, BS1_FILE, BS3_FILE, PIN_CAPACITY};
+
+pub fn run(args: &[String]) -> i32 {
+    match args[0].as_str() {
+        "list" => list(&args[1..]),
+        "help" | "--help" | "-h" => {
+            print_usage();
+            0
+        }
+        other => {
+            eprintln!("Неизвестная команда: {}", other);
+            print_usage();
+            2
+        }
+    }
+}
+
+fn print_usage() {
+    println!("Использование:");
+    println!("  otp_client                     запуск графического интерфейса");
+    println!("  otp_client list <от> <до> [интервал] [--csv файл] [--pdf файл]");
+    println!("                                 список паролей на период, время в формате");
+    println!("                                 \"ДД.ММ.ГГГГ ЧЧ:ММ:СС\", интервал в секундах");
+    println!("                                 (по умолчанию {}); PIN читается из stdin,", DEFAULT_SLOT);
+    println!("                                 без --csv и --pdf CSV выводится на экран");
+}
+
+fn list(args: &[String]) -> i32 {
+    let mut positional = Vec::new();
+    let mut csv_path = None;
+    let mut pdf_path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--csv" => csv_path = iter.next(),
+            "--pdf" => pdf_path = iter.next(),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    if positional.len() < 2 || positional.len() > 3 {
+        print_usage();
+        return 2;
+    }
+
+    match generate_list(&positional, csv_path, pdf_path) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn generate_list(positional: &[&str], csv_path: Option<&String>, pdf_path: Option<&String>) -> Result<(), String> {
+    let from = parse_datetime(positional[0]).map_err(|e| format!("Начало периода: {}", e))?;
+    let to = parse_datetime(positional[1]).map_err(|e| format!("Конец периода: {}", e))?;
+    let slot = match positional.get(2) {
+        Some(slot) => slot.parse().map_err(|_| format!("Неверный интервал: {}", slot))?,
+        None => DEFAULT_SLOT,
+    };
+
+    let profile = match read_text_file(PROFILE_FILE) {
+        Ok(text) => Profile::parse(&text).map_err(|e| format!("{}: {}", PROFILE_FILE, e))?,
+        Err(_) => Profile::default(),
+    };
+    if profile.mode != AuthMode::Time {
+        return Err(format!("Список паролей строится только в режиме «{}»", AuthMode::Time.title()));
+    }
+
+    let bs1 = SecretString::from(read_text_file(BS1_FILE).map_err(|e| format!("{}: {}", BS1_FILE, e))?);
+    let bs3 = read_text_file(BS3_FILE).map_err(|e| format!("{}: {}", BS3_FILE, e))?;
+    let epoch = parse_datetime(bs3.trim()).map_err(|e| format!("{}: {}", BS3_FILE, e))?;
+
+    eprint!("PIN: ");
+    let _ = io::stderr().flush();
+    let mut pin = secret::buffer(PIN_CAPACITY);
+    io::stdin().lock().read_line(&mut pin).map_err(|e| format!("PIN: {}", e))?;
+
+    let (bs2, warning) = derive_bs2(&pin, &bs1, profile.algorithm)?;
+    if let Some(warning) = warning {
+        eprintln!("{}", warning);
+    }
+
+    let list = offline::generate(&profile, &bs2, epoch, from, to, slot)?;
+
+    if let Some(path) = csv_path {
+        write_text_file(path, &list.to_csv()).map_err(|e| format!("{}: {}", path, e))?;
+        eprintln!("{}: паролей: {}", path, list.entries.len());
+    }
+
+    if let Some(path) = pdf_path {
+        let pdf = list.to_sheet().to_pdf(&sheet_font())?;
+        fs::write(path, pdf).map_err(|e| format!("{}: {}", path, e))?;
+        eprintln!("{}: паролей: {}", path, list.entries.len());
+    }
+
+    if csv_path.is_none() && pdf_path.is_none() {
+        print!("{}", list.to_csv());
+    }
+
+    Ok(())
+}
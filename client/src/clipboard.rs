@@ -0,0 +1,67 @@
+use arboard::Clipboard;
+use otp_core::secret::{self, SecretString, Zeroize};
+use std::time::{Duration, Instant};
+
+// Через сколько секунд пароль убирается из буфера обмена; 0 - не убирается
+pub const DEFAULT_TIMEOUT: u64 = 30;
+pub const MAX_TIMEOUT: u64 = 600;
+
+// Пароль в системном буфере обмена. По истечении срока буфер очищается,
+// только если в нём всё ещё наш пароль: скопированное после него не трогаем.
+#[derive(Default)]
+pub struct ClipboardGuard {
+    // Открывается при первом копировании; в X11 содержимое буфера живёт,
+    // пока жив объект
+    clipboard: Option<Clipboard>,
+    // Скопированный пароль и время очистки
+    pending: Option<(SecretString, Instant)>,
+}
+
+impl ClipboardGuard {
+    pub fn copy(&mut self, text: &str, timeout: u64) -> Result<(), String> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self.clipboard.insert(Clipboard::new().map_err(|e| format!("Буфер обмена: {}", e))?),
+        };
+        clipboard.set_text(text).map_err(|e| format!("Буфер обмена: {}", e))?;
+
+        self.pending = (timeout > 0).then(|| (secret::copy(text), Instant::now() + Duration::from_secs(timeout)));
+        Ok(())
+    }
+
+    // Сколько осталось до очистки
+    pub fn remaining(&self) -> Option<Duration> {
+        self.pending
+            .as_ref()
+            .map(|(_, deadline)| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    // Вызывается на каждом кадре
+    pub fn tick(&mut self) {
+        if self.remaining() == Some(Duration::ZERO) {
+            self.clear();
+        }
+    }
+
+    // Очистка сразу, если в буфере наш пароль
+    pub fn clear(&mut self) {
+        let Some((text, _)) = self.pending.take() else {
+            return;
+        };
+        if let Some(clipboard) = &mut self.clipboard {
+            if let Ok(mut current) = clipboard.get_text() {
+                if current == *text {
+                    let _ = clipboard.clear();
+                }
+                current.zeroize();
+            }
+        }
+    }
+}
+
+// Пароль не остаётся в буфере после закрытия клиента
+impl Drop for ClipboardGuard {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
@@ -1,19 +1,48 @@
+mod cli;
+mod clipboard;
+mod offline;
+mod profile;
+
 use eframe::egui;
-use des::cipher::{BlockEncrypt, KeyInit};
-use des::Des;
-use hex::{encode, decode};
-use chrono::{NaiveDateTime, Local};
+use chrono::{NaiveDateTime, Local, Utc};
 use std::fs;
 use std::path::Path;
-use encoding_rs::WINDOWS_1251;
+use std::time::{Duration, Instant};
+use otp_core::cipher::{challenge_from_transaction, encrypt, time_block, Algorithm, MAX_SLOT, MAX_TIME_STEP};
+use otp_core::format::{format_password, OutputFormat, MAX_DIGITS, MIN_DIGITS};
+use otp_core::mode::AuthMode;
+use otp_core::ocra::{self, OcraInput, OcraSuite, DEFAULT_SUITE};
+use otp_core::secret::{self, SecretString, Zeroize};
+use otp_core::skey::{self, ChainHash};
+use otp_core::text::TextEncoding;
+use otp_core::weak_keys::{key_weakness, KeyWeakness};
+
+use clipboard::ClipboardGuard;
+use profile::{Profile, PROFILE_FILE};
 
 const BS1_FILE: &str = "bs1.txt";
 const BS3_FILE: &str = "bs3.txt";
 
+const LIST_CSV_FILE: &str = "passwords.csv";
+const LIST_PDF_FILE: &str = "passwords.pdf";
+
+// Запас буферов ввода PIN и BS1: ввод в их пределах не перевыделяет память
+// и не оставляет незатёртых копий
+const PIN_CAPACITY: usize = 16;
+const BS1_CAPACITY: usize = 128;
+
+// Через сколько BS2, пароль и блок стираются из памяти и окна
+const SECRET_LIFETIME: Duration = Duration::from_secs(60);
+
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+    
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([550.0, 700.0])
+            .with_inner_size([550.0, 800.0])
             .with_resizable(true),
         ..Default::default()
     };
@@ -26,26 +55,65 @@ fn main() -> Result<(), eframe::Error> {
 }
 
 struct ClientApp {
-    pin: String,
-    bs1: String,
+    pin: SecretString,
+    bs1: SecretString,
     bs3: String,
-    password: String,
-    bs2: String,
+    algorithm: Algorithm,
+    format: OutputFormat,
+    mode: AuthMode,
+    suite: String,
+    counter: u64,
+    step: u64,
+    chain_hash: ChainHash,
+    seed: String,
+    sequence: u64,
+    clipboard_timeout: u64,
+    auto_copy: bool,
+    clipboard: ClipboardGuard,
+    challenge: String,
+    transaction: String,
+    list_from: String,
+    list_to: String,
+    list_slot: u64,
+    password: SecretString,
+    bs2: SecretString,
     time_block: String,
+    // Когда получен пароль: по истечении SECRET_LIFETIME он стирается
+    generated: Option<Instant>,
     error_message: String,
+    warning_message: String,
     success_message: String,
 }
 
 impl Default for ClientApp {
     fn default() -> Self {
         let mut app = Self {
-            pin: String::new(),
-            bs1: String::new(),
+            pin: secret::buffer(PIN_CAPACITY),
+            bs1: secret::buffer(BS1_CAPACITY),
             bs3: String::new(),
-            password: String::new(),
-            bs2: String::new(),
+            algorithm: Algorithm::Des,
+            format: OutputFormat::Hex,
+            mode: AuthMode::Time,
+            suite: DEFAULT_SUITE.to_string(),
+            counter: 0,
+            step: 1,
+            chain_hash: ChainHash::Md5,
+            seed: String::new(),
+            sequence: skey::DEFAULT_SEQUENCE,
+            clipboard_timeout: clipboard::DEFAULT_TIMEOUT,
+            auto_copy: false,
+            clipboard: ClipboardGuard::default(),
+            challenge: String::new(),
+            transaction: String::new(),
+            list_from: String::new(),
+            list_to: String::new(),
+            list_slot: offline::DEFAULT_SLOT,
+            password: SecretString::default(),
+            bs2: SecretString::default(),
             time_block: String::new(),
+            generated: None,
             error_message: String::new(),
+            warning_message: String::new(),
             success_message: String::new(),
         };
         
@@ -56,14 +124,35 @@ impl Default for ClientApp {
 
 impl ClientApp {
     fn load_config(&mut self) {
-        if let Ok(content) = read_file_windows1251(BS1_FILE) {
-            self.bs1 = content.trim().to_uppercase();
+        if let Ok(content) = read_text_file(BS1_FILE) {
+            let content = SecretString::from(content);
+            self.bs1.zeroize();
+            self.bs1.extend(content.trim().chars().map(|c| c.to_ascii_uppercase()));
         }
         
-        if let Ok(content) = read_file_windows1251(BS3_FILE) {
+        if let Ok(content) = read_text_file(BS3_FILE) {
             self.bs3 = content.trim().to_string();
         }
         
+        if let Ok(content) = read_text_file(PROFILE_FILE) {
+            match Profile::parse(&content) {
+                Ok(profile) => {
+                    self.algorithm = profile.algorithm;
+                    self.format = profile.format;
+                    self.mode = profile.mode;
+                    self.suite = profile.suite;
+                    self.counter = profile.counter;
+                    self.step = profile.step;
+                    self.chain_hash = profile.chain_hash;
+                    self.seed = profile.seed;
+                    self.sequence = profile.sequence;
+                    self.clipboard_timeout = profile.clipboard_timeout;
+                    self.auto_copy = profile.auto_copy;
+                }
+                Err(e) => self.error_message = format!("{}: {}", PROFILE_FILE, e),
+            }
+        }
+        
         if !self.bs1.is_empty() || !self.bs3.is_empty() {
             self.success_message = "Конфигурация загружена".to_string();
         }
@@ -72,14 +161,18 @@ impl ClientApp {
     fn save_config(&mut self) {
         let mut errors = Vec::new();
         
-        if let Err(e) = write_file_windows1251(BS1_FILE, &self.bs1) {
+        if let Err(e) = write_text_file(BS1_FILE, &self.bs1) {
             errors.push(format!("BS1: {}", e));
         }
         
-        if let Err(e) = write_file_windows1251(BS3_FILE, &self.bs3) {
+        if let Err(e) = write_text_file(BS3_FILE, &self.bs3) {
             errors.push(format!("BS3: {}", e));
         }
         
+        if let Err(e) = self.save_profile() {
+            errors.push(format!("Профиль: {}", e));
+        }
+        
         if errors.is_empty() {
             self.success_message = "Конфигурация сохранена!".to_string();
             self.error_message.clear();
@@ -89,205 +182,642 @@ impl ClientApp {
         }
     }
     
+    fn profile(&self) -> Profile {
+        Profile {
+            algorithm: self.algorithm,
+            format: self.format,
+            mode: self.mode,
+            suite: self.suite.trim().to_string(),
+            counter: self.counter,
+            step: self.step,
+            chain_hash: self.chain_hash,
+            seed: self.seed.trim().to_string(),
+            sequence: self.sequence,
+            clipboard_timeout: self.clipboard_timeout,
+            auto_copy: self.auto_copy,
+        }
+    }
+    
+    fn save_profile(&self) -> Result<(), String> {
+        write_text_file(PROFILE_FILE, &self.profile().to_text())
+    }
+    
     fn clear_config(&mut self) {
-        self.bs1.clear();
+        self.bs1.zeroize();
         self.bs3.clear();
-        self.pin.clear();
-        self.password.clear();
-        self.bs2.clear();
-        self.time_block.clear();
+        self.pin.zeroize();
+        self.forget_password();
         
         let _ = fs::remove_file(BS1_FILE);
         let _ = fs::remove_file(BS3_FILE);
+        let _ = fs::remove_file(PROFILE_FILE);
+        self.algorithm = Algorithm::Des;
+        self.format = OutputFormat::Hex;
+        self.mode = AuthMode::Time;
+        self.suite = DEFAULT_SUITE.to_string();
+        self.counter = 0;
+        self.step = 1;
+        self.chain_hash = ChainHash::Md5;
+        self.seed.clear();
+        self.sequence = skey::DEFAULT_SEQUENCE;
+        self.clipboard_timeout = clipboard::DEFAULT_TIMEOUT;
+        self.auto_copy = false;
+        self.clipboard.clear();
+        self.challenge.clear();
+        self.transaction.clear();
         
         self.success_message = "Конфигурация очищена".to_string();
         self.error_message.clear();
+        self.warning_message.clear();
     }
     
     fn generate_password(&mut self) {
         self.error_message.clear();
+        self.warning_message.clear();
         self.success_message.clear();
         
-        let pin = self.pin.trim().to_uppercase();
-        if pin.len() != 4 || !pin.chars().all(|c| c.is_ascii_hexdigit()) {
-            self.error_message = "PIN: 4 HEX символа (0-9, A-F)!".to_string();
-            return;
-        }
-        
-        let bs1 = self.bs1.trim().to_uppercase();
-        if bs1.len() != 12 || !bs1.chars().all(|c| c.is_ascii_hexdigit()) {
-            self.error_message = "BS1: 12 HEX символов!".to_string();
-            return;
+        match derive_bs2(&self.pin, &self.bs1, self.algorithm) {
+            Ok((bs2, warning)) => {
+                self.bs2 = bs2;
+                self.warning_message = warning.unwrap_or_default();
+            }
+            Err(e) => {
+                self.error_message = e;
+                self.forget_password();
+                return;
+            }
         }
         
-        self.bs2 = format!("{}{}", pin, bs1);
+        let password = match self.mode {
+            AuthMode::Time => self.time_password(),
+            AuthMode::Challenge => self.challenge_password(),
+            AuthMode::Ocra => self.ocra_response(),
+            AuthMode::Counter => self.counter_password(),
+            AuthMode::HashChain => self.hash_chain_password(),
+        };
         
-        let start_time = match parse_datetime(self.bs3.trim()) {
-            Ok(dt) => dt,
+        match password {
+            Ok(encrypted) => {
+                self.password = encrypted.into();
+                self.generated = Some(Instant::now());
+                self.success_message = "Пароль сгенерирован!".to_string();
+                if self.auto_copy {
+                    self.copy_password();
+                }
+            }
             Err(e) => {
-                self.error_message = format!("Дата: {}. Формат: ДД.ММ.ГГГГ ЧЧ:ММ:СС", e);
-                return;
+                self.error_message = e;
             }
+        }
+    }
+    
+    // BS2, пароль и блок стираются после SECRET_LIFETIME и при очистке
+    fn forget_password(&mut self) {
+        self.bs2.zeroize();
+        self.password.zeroize();
+        self.time_block.zeroize();
+        self.generated = None;
+    }
+    
+    fn copy_password(&mut self) {
+        match self.clipboard.copy(&self.password, self.clipboard_timeout) {
+            Ok(()) => self.success_message = "Пароль скопирован в буфер обмена".to_string(),
+            Err(e) => self.error_message = e,
+        }
+    }
+    
+    // Список паролей на период для печати (режим по времени)
+    fn export_list(&mut self, pdf: bool) {
+        self.error_message.clear();
+        self.warning_message.clear();
+        self.success_message.clear();
+        
+        match self.build_list(pdf) {
+            Ok(message) => self.success_message = message,
+            Err(e) => self.error_message = e,
+        }
+    }
+    
+    fn build_list(&mut self, pdf: bool) -> Result<String, String> {
+        let (bs2, warning) = derive_bs2(&self.pin, &self.bs1, self.algorithm)?;
+        self.warning_message = warning.unwrap_or_default();
+        
+        let epoch = parse_datetime(self.bs3.trim())
+            .map_err(|e| format!("Дата: {}. Формат: ДД.ММ.ГГГГ ЧЧ:ММ:СС", e))?;
+        let from = parse_datetime(self.list_from.trim()).map_err(|e| format!("Начало периода: {}", e))?;
+        let to = parse_datetime(self.list_to.trim()).map_err(|e| format!("Конец периода: {}", e))?;
+        
+        let list = offline::generate(&self.profile(), &bs2, epoch, from, to, self.list_slot)?;
+        
+        let path = if pdf {
+            let bytes = list.to_sheet().to_pdf(&sheet_font())?;
+            fs::write(LIST_PDF_FILE, bytes).map_err(|e| format!("{}: {}", LIST_PDF_FILE, e))?;
+            LIST_PDF_FILE
+        } else {
+            write_text_file(LIST_CSV_FILE, &list.to_csv()).map_err(|e| format!("{}: {}", LIST_CSV_FILE, e))?;
+            LIST_CSV_FILE
         };
         
+        Ok(format!("Список сохранён: {} (паролей: {})", path, list.entries.len()))
+    }
+    
+    fn time_password(&mut self) -> Result<String, String> {
+        let start_time = parse_datetime(self.bs3.trim())
+            .map_err(|e| format!("Дата: {}. Формат: ДД.ММ.ГГГГ ЧЧ:ММ:СС", e))?;
+        
         let current_time = Local::now().naive_local();
         let time_diff = (current_time.and_utc().timestamp() - start_time.and_utc().timestamp()).max(0) as u64;
         
-        self.time_block = format!("{:016X}", time_diff);
+        self.time_block = time_block(self.algorithm, time_diff / self.step.max(1));
         
-        match des_encrypt(&self.time_block, &self.bs2) {
-            Ok(encrypted) => {
-                self.password = encrypted;
-                self.success_message = "Пароль сгенерирован!".to_string();
-            }
-            Err(e) => {
-                self.error_message = format!("Шифрование: {}", e);
+        encrypt(self.algorithm, &self.time_block, &self.bs2)
+            .and_then(|hex| format_password(self.format, &hex))
+            .map_err(|e| format!("Шифрование: {}", e))
+    }
+    
+    // Каждый пароль расходует одно значение счётчика; оно сохраняется сразу,
+    // чтобы пароль не повторился после перезапуска
+    fn counter_password(&mut self) -> Result<String, String> {
+        self.time_block = time_block(self.algorithm, self.counter);
+        
+        let password = encrypt(self.algorithm, &self.time_block, &self.bs2)
+            .and_then(|hex| format_password(self.format, &hex))
+            .map_err(|e| format!("Шифрование: {}", e))?;
+        
+        self.counter += 1;
+        self.save_profile().map_err(|e| format!("Профиль: {}", e))?;
+        
+        Ok(password)
+    }
+    
+    // Пароли цепочки S/KEY расходуются от большего номера к меньшему; номер
+    // уменьшается и сохраняется сразу, как и счётчик событий
+    fn hash_chain_password(&mut self) -> Result<String, String> {
+        let seed = self.seed.trim().to_string();
+        skey::validate_seed(&seed)?;
+        
+        if self.sequence == 0 {
+            return Err("Цепочка S/KEY исчерпана, получите новый seed у администратора!".to_string());
+        }
+        
+        let value = skey::otp(self.chain_hash, &seed, &self.bs2, self.sequence);
+        self.time_block = format!("{} = {}", skey::challenge(self.chain_hash, self.sequence, &seed), skey::to_hex(value));
+        
+        self.sequence -= 1;
+        self.save_profile().map_err(|e| format!("Профиль: {}", e))?;
+        
+        if self.sequence < 10 {
+            self.warning_message = format!("⚠ В цепочке S/KEY осталось паролей: {}", self.sequence);
+        }
+        
+        Ok(skey::to_words(value))
+    }
+    
+    // Запрос сервера шифруется вместо блока времени
+    fn challenge_password(&mut self) -> Result<String, String> {
+        let derived = challenge_from_transaction(self.algorithm, &self.transaction);
+        let challenge = self.resolve_challenge(derived)?.to_uppercase();
+        
+        let block_len = self.algorithm.block_len() * 2;
+        if challenge.len() != block_len || !challenge.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Запрос: {} HEX символов!", block_len));
+        }
+        
+        self.time_block = challenge;
+        
+        encrypt(self.algorithm, &self.time_block, &self.bs2)
+            .and_then(|hex| format_password(self.format, &hex))
+            .map_err(|e| format!("Шифрование: {}", e))
+    }
+    
+    fn ocra_response(&mut self) -> Result<String, String> {
+        let suite = OcraSuite::parse(self.suite.trim())?;
+        let challenge = self.resolve_challenge(suite.challenge_from_transaction(&self.transaction))?;
+        let key = secret::decode_hex(&self.bs2).map_err(|e| format!("Ключ: {}", e))?;
+        
+        // BS2 начинается с PIN в верхнем регистре
+        let input = OcraInput {
+            question: &challenge,
+            pin: &self.bs2[..4],
+            unix_time: Utc::now().timestamp().max(0) as u64,
+            ..Default::default()
+        };
+        let response = ocra::response(&suite, &key, &input)?;
+        
+        self.time_block = challenge;
+        Ok(response)
+    }
+    
+    // Если введены данные транзакции, запрос вычисляется из них; введённый
+    // вручную запрос должен с ним совпасть, иначе подписываются чужие данные
+    fn resolve_challenge(&self, derived: String) -> Result<String, String> {
+        let challenge = self.challenge.trim();
+        
+        if self.transaction.trim().is_empty() {
+            if challenge.is_empty() {
+                return Err("Введите запрос сервера!".to_string());
             }
+            return Ok(challenge.to_string());
+        }
+        
+        if !challenge.is_empty() && !challenge.eq_ignore_ascii_case(&derived) {
+            return Err("Запрос не соответствует данным транзакции!".to_string());
         }
+        
+        Ok(derived)
+    }
+    
+    // Служебная информация: секреты скрыты, пока нажата кнопка 👁. В рабочей
+    // сборке (без feature diagnostics) панели нет
+    #[cfg(feature = "diagnostics")]
+    fn diagnostics(&self, ui: &mut egui::Ui) {
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgb(248, 249, 250))
+            .inner_margin(10.0)
+            .rounding(5.0)
+            .show(ui, |ui| {
+                let reveal = ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("Служебная информация:").strong().color(egui::Color32::from_gray(100)));
+                    hold_to_reveal(ui)
+                }).inner;
+                ui.add_space(5.0);
+
+                let show = |value: &str| match value {
+                    "" => "-".to_string(),
+                    value if reveal => value.to_string(),
+                    value => mask(value),
+                };
+                ui.label(format!("Базовый секрет 1: {}", show(&self.bs1)));
+                ui.label(format!("Базовый секрет 2 (ключ): {}", show(&self.bs2)));
+                ui.label(format!("Начальная настройка: {}", show(&self.bs3)));
+                let block_label = match self.mode {
+                    AuthMode::Time => "Показание часов (блок)",
+                    AuthMode::Counter => "Счётчик (блок)",
+                    AuthMode::HashChain => "Звено цепочки",
+                    AuthMode::Challenge | AuthMode::Ocra => "Запрос (блок)",
+                };
+                ui.label(format!("{}: {}", block_label, show(&self.time_block)));
+            });
     }
 }
 
 impl eframe::App for ClientApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Отсчёт до очистки буфера идёт и без действий пользователя
+        self.clipboard.tick();
+        if self.clipboard.remaining().is_some() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+        if let Some(generated) = self.generated {
+            match SECRET_LIFETIME.checked_sub(generated.elapsed()) {
+                Some(remaining) => ctx.request_repaint_after(remaining),
+                None => self.forget_password(),
+            }
+        }
+        
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.add_space(10.0);
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add_space(10.0);
             
-            ui.vertical_centered(|ui| {
-                ui.heading("🔐 Генератор одноразовых паролей");
-            });
+                ui.vertical_centered(|ui| {
+                    ui.heading("🔐 Генератор одноразовых паролей");
+                });
             
-            ui.add_space(10.0);
-            ui.separator();
-            ui.add_space(10.0);
+                ui.add_space(10.0);
+                ui.separator();
+                ui.add_space(10.0);
             
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(248, 249, 250))
-                .inner_margin(10.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    ui.label(egui::RichText::new("⚙️ Конфигурация").strong());
-                    ui.add_space(5.0);
+                egui::Frame::none()
+                    .fill(egui::Color32::from_rgb(248, 249, 250))
+                    .inner_margin(10.0)
+                    .rounding(5.0)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new("⚙️ Конфигурация").strong());
+                        ui.add_space(5.0);
                     
-                    ui.label("Базовый секрет 1 (48-bit HEX):");
-                    ui.add(
-                        egui::TextEdit::singleline(&mut self.bs1)
-                            .hint_text("e2d76510bf24")
-                            .font(egui::TextStyle::Monospace)
-                    );
+                        ui.horizontal(|ui| {
+                            ui.label("Алгоритм:");
+                            egui::ComboBox::from_id_source("algorithm")
+                                .selected_text(self.algorithm.title())
+                                .show_ui(ui, |ui| {
+                                    for algorithm in Algorithm::ALL {
+                                        ui.selectable_value(&mut self.algorithm, algorithm, algorithm.title());
+                                    }
+                                });
+                        });
+                    
+                        ui.horizontal(|ui| {
+                            ui.label("Формат пароля:");
+                            egui::ComboBox::from_id_source("format")
+                                .selected_text(self.format.title())
+                                .show_ui(ui, |ui| {
+                                    for option in OutputFormat::ALL {
+                                        let selected = std::mem::discriminant(&option) == std::mem::discriminant(&self.format);
+                                        if ui.selectable_label(selected, option.title()).clicked() && !selected {
+                                            self.format = option;
+                                        }
+                                    }
+                                });
+                            if let OutputFormat::Decimal(digits) = &mut self.format {
+                                ui.add(egui::DragValue::new(digits).clamp_range(MIN_DIGITS..=MAX_DIGITS).suffix(" цифр"));
+                            }
+                        });
                     
-                    ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Режим:");
+                            egui::ComboBox::from_id_source("mode")
+                                .selected_text(self.mode.title())
+                                .show_ui(ui, |ui| {
+                                    for mode in AuthMode::ALL {
+                                        ui.selectable_value(&mut self.mode, mode, mode.title());
+                                    }
+                                });
+                        });
                     
-                    ui.label("Начальная настройка часов (ДД.ММ.ГГГГ ЧЧ:ММ:СС):");
-                    ui.add(
-                        egui::TextEdit::singleline(&mut self.bs3)
-                            .hint_text("06.05.2007 21:24:30")
-                    );
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.auto_copy, "Копировать пароль в буфер");
+                            ui.label("очистить через");
+                            ui.add(egui::DragValue::new(&mut self.clipboard_timeout).clamp_range(0..=clipboard::MAX_TIMEOUT).suffix(" с"))
+                                .on_hover_text("0 - не очищать");
+                        });
                     
-                    ui.add_space(10.0);
+                        if self.mode == AuthMode::Time {
+                            ui.horizontal(|ui| {
+                                ui.label("Шаг времени:");
+                                ui.add(egui::DragValue::new(&mut self.step).clamp_range(1..=MAX_TIME_STEP).suffix(" с"));
+                            });
+                        }
                     
-                    ui.horizontal(|ui| {
-                        if ui.button("💾 Сохранить").clicked() {
-                            self.save_config();
+                        if self.mode == AuthMode::Counter {
+                            ui.horizontal(|ui| {
+                                ui.label("Следующее значение счётчика:");
+                                ui.add(egui::DragValue::new(&mut self.counter));
+                            });
                         }
-                        if ui.button("📂 Загрузить").clicked() {
-                            self.load_config();
+                    
+                        if self.mode == AuthMode::HashChain {
+                            ui.horizontal(|ui| {
+                                ui.label("Хеш цепочки:");
+                                egui::ComboBox::from_id_source("chain_hash")
+                                    .selected_text(self.chain_hash.title())
+                                    .show_ui(ui, |ui| {
+                                        for hash in ChainHash::ALL {
+                                            ui.selectable_value(&mut self.chain_hash, hash, hash.title());
+                                        }
+                                    });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Номер следующего пароля:");
+                                ui.add(egui::DragValue::new(&mut self.sequence));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Seed:");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.seed)
+                                        .hint_text("ke1234")
+                                        .font(egui::TextStyle::Monospace)
+                                );
+                            });
                         }
-                        if ui.button("🗑️ Очистить").clicked() {
-                            self.clear_config();
+                    
+                        if self.mode == AuthMode::Ocra {
+                            ui.label("OCRASuite:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.suite)
+                                    .hint_text(DEFAULT_SUITE)
+                                    .font(egui::TextStyle::Monospace)
+                            );
                         }
-                    });
-                });
-            
-            ui.add_space(15.0);
-            
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(40, 167, 69))
-                .inner_margin(15.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
                     
-                    ui.vertical_centered(|ui| {
-                        ui.label(egui::RichText::new("Введите PIN-код (4 HEX):").strong());
                         ui.add_space(5.0);
-                        
+                    
+                        let reveal = ui.horizontal(|ui| {
+                            ui.label(format!("Базовый секрет 1 ({}-bit HEX):", self.algorithm.bs1_hex_len() * 4));
+                            hold_to_reveal(ui)
+                        }).inner;
                         ui.add(
-                            egui::TextEdit::singleline(&mut self.pin)
-                                .hint_text("AAAA")
-                                .font(egui::TextStyle::Heading)
-                                .char_limit(4)
+                            egui::TextEdit::singleline(&mut *self.bs1)
+                                .hint_text("e2d76510bf24")
+                                .font(egui::TextStyle::Monospace)
+                                .password(!reveal)
                         );
-                    });
                     
-                    ui.add_space(10.0);
+                        ui.add_space(5.0);
                     
-                    if ui.add_sized([ui.available_width(), 40.0], 
-                        egui::Button::new(egui::RichText::new("🔑 Получить пароль").strong())
-                    ).clicked() {
-                        self.generate_password();
-                    }
+                        ui.label("Начальная настройка часов (ДД.ММ.ГГГГ ЧЧ:ММ:СС):");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.bs3)
+                                .hint_text("06.05.2007 21:24:30")
+                        );
                     
-                    ui.add_space(10.0);
+                        ui.add_space(10.0);
                     
-                    egui::Frame::none()
-                        .fill(egui::Color32::from_rgba_premultiplied(255, 255, 255, 50))
-                        .inner_margin(10.0)
-                        .rounding(5.0)
-                        .show(ui, |ui| {
-                            ui.vertical_centered(|ui| {
-                                if self.password.is_empty() {
-                                    ui.label(egui::RichText::new("Пароль появится здесь").color(egui::Color32::from_gray(200)));
-                                } else {
-                                    ui.label(egui::RichText::new(&self.password)
-                                        .font(egui::FontId::monospace(20.0))
-                                        .strong()
-                                    );
-                                }
-                            });
+                        ui.horizontal(|ui| {
+                            if ui.button("💾 Сохранить").clicked() {
+                                self.save_config();
+                            }
+                            if ui.button("📂 Загрузить").clicked() {
+                                self.load_config();
+                            }
+                            if ui.button("🗑️ Очистить").clicked() {
+                                self.clear_config();
+                            }
                         });
-                });
+                    });
             
-            ui.add_space(15.0);
+                ui.add_space(15.0);
             
-            if !self.error_message.is_empty() {
                 egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(248, 215, 218))
-                    .inner_margin(10.0)
+                    .fill(egui::Color32::from_rgb(40, 167, 69))
+                    .inner_margin(15.0)
                     .rounding(5.0)
                     .show(ui, |ui| {
-                        ui.label(egui::RichText::new(&self.error_message).color(egui::Color32::from_rgb(114, 28, 36)));
+                        ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+                    
+                        if self.mode.uses_challenge() {
+                            ui.label("Запрос сервера:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.challenge)
+                                    .hint_text("12345678")
+                                    .font(egui::TextStyle::Monospace)
+                            );
+                            ui.label("Данные транзакции (если запрос получен по ним):");
+                            ui.add(egui::TextEdit::singleline(&mut self.transaction));
+                            ui.add_space(10.0);
+                        }
+                    
+                        ui.vertical_centered(|ui| {
+                            let reveal = ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new("Введите PIN-код (4 HEX):").strong());
+                                hold_to_reveal(ui)
+                            }).inner;
+                            ui.add_space(5.0);
+                        
+                            ui.add(
+                                egui::TextEdit::singleline(&mut *self.pin)
+                                    .hint_text("AAAA")
+                                    .font(egui::TextStyle::Heading)
+                                    .char_limit(4)
+                                    .password(!reveal)
+                            );
+                        });
+                    
+                        ui.add_space(10.0);
+                    
+                        if ui.add_sized([ui.available_width(), 40.0], 
+                            egui::Button::new(egui::RichText::new("🔑 Получить пароль").strong())
+                        ).clicked() {
+                            self.generate_password();
+                        }
+                    
+                        ui.add_space(10.0);
+                    
+                        egui::Frame::none()
+                            .fill(egui::Color32::from_rgba_premultiplied(255, 255, 255, 50))
+                            .inner_margin(10.0)
+                            .rounding(5.0)
+                            .show(ui, |ui| {
+                                ui.vertical_centered(|ui| {
+                                    if self.password.is_empty() {
+                                        ui.label(egui::RichText::new("Пароль появится здесь").color(egui::Color32::from_gray(200)));
+                                    } else {
+                                        ui.label(egui::RichText::new(self.password.as_str())
+                                            .font(egui::FontId::monospace(20.0))
+                                            .strong()
+                                        );
+                                        if ui.button("📋 Копировать").clicked() {
+                                            self.copy_password();
+                                        }
+                                        if let Some(remaining) = self.clipboard.remaining() {
+                                            ui.label(format!("Буфер обмена очистится через {} с", remaining.as_secs() + 1));
+                                        }
+                                    }
+                                });
+                            });
                     });
-                ui.add_space(10.0);
-            }
             
-            if !self.success_message.is_empty() {
-                egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(212, 237, 218))
-                    .inner_margin(10.0)
-                    .rounding(5.0)
-                    .show(ui, |ui| {
-                        ui.label(egui::RichText::new(&self.success_message).color(egui::Color32::from_rgb(21, 87, 36)));
-                    });
-                ui.add_space(10.0);
-            }
+                ui.add_space(15.0);
             
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(248, 249, 250))
-                .inner_margin(10.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    ui.label(egui::RichText::new("Служебная информация:").strong().color(egui::Color32::from_gray(100)));
-                    ui.add_space(5.0);
+                if self.mode == AuthMode::Time {
+                    egui::CollapsingHeader::new("🖨 Список паролей на период").show(ui, |ui| {
+                        ui.label("Начало и конец (ДД.ММ.ГГГГ ЧЧ:ММ:СС):");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.list_from).hint_text("01.11.2026 08:00:00").desired_width(160.0));
+                            ui.label("-");
+                            ui.add(egui::TextEdit::singleline(&mut self.list_to).hint_text("01.11.2026 20:00:00").desired_width(160.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Интервал:");
+                            ui.add(egui::DragValue::new(&mut self.list_slot).clamp_range(1..=MAX_SLOT).suffix(" с"));
+                        });
+                        ui.label("Используется PIN, введённый выше.");
+                        ui.horizontal(|ui| {
+                            if ui.button(format!("💾 {}", LIST_CSV_FILE)).clicked() {
+                                self.export_list(false);
+                            }
+                            if ui.button(format!("💾 {}", LIST_PDF_FILE)).clicked() {
+                                self.export_list(true);
+                            }
+                        });
+                    });
                     
-                    ui.label(format!("Базовый секрет 1: {}", if self.bs1.is_empty() { "-" } else { &self.bs1 }));
-                    ui.label(format!("Базовый секрет 2 (ключ): {}", if self.bs2.is_empty() { "-" } else { &self.bs2 }));
-                    ui.label(format!("Начальная настройка: {}", if self.bs3.is_empty() { "-" } else { &self.bs3 }));
-                    ui.label(format!("Показание часов (блок): {}", if self.time_block.is_empty() { "-" } else { &self.time_block }));
-                });
+                    ui.add_space(15.0);
+                }
+            
+                if !self.error_message.is_empty() {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(248, 215, 218))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(&self.error_message).color(egui::Color32::from_rgb(114, 28, 36)));
+                        });
+                    ui.add_space(10.0);
+                }
+            
+                if !self.warning_message.is_empty() {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(255, 243, 205))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(&self.warning_message).color(egui::Color32::from_rgb(133, 100, 4)));
+                        });
+                    ui.add_space(10.0);
+                }
+
+                // Секреты могут попасть в файл подкачки
+                if let Some(e) = secret::lock_error() {
+                    ui.label(egui::RichText::new(format!("⚠ {}", e)).small().color(egui::Color32::from_rgb(133, 100, 4)));
+                    ui.add_space(10.0);
+                }
+            
+                if !self.success_message.is_empty() {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(212, 237, 218))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(&self.success_message).color(egui::Color32::from_rgb(21, 87, 36)));
+                        });
+                    ui.add_space(10.0);
+                }
+            
+                #[cfg(feature = "diagnostics")]
+                self.diagnostics(ui);
+            });
         });
     }
 }
 
+// BS2 = PIN + BS1 используется как ключ шифра: слабые ключи недопустимы,
+// о полуслабых возвращается предупреждение
+fn derive_bs2(pin: &str, bs1: &str, algorithm: Algorithm) -> Result<(SecretString, Option<String>), String> {
+    let pin = pin.trim();
+    if pin.len() != 4 || !pin.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("PIN: 4 HEX символа (0-9, A-F)!".to_string());
+    }
+    
+    let bs1 = bs1.trim();
+    let bs1_len = algorithm.bs1_hex_len();
+    if bs1.len() != bs1_len || !bs1.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("BS1 ({}): {} HEX символов!", algorithm.title(), bs1_len));
+    }
+    
+    // Сразу в буфер ключа, без промежуточных to_uppercase() и format!
+    let mut bs2 = secret::buffer(algorithm.key_hex_len());
+    bs2.extend(pin.chars().chain(bs1.chars()).map(|c| c.to_ascii_uppercase()));
+    
+    match key_weakness(algorithm, &bs2) {
+        Some(KeyWeakness::Weak) => Err(format!("PIN + BS1 дают слабый ключ {}! Смените PIN", algorithm.title())),
+        Some(KeyWeakness::SemiWeak) => Ok((
+            bs2,
+            Some(format!("⚠ PIN + BS1 дают полуслабый ключ {}, рекомендуется сменить PIN", algorithm.title())),
+        )),
+        None => Ok((bs2, None)),
+    }
+}
+
+// Кнопка 👁: секрет виден, пока она нажата
+fn hold_to_reveal(ui: &mut egui::Ui) -> bool {
+    ui.small_button("👁").on_hover_text("Удерживайте, чтобы показать").is_pointer_button_down_on()
+}
+
+// Маска той же длины, что и секрет
+#[cfg(feature = "diagnostics")]
+fn mask(secret: &str) -> String {
+    "•".repeat(secret.chars().count())
+}
+
+// Моноширинный шрифт egui содержит кириллицу и встраивается в PDF
+fn sheet_font() -> Vec<u8> {
+    egui::FontDefinitions::default()
+        .font_data
+        .get("Hack")
+        .map(|data| data.font.to_vec())
+        .unwrap_or_default()
+}
+
 fn parse_datetime(s: &str) -> Result<NaiveDateTime, String> {
     let parts: Vec<&str> = s.split_whitespace().collect();
     if parts.len() != 2 {
@@ -315,38 +845,13 @@ fn parse_datetime(s: &str) -> Result<NaiveDateTime, String> {
     ).map_err(|e| format!("Ошибка: {}", e))
 }
 
-fn des_encrypt(data_hex: &str, key_hex: &str) -> Result<String, String> {
-    let data_bytes = decode(data_hex).map_err(|e| format!("Данные: {}", e))?;
-    let key_bytes = decode(key_hex).map_err(|e| format!("Ключ: {}", e))?;
-    
-    if data_bytes.len() != 8 {
-        return Err(format!("Данные: 8 байт, получено {}", data_bytes.len()));
-    }
-    
-    if key_bytes.len() != 8 {
-        return Err(format!("Ключ: 8 байт, получено {}", key_bytes.len()));
-    }
-    
-    let cipher = Des::new_from_slice(&key_bytes)
-        .map_err(|e| format!("Cipher: {}", e))?;
-    
-    // ✅ Правильное преобразование
-    let mut block = [0u8; 8];
-    block.copy_from_slice(&data_bytes);
-    
-    let mut block_array = block.into();
-    cipher.encrypt_block(&mut block_array);
-    
-    Ok(encode(block_array).to_uppercase())
-}
-
-fn read_file_windows1251<P: AsRef<Path>>(path: P) -> Result<String, std::io::Error> {
-    let bytes = fs::read(path)?;
-    let (decoded, _, _) = WINDOWS_1251.decode(&bytes);
-    Ok(decoded.into_owned())
+// Файлы клиента читаются с автоопределением кодировки: Windows-1251
+// исходного Client.exe или UTF-8 (в том числе с BOM после Блокнота)
+fn read_text_file<P: AsRef<Path>>(path: P) -> Result<String, String> {
+    TextEncoding::Auto.read(path).map(|decoded| decoded.text)
 }
 
-fn write_file_windows1251<P: AsRef<Path>>(path: P, content: &str) -> Result<(), std::io::Error> {
-    let (encoded, _, _) = WINDOWS_1251.encode(content);
-    fs::write(path, encoded.as_ref())
+// Запись в Windows-1251, если текст в ней представим, иначе в UTF-8
+fn write_text_file<P: AsRef<Path>>(path: P, content: &str) -> Result<(), String> {
+    TextEncoding::Auto.write(path, content)
 }
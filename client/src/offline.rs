@@ -0,0 +1,159 @@
+use chrono::{Duration, NaiveDateTime};
+use otp_core::cipher::{encrypt, slot_start, time_block, MAX_SLOT};
+use otp_core::format::format_password;
+use otp_core::sheet::Sheet;
+
+use crate::profile::Profile;
+
+// Печатный список паролей для работы без компьютера: пароль на начало
+// каждого интервала (слота), интервалы выровнены по часам. Сервер принимает
+// такой пароль до конца интервала у пользователей с атрибутом slot=.
+pub const DEFAULT_SLOT: u64 = 60;
+const MAX_ENTRIES: usize = 10_000;
+
+pub struct PasswordList {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub slot: u64,
+    pub entries: Vec<(NaiveDateTime, String)>,
+}
+
+pub fn generate(
+    profile: &Profile,
+    bs2: &str,
+    epoch: NaiveDateTime,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    slot: u64,
+) -> Result<PasswordList, String> {
+    if slot == 0 || slot > MAX_SLOT {
+        return Err(format!("Интервал: от 1 до {} секунд", MAX_SLOT));
+    }
+    if to < from {
+        return Err("Конец периода раньше начала".to_string());
+    }
+
+    let step = Duration::seconds(slot as i64);
+    let mut time = slot_start(from, slot);
+    let mut entries = Vec::new();
+
+    while time <= to {
+        if entries.len() == MAX_ENTRIES {
+            return Err(format!("Не более {} паролей, увеличьте интервал", MAX_ENTRIES));
+        }
+
+        let elapsed = (time.and_utc().timestamp() - epoch.and_utc().timestamp()).max(0) as u64;
+        let block = time_block(profile.algorithm, elapsed / profile.step.max(1));
+        let password = encrypt(profile.algorithm, &block, bs2)
+            .and_then(|hex| format_password(profile.format, &hex))
+            .map_err(|e| format!("Шифрование: {}", e))?;
+
+        entries.push((time, password));
+        time += step;
+    }
+
+    Ok(PasswordList { from, to, slot, entries })
+}
+
+impl PasswordList {
+    fn time_format(&self) -> &'static str {
+        if self.slot.is_multiple_of(60) {
+            "%d.%m.%Y %H:%M"
+        } else {
+            "%d.%m.%Y %H:%M:%S"
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = "Время;Пароль\n".to_string();
+        for (time, password) in &self.entries {
+            csv.push_str(&format!("{};{}\n", time.format(self.time_format()), password));
+        }
+        csv
+    }
+
+    pub fn to_sheet(&self) -> Sheet {
+        let mut lines = vec![
+            format!("Интервал: {} с. Пароль действует с указанного времени до следующей строки.", self.slot),
+            String::new(),
+        ];
+        lines.extend(
+            self.entries
+                .iter()
+                .map(|(time, password)| format!("{}   {}", time.format(self.time_format()), password)),
+        );
+
+        Sheet {
+            title: format!(
+                "Одноразовые пароли {} - {}",
+                self.from.format("%d.%m.%Y %H:%M"),
+                self.to.format("%d.%m.%Y %H:%M")
+            ),
+            lines,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use otp_core::cipher::Algorithm;
+    use otp_core::format::OutputFormat;
+
+    const BS2: &str = "AAAAE2D76510BF24";
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%d.%m.%Y %H:%M:%S").unwrap()
+    }
+
+    fn expected(epoch: NaiveDateTime, at: NaiveDateTime, step: u64) -> String {
+        let elapsed = (at - epoch).num_seconds() as u64 / step;
+        let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, elapsed), BS2).unwrap();
+        format_password(OutputFormat::Hex, &hex).unwrap()
+    }
+
+    #[test]
+    fn covers_period_from_aligned_slot() {
+        let epoch = time("06.05.2007 21:24:30");
+        let list = generate(&Profile::default(), BS2, epoch, time("01.01.2024 12:00:30"), time("01.01.2024 12:10:00"), 60).unwrap();
+
+        // Первый интервал начинается до начала периода, последний - на его конце
+        assert_eq!(list.entries.len(), 11);
+        assert_eq!(list.entries[0].0, time("01.01.2024 12:00:00"));
+        assert_eq!(list.entries[10].0, time("01.01.2024 12:10:00"));
+        for (at, password) in &list.entries {
+            assert_eq!(*password, expected(epoch, *at, 1));
+        }
+        assert_eq!(list.to_csv().lines().count(), 12);
+    }
+
+    #[test]
+    fn follows_profile_step() {
+        let epoch = time("06.05.2007 21:24:30");
+        let profile = Profile { step: 30, ..Default::default() };
+        let list = generate(&profile, BS2, epoch, time("01.01.2024 12:00:00"), time("01.01.2024 12:05:00"), 300).unwrap();
+        assert_eq!(list.entries.len(), 2);
+        assert_eq!(list.entries[1].1, expected(epoch, time("01.01.2024 12:05:00"), 30));
+    }
+
+    #[test]
+    fn rejects_bad_slot_and_period() {
+        let epoch = time("06.05.2007 21:24:30");
+        let (from, to) = (time("01.01.2024 12:00:00"), time("01.01.2024 13:00:00"));
+        assert!(generate(&Profile::default(), BS2, epoch, from, to, 0).is_err());
+        assert!(generate(&Profile::default(), BS2, epoch, from, to, MAX_SLOT + 1).is_err());
+        assert!(generate(&Profile::default(), BS2, epoch, to, from, 60).is_err());
+        assert!(generate(&Profile::default(), BS2, epoch, from, to, MAX_SLOT).is_ok());
+    }
+
+    #[test]
+    fn enforces_max_entries() {
+        let epoch = time("06.05.2007 21:24:30");
+        let from = time("01.01.2024 00:00:00");
+        let last = from + Duration::seconds((MAX_ENTRIES as i64 - 1) * 60);
+
+        let list = generate(&Profile::default(), BS2, epoch, from, last, 60).unwrap();
+        assert_eq!(list.entries.len(), MAX_ENTRIES);
+        assert!(generate(&Profile::default(), BS2, epoch, from, last + Duration::seconds(60), 60).is_err());
+    }
+}
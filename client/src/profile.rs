@@ -0,0 +1,148 @@
+use otp_core::cipher::{Algorithm, MAX_TIME_STEP};
+use otp_core::format::OutputFormat;
+use otp_core::mode::AuthMode;
+use otp_core::ocra::DEFAULT_SUITE;
+use otp_core::skey::{self, ChainHash};
+
+use crate::clipboard;
+
+// profile.txt дополняет bs1.txt и bs3.txt настройками, которых не было в
+// оригинальном Client.exe. Строки вида ключ=значение, # - комментарий.
+// Если файла нет, используется исходная схема (DES).
+pub const PROFILE_FILE: &str = "profile.txt";
+
+const KEY_ALGORITHM: &str = "algorithm";
+const KEY_FORMAT: &str = "format";
+const KEY_MODE: &str = "mode";
+const KEY_SUITE: &str = "suite";
+const KEY_COUNTER: &str = "counter";
+const KEY_STEP: &str = "step";
+const KEY_HASH: &str = "hash";
+const KEY_SEED: &str = "seed";
+const KEY_SEQUENCE: &str = "seq";
+const KEY_CLIPBOARD: &str = "clipboard";
+const KEY_AUTO_COPY: &str = "autocopy";
+
+pub struct Profile {
+    pub algorithm: Algorithm,
+    pub format: OutputFormat,
+    pub mode: AuthMode,
+    pub suite: String,
+    pub counter: u64,
+    pub step: u64,
+    pub chain_hash: ChainHash,
+    pub seed: String,
+    pub sequence: u64,
+    // Секунды до очистки буфера обмена, 0 - не очищать
+    pub clipboard_timeout: u64,
+    // Копировать пароль в буфер сразу после получения
+    pub auto_copy: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::Des,
+            format: OutputFormat::Hex,
+            mode: AuthMode::Time,
+            suite: DEFAULT_SUITE.to_string(),
+            counter: 0,
+            step: 1,
+            chain_hash: ChainHash::Md5,
+            seed: String::new(),
+            sequence: skey::DEFAULT_SEQUENCE,
+            clipboard_timeout: clipboard::DEFAULT_TIMEOUT,
+            auto_copy: false,
+        }
+    }
+}
+
+impl Profile {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut profile = Self::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("строка {}: ожидается ключ=значение", index + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                KEY_ALGORITHM => {
+                    profile.algorithm = Algorithm::parse(value)
+                        .ok_or_else(|| format!("строка {}: неизвестный алгоритм {}", index + 1, value))?;
+                }
+                KEY_FORMAT => {
+                    profile.format = OutputFormat::parse(value)
+                        .ok_or_else(|| format!("строка {}: неизвестный формат пароля {}", index + 1, value))?;
+                }
+                KEY_MODE => {
+                    profile.mode = AuthMode::parse(value)
+                        .ok_or_else(|| format!("строка {}: неизвестный режим {}", index + 1, value))?;
+                }
+                KEY_SUITE => profile.suite = value.to_string(),
+                KEY_COUNTER => {
+                    profile.counter = value
+                        .parse()
+                        .map_err(|_| format!("строка {}: неверное значение счётчика {}", index + 1, value))?;
+                }
+                KEY_STEP => {
+                    profile.step = value
+                        .parse()
+                        .ok()
+                        .filter(|step| (1..=MAX_TIME_STEP).contains(step))
+                        .ok_or_else(|| format!("строка {}: шаг времени от 1 до {} секунд", index + 1, MAX_TIME_STEP))?;
+                }
+                KEY_HASH => {
+                    profile.chain_hash = ChainHash::parse(value)
+                        .ok_or_else(|| format!("строка {}: неизвестный хеш цепочки {}", index + 1, value))?;
+                }
+                KEY_SEED => profile.seed = value.to_string(),
+                KEY_SEQUENCE => {
+                    profile.sequence = value
+                        .parse()
+                        .map_err(|_| format!("строка {}: неверный номер в цепочке {}", index + 1, value))?;
+                }
+                KEY_CLIPBOARD => {
+                    profile.clipboard_timeout = value
+                        .parse()
+                        .ok()
+                        .filter(|timeout| *timeout <= clipboard::MAX_TIMEOUT)
+                        .ok_or_else(|| format!("строка {}: очистка буфера от 0 до {} секунд", index + 1, clipboard::MAX_TIMEOUT))?;
+                }
+                KEY_AUTO_COPY => {
+                    profile.auto_copy = match value {
+                        "1" => true,
+                        "0" => false,
+                        _ => return Err(format!("строка {}: autocopy - 0 или 1", index + 1)),
+                    };
+                }
+                _ => return Err(format!("строка {}: неизвестный параметр {}", index + 1, key)),
+            }
+        }
+
+        Ok(profile)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n{}={}\n",
+            KEY_ALGORITHM, self.algorithm.name(),
+            KEY_FORMAT, self.format.name(),
+            KEY_MODE, self.mode.name(),
+            KEY_SUITE, self.suite,
+            KEY_COUNTER, self.counter,
+            KEY_STEP, self.step,
+            KEY_HASH, self.chain_hash.name(),
+            KEY_SEED, self.seed,
+            KEY_SEQUENCE, self.sequence,
+            KEY_CLIPBOARD, self.clipboard_timeout,
+            KEY_AUTO_COPY, u8::from(self.auto_copy)
+        )
+    }
+}
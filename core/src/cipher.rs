@@ -0,0 +1,193 @@
+use aes::Aes128;
+use chrono::NaiveDateTime;
+use des::cipher::{BlockEncrypt, KeyInit};
+use des::{Des, TdesEde2, TdesEde3};
+use hex::{decode, encode};
+use sha2::{Digest, Sha256};
+
+use crate::secret;
+
+// Алгоритм шифрования блока времени. DES - исходная схема из методички,
+// остальные варианты используют ту же конструкцию с более длинным ключом
+// BS2 = PIN + BS1.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Algorithm {
+    #[default]
+    Des,
+    TdesEde2,
+    TdesEde3,
+    Aes128,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::Des,
+        Algorithm::TdesEde2,
+        Algorithm::TdesEde3,
+        Algorithm::Aes128,
+    ];
+
+    // Имя для database.txt и profile.txt
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Des => "des",
+            Algorithm::TdesEde2 => "3des2",
+            Algorithm::TdesEde3 => "3des3",
+            Algorithm::Aes128 => "aes128",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Algorithm::Des => "DES",
+            Algorithm::TdesEde2 => "3DES (EDE2)",
+            Algorithm::TdesEde3 => "3DES (EDE3)",
+            Algorithm::Aes128 => "AES-128",
+        }
+    }
+
+    pub fn key_len(self) -> usize {
+        match self {
+            Algorithm::Des => 8,
+            Algorithm::TdesEde2 => 16,
+            Algorithm::TdesEde3 => 24,
+            Algorithm::Aes128 => 16,
+        }
+    }
+
+    pub fn block_len(self) -> usize {
+        match self {
+            Algorithm::Aes128 => 16,
+            _ => 8,
+        }
+    }
+
+    // Длина BS2 в HEX символах
+    pub fn key_hex_len(self) -> usize {
+        self.key_len() * 2
+    }
+
+    // BS1 = BS2 без 4 HEX символов PIN
+    pub fn bs1_hex_len(self) -> usize {
+        self.key_hex_len() - 4
+    }
+}
+
+// Шаг квантования времени: блок равен elapsed / step, шаг 1 - исходная схема
+pub const MAX_TIME_STEP: u64 = 3600;
+
+// Блок времени: число секунд (шагов) в виде big-endian числа длиной в блок шифра
+pub fn time_block(algorithm: Algorithm, elapsed: u64) -> String {
+    format!("{:0width$X}", elapsed, width = algorithm.block_len() * 2)
+}
+
+// Интервал печатного списка паролей, секунд: пароль вычислен на начало
+// интервала, сервер принимает его до конца интервала
+pub const MAX_SLOT: u64 = 3600;
+
+// Начало интервала, в который попадает время; интервалы выровнены по часам
+// одинаково в списке клиента и при проверке на сервере
+pub fn slot_start(time: NaiveDateTime, slot: u64) -> NaiveDateTime {
+    let offset = time.and_utc().timestamp().rem_euclid(slot.max(1) as i64);
+    time - chrono::Duration::seconds(offset)
+}
+
+// Запрос для режима запрос-ответ: блок шифра из первых байтов seed
+// (случайных или хеша данных транзакции)
+pub fn challenge_block(algorithm: Algorithm, seed: &[u8]) -> String {
+    encode(&seed[..algorithm.block_len().min(seed.len())]).to_uppercase()
+}
+
+pub fn challenge_from_transaction(algorithm: Algorithm, transaction: &str) -> String {
+    challenge_block(algorithm, &Sha256::digest(transaction.trim().as_bytes()))
+}
+
+// Пароль - первые 8 байт зашифрованного блока (16 HEX символов) для любого
+// алгоритма, поэтому формат ввода на сервере не зависит от шифра
+pub fn encrypt(algorithm: Algorithm, data_hex: &str, key_hex: &str) -> Result<String, String> {
+    let data_bytes = decode(data_hex).map_err(|e| format!("Данные: {}", e))?;
+    // Ключ затирается после шифрования, расписание ключей - в Drop шифра
+    let key_bytes = secret::decode_hex(key_hex).map_err(|e| format!("Ключ: {}", e))?;
+
+    if data_bytes.len() != algorithm.block_len() {
+        return Err(format!("Данные: {} байт, получено {}", algorithm.block_len(), data_bytes.len()));
+    }
+
+    if key_bytes.len() != algorithm.key_len() {
+        return Err(format!("Ключ: {} байт, получено {}", algorithm.key_len(), key_bytes.len()));
+    }
+
+    let mut block = data_bytes;
+    match algorithm {
+        Algorithm::Des => encrypt_with::<Des>(&key_bytes, &mut block)?,
+        Algorithm::TdesEde2 => encrypt_with::<TdesEde2>(&key_bytes, &mut block)?,
+        Algorithm::TdesEde3 => encrypt_with::<TdesEde3>(&key_bytes, &mut block)?,
+        Algorithm::Aes128 => encrypt_with::<Aes128>(&key_bytes, &mut block)?,
+    }
+
+    Ok(encode(&block[..8]).to_uppercase())
+}
+
+fn encrypt_with<C: BlockEncrypt + KeyInit>(key: &[u8], block: &mut [u8]) -> Result<(), String> {
+    let cipher = C::new_from_slice(key).map_err(|e| format!("Cipher: {}", e))?;
+    cipher.encrypt_block(block.into());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "0123456789ABCDEF";
+    const DES_KEY: &str = "133457799BBCDFF1";
+    const DES_RESULT: &str = "85E813540F0AB405";
+
+    #[test]
+    fn des_known_answer() {
+        assert_eq!(encrypt(Algorithm::Des, BLOCK, DES_KEY).unwrap(), DES_RESULT);
+    }
+
+    // NIST SP 800-67, пример: первый блок "The qufck brown fox jump"
+    #[test]
+    fn tdes_ede3_known_answer() {
+        let key = "0123456789ABCDEF23456789ABCDEF01456789ABCDEF0123";
+        assert_eq!(encrypt(Algorithm::TdesEde3, "5468652071756663", key).unwrap(), "A826FD8CE53B855F");
+    }
+
+    // 3DES с одинаковыми подключами совпадает с одиночным DES
+    #[test]
+    fn tdes_with_equal_subkeys_is_des() {
+        let ede2 = DES_KEY.repeat(2);
+        let ede3 = DES_KEY.repeat(3);
+        assert_eq!(encrypt(Algorithm::TdesEde2, BLOCK, &ede2).unwrap(), DES_RESULT);
+        assert_eq!(encrypt(Algorithm::TdesEde3, BLOCK, &ede3).unwrap(), DES_RESULT);
+        assert_ne!(encrypt(Algorithm::TdesEde2, BLOCK, &format!("{}0123456789ABCDEF", DES_KEY)).unwrap(), DES_RESULT);
+    }
+
+    // FIPS 197, приложения B и C.1: пароль - первые 8 байт блока
+    #[test]
+    fn aes128_known_answer() {
+        let result = encrypt(Algorithm::Aes128, "3243F6A8885A308D313198A2E0370734", "2B7E151628AED2A6ABF7158809CF4F3C");
+        assert_eq!(result.unwrap(), "3925841D02DC09FB");
+        let result = encrypt(Algorithm::Aes128, "00112233445566778899AABBCCDDEEFF", "000102030405060708090A0B0C0D0E0F");
+        assert_eq!(result.unwrap(), "69C4E0D86A7B0430");
+    }
+
+    #[test]
+    fn rejects_wrong_lengths() {
+        assert!(encrypt(Algorithm::Des, "0123", DES_KEY).is_err());
+        assert!(encrypt(Algorithm::Aes128, BLOCK, "000102030405060708090A0B0C0D0E0F").is_err());
+        assert!(encrypt(Algorithm::TdesEde3, BLOCK, &DES_KEY.repeat(2)).is_err());
+        assert!(encrypt(Algorithm::Des, BLOCK, "133457799BBCDFFZ").is_err());
+    }
+
+    #[test]
+    fn time_block_fills_cipher_block() {
+        assert_eq!(time_block(Algorithm::Des, 0x1234), "0000000000001234");
+        assert_eq!(time_block(Algorithm::Aes128, 0x1234), "00000000000000000000000000001234");
+    }
+}
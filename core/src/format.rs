@@ -0,0 +1,197 @@
+use subtle::ConstantTimeEq;
+
+// Формат отображения пароля. Исходный пароль - 64 бита (16 HEX символов),
+// остальные форматы получаются из него и на сервере сравниваются после
+// нормализации ввода (регистр, разделители, похожие символы base32).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OutputFormat {
+    #[default]
+    Hex,
+    Grouped,
+    Decimal(u32),
+    Base32,
+}
+
+pub const MIN_DIGITS: u32 = 6;
+pub const MAX_DIGITS: u32 = 10;
+
+pub const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [
+        OutputFormat::Hex,
+        OutputFormat::Grouped,
+        OutputFormat::Decimal(8),
+        OutputFormat::Base32,
+    ];
+
+    // Имя для database.txt и profile.txt: hex, grouped, dec6..dec10, base32
+    pub fn name(self) -> String {
+        match self {
+            OutputFormat::Hex => "hex".to_string(),
+            OutputFormat::Grouped => "grouped".to_string(),
+            OutputFormat::Decimal(digits) => format!("dec{}", digits),
+            OutputFormat::Base32 => "base32".to_string(),
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "hex" => Some(OutputFormat::Hex),
+            "grouped" => Some(OutputFormat::Grouped),
+            "base32" => Some(OutputFormat::Base32),
+            other => {
+                let digits: u32 = other.strip_prefix("dec")?.parse().ok()?;
+                (MIN_DIGITS..=MAX_DIGITS).contains(&digits).then_some(OutputFormat::Decimal(digits))
+            }
+        }
+    }
+
+    pub fn title(self) -> String {
+        match self {
+            OutputFormat::Hex => "HEX (16 символов)".to_string(),
+            OutputFormat::Grouped => "HEX по группам (0200-0000-6129-0047)".to_string(),
+            OutputFormat::Decimal(digits) => format!("Десятичный ({} цифр)", digits),
+            OutputFormat::Base32 => "Base32 Crockford (13 символов)".to_string(),
+        }
+    }
+
+    pub fn is_decimal(self) -> bool {
+        matches!(self, OutputFormat::Decimal(_))
+    }
+
+    // Длина пароля без разделителей (после normalize_password)
+    pub fn length(self) -> usize {
+        match self {
+            OutputFormat::Hex | OutputFormat::Grouped => 16,
+            OutputFormat::Decimal(digits) => digits as usize,
+            OutputFormat::Base32 => 13,
+        }
+    }
+}
+
+pub fn format_password(format: OutputFormat, password_hex: &str) -> Result<String, String> {
+    let value = u64::from_str_radix(password_hex, 16).map_err(|e| format!("Пароль: {}", e))?;
+
+    Ok(match format {
+        OutputFormat::Hex => format!("{:016X}", value),
+        OutputFormat::Grouped => {
+            let hex = format!("{:016X}", value);
+            let groups: Vec<&str> = (0..4).map(|i| &hex[i * 4..i * 4 + 4]).collect();
+            groups.join("-")
+        }
+        OutputFormat::Decimal(digits) => {
+            format!("{:0width$}", truncate(value) % 10u64.pow(digits), width = digits as usize)
+        }
+        OutputFormat::Base32 => (0..13)
+            .rev()
+            .map(|i| CROCKFORD[((value >> (i * 5)) & 0x1F) as usize] as char)
+            .collect(),
+    })
+}
+
+// Приведение введённого пароля к каноническому виду формата: без
+// разделителей, в верхнем регистре, с заменой I/L на 1 и O на 0 для base32
+pub fn normalize_password(format: OutputFormat, input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_' && *c != '.')
+        .map(|c| c.to_ascii_uppercase())
+        .map(|c| match (format, c) {
+            (OutputFormat::Base32, 'I' | 'L') => '1',
+            (OutputFormat::Base32, 'O') => '0',
+            _ => c,
+        })
+        .collect()
+}
+
+// Сравнение за постоянное время: время не зависит от позиции первого
+// несовпадающего символа
+pub fn passwords_equal(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+// Динамическое усечение как в HOTP (RFC 4226), адаптированное к 8 байтам:
+// смещение берётся из двух младших битов последнего байта
+fn truncate(value: u64) -> u64 {
+    let bytes = value.to_be_bytes();
+    let offset = (bytes[7] & 0x03) as usize;
+    let chunk = [bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]];
+    (u32::from_be_bytes(chunk) & 0x7FFF_FFFF) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: [u64; 5] = [0, 1, 0x0200_0000_6129_0047, 0x7FFF_FFFF_FFFF_FFFF, u64::MAX];
+
+    fn formats() -> Vec<OutputFormat> {
+        let decimal = (MIN_DIGITS..=MAX_DIGITS).map(OutputFormat::Decimal);
+        [OutputFormat::Hex, OutputFormat::Grouped, OutputFormat::Base32].into_iter().chain(decimal).collect()
+    }
+
+    fn from_base32(text: &str) -> u64 {
+        text.bytes().fold(0, |value, c| value << 5 | CROCKFORD.iter().position(|&d| d == c).unwrap() as u64)
+    }
+
+    #[test]
+    fn length_matches_format() {
+        for format in formats() {
+            for value in VALUES {
+                let password = format_password(format, &format!("{:016X}", value)).unwrap();
+                assert_eq!(normalize_password(format, &password).len(), format.length(), "{:?} {:X}", format, value);
+            }
+        }
+        assert_eq!(OutputFormat::Decimal(MIN_DIGITS).length(), 6);
+        assert_eq!(OutputFormat::Decimal(MAX_DIGITS).length(), 10);
+    }
+
+    // Пароль восстанавливается из нормализованного ввода; десятичный -
+    // усечение, которое сервер вычисляет так же
+    #[test]
+    fn round_trip() {
+        for value in VALUES {
+            let hex = format!("{:016X}", value);
+            let grouped = format_password(OutputFormat::Grouped, &hex).unwrap();
+            assert_eq!(normalize_password(OutputFormat::Grouped, &grouped), hex);
+            assert_eq!(normalize_password(OutputFormat::Hex, &hex.to_lowercase()), hex);
+
+            let base32 = format_password(OutputFormat::Base32, &hex).unwrap();
+            assert_eq!(from_base32(&normalize_password(OutputFormat::Base32, &base32.to_lowercase())), value);
+
+            for digits in [MIN_DIGITS, MAX_DIGITS] {
+                let decimal = format_password(OutputFormat::Decimal(digits), &hex).unwrap();
+                assert_eq!(decimal.parse::<u64>().unwrap(), truncate(value) % 10u64.pow(digits));
+                assert!(decimal.chars().all(|c| c.is_ascii_digit()));
+            }
+        }
+    }
+
+    #[test]
+    fn decimal_keeps_leading_zeros() {
+        // 0x...47: смещение 3, усечённое значение 0x00612900 = 6367488
+        let hex = "0200000061290047";
+        assert_eq!(format_password(OutputFormat::Decimal(MAX_DIGITS), hex).unwrap(), "0006367488");
+        assert_eq!(format_password(OutputFormat::Decimal(MIN_DIGITS), hex).unwrap(), "367488");
+        assert_eq!(format_password(OutputFormat::Decimal(MIN_DIGITS), "0000000000000000").unwrap(), "000000");
+    }
+
+    #[test]
+    fn base32_accepts_confusable_letters() {
+        let base32 = format_password(OutputFormat::Base32, "0200000061290047").unwrap();
+        let typed = base32.replace('1', "l").replace('0', "O");
+        assert!(passwords_equal(&normalize_password(OutputFormat::Base32, &typed), &base32));
+    }
+
+    #[test]
+    fn names_cover_digit_range() {
+        assert_eq!(OutputFormat::parse("dec6"), Some(OutputFormat::Decimal(MIN_DIGITS)));
+        assert_eq!(OutputFormat::parse("DEC10"), Some(OutputFormat::Decimal(MAX_DIGITS)));
+        assert_eq!(OutputFormat::parse("dec5"), None);
+        assert_eq!(OutputFormat::parse("dec11"), None);
+        for format in formats() {
+            assert_eq!(OutputFormat::parse(&format.name()), Some(format));
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod cipher;
+pub mod format;
+pub mod mode;
+pub mod ocra;
+pub mod secret;
+pub mod sheet;
+pub mod skey;
+pub mod text;
+pub mod weak_keys;
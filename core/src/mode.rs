@@ -0,0 +1,53 @@
+// Способ получения блока, который шифруется ключом BS2
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AuthMode {
+    // Исходная схема: блок - число секунд от начальной настройки
+    #[default]
+    Time,
+    // Сервер выдаёт запрос, который шифруется вместо блока времени
+    Challenge,
+    // Запрос-ответ OCRA (RFC 6287), ключ HMAC - байты BS2
+    Ocra,
+    // Блок - счётчик событий, увеличивается с каждым паролем
+    Counter,
+    // Цепочка хешей S/KEY (RFC 2289), BS2 - секрет цепочки
+    HashChain,
+}
+
+impl AuthMode {
+    pub const ALL: [AuthMode; 5] = [
+        AuthMode::Time,
+        AuthMode::Challenge,
+        AuthMode::Ocra,
+        AuthMode::Counter,
+        AuthMode::HashChain,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AuthMode::Time => "time",
+            AuthMode::Challenge => "challenge",
+            AuthMode::Ocra => "ocra",
+            AuthMode::Counter => "counter",
+            AuthMode::HashChain => "skey",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            AuthMode::Time => "По времени",
+            AuthMode::Challenge => "Запрос-ответ (шифр блока)",
+            AuthMode::Ocra => "OCRA (RFC 6287)",
+            AuthMode::Counter => "По счётчику событий",
+            AuthMode::HashChain => "Цепочка хешей S/KEY (RFC 2289)",
+        }
+    }
+
+    pub fn uses_challenge(self) -> bool {
+        matches!(self, AuthMode::Challenge | AuthMode::Ocra)
+    }
+}
@@ -0,0 +1,383 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+// OCRA (RFC 6287) - алгоритм запрос-ответ на основе HMAC. Набор параметров
+// задаётся строкой OCRASuite, например OCRA-1:HOTP-SHA1-8:QN08-PSHA1:
+// SHA1, 8 цифр ответа, числовой запрос до 8 цифр, хеш PIN-кода.
+pub const DEFAULT_SUITE: &str = "OCRA-1:HOTP-SHA1-8:QN08";
+
+const QUESTION_BYTES: usize = 128;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HashFunction {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QuestionFormat {
+    Alphanumeric,
+    Numeric,
+    Hex,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OcraSuite {
+    text: String,
+    pub hash: HashFunction,
+    pub digits: u32,
+    pub counter: bool,
+    pub question: QuestionFormat,
+    pub question_len: usize,
+    pub pin_hash: Option<HashFunction>,
+    pub session_len: Option<usize>,
+    pub time_step: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct OcraInput<'a> {
+    pub counter: u64,
+    pub question: &'a str,
+    pub pin: &'a str,
+    pub session: &'a str,
+    pub unix_time: u64,
+}
+
+impl HashFunction {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "SHA1" => Some(HashFunction::Sha1),
+            "SHA256" => Some(HashFunction::Sha256),
+            "SHA512" => Some(HashFunction::Sha512),
+            _ => None,
+        }
+    }
+
+    fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashFunction::Sha1 => Sha1::digest(data).to_vec(),
+            HashFunction::Sha256 => Sha256::digest(data).to_vec(),
+            HashFunction::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        // HMAC принимает ключ любой длины, ошибка невозможна
+        match self {
+            HashFunction::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            HashFunction::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            HashFunction::Sha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+}
+
+impl OcraSuite {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = text.split(':').collect();
+        if parts.len() != 3 || parts[0] != "OCRA-1" {
+            return Err(format!("OCRASuite {}: ожидается OCRA-1:HOTP-H-t:DataInput", text));
+        }
+
+        let function: Vec<&str> = parts[1].split('-').collect();
+        if function.len() != 3 || function[0] != "HOTP" {
+            return Err(format!("OCRASuite {}: неверная функция {}", text, parts[1]));
+        }
+        let hash = HashFunction::parse(function[1])
+            .ok_or_else(|| format!("OCRASuite {}: неизвестный хеш {}", text, function[1]))?;
+        let digits: u32 = function[2]
+            .parse()
+            .ok()
+            .filter(|d| *d == 0 || (4..=10).contains(d))
+            .ok_or_else(|| format!("OCRASuite {}: длина ответа 0 или 4-10", text))?;
+
+        let mut suite = OcraSuite {
+            text: text.to_string(),
+            hash,
+            digits,
+            counter: false,
+            question: QuestionFormat::Numeric,
+            question_len: 0,
+            pin_hash: None,
+            session_len: None,
+            time_step: None,
+        };
+
+        for input in parts[2].split('-') {
+            let bad = || format!("OCRASuite {}: неверный параметр {}", text, input);
+
+            if input == "C" {
+                suite.counter = true;
+            } else if let Some(q) = input.strip_prefix('Q') {
+                let (format, len) = q.split_at(q.len().min(1));
+                suite.question = match format {
+                    "A" => QuestionFormat::Alphanumeric,
+                    "N" => QuestionFormat::Numeric,
+                    "H" => QuestionFormat::Hex,
+                    _ => return Err(bad()),
+                };
+                suite.question_len = len.parse().ok().filter(|l| (4..=64).contains(l)).ok_or_else(bad)?;
+            } else if let Some(p) = input.strip_prefix('P') {
+                suite.pin_hash = Some(HashFunction::parse(p).ok_or_else(bad)?);
+            } else if let Some(s) = input.strip_prefix('S') {
+                suite.session_len = Some(s.parse().ok().filter(|l| *l <= 512).ok_or_else(bad)?);
+            } else if let Some(t) = input.strip_prefix('T') {
+                let (count, unit) = t.split_at(t.len().saturating_sub(1));
+                let count: u64 = count.parse().ok().filter(|c| *c > 0).ok_or_else(bad)?;
+                let unit = match unit {
+                    "S" => 1,
+                    "M" => 60,
+                    "H" => 3600,
+                    _ => return Err(bad()),
+                };
+                suite.time_step = Some(count * unit);
+            } else {
+                return Err(bad());
+            }
+        }
+
+        if suite.question_len == 0 {
+            return Err(format!("OCRASuite {}: не задан формат запроса Qxx", text));
+        }
+
+        Ok(suite)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn validate_question(&self, question: &str) -> Result<(), String> {
+        let valid_chars = match self.question {
+            QuestionFormat::Alphanumeric => question.chars().all(|c| c.is_ascii_alphanumeric()),
+            QuestionFormat::Numeric => question.chars().all(|c| c.is_ascii_digit()),
+            QuestionFormat::Hex => question.chars().all(|c| c.is_ascii_hexdigit()),
+        };
+
+        if !valid_chars || question.len() < 4 || question.len() > self.question_len {
+            let kind = match self.question {
+                QuestionFormat::Alphanumeric => "букв и цифр",
+                QuestionFormat::Numeric => "цифр",
+                QuestionFormat::Hex => "HEX символов",
+            };
+            return Err(format!("Запрос: от 4 до {} {}", self.question_len, kind));
+        }
+
+        Ok(())
+    }
+
+    // Запрос максимальной длины из произвольных байтов (случайных или хеша
+    // данных транзакции)
+    pub fn challenge_from_bytes(&self, bytes: &[u8]) -> String {
+        let hex = hex::encode_upper(bytes);
+        match self.question {
+            QuestionFormat::Numeric => bytes
+                .iter()
+                .cycle()
+                .take(self.question_len)
+                .map(|b| char::from(b'0' + b % 10))
+                .collect(),
+            QuestionFormat::Hex | QuestionFormat::Alphanumeric => hex.chars().cycle().take(self.question_len).collect(),
+        }
+    }
+
+    // Запрос, привязанный к данным транзакции: клиент вычисляет его сам и
+    // может убедиться, что подписывает именно эти данные
+    pub fn challenge_from_transaction(&self, transaction: &str) -> String {
+        self.challenge_from_bytes(&Sha256::digest(transaction.trim().as_bytes()))
+    }
+}
+
+pub fn response(suite: &OcraSuite, key: &[u8], input: &OcraInput) -> Result<String, String> {
+    suite.validate_question(input.question)?;
+
+    let mut message = suite.text.as_bytes().to_vec();
+    message.push(0);
+
+    if suite.counter {
+        message.extend_from_slice(&input.counter.to_be_bytes());
+    }
+
+    message.extend_from_slice(&question_bytes(suite.question, input.question));
+
+    if let Some(hash) = suite.pin_hash {
+        message.extend_from_slice(&hash.digest(input.pin.as_bytes()));
+    }
+
+    if let Some(len) = suite.session_len {
+        let session = input.session.as_bytes();
+        if session.len() > len {
+            return Err(format!("Данные сессии: не более {} байт", len));
+        }
+        message.resize(message.len() + len - session.len(), 0);
+        message.extend_from_slice(session);
+    }
+
+    if let Some(step) = suite.time_step {
+        message.extend_from_slice(&(input.unix_time / step).to_be_bytes());
+    }
+
+    let mac = suite.hash.hmac(key, &message);
+
+    if suite.digits == 0 {
+        return Ok(hex::encode_upper(mac));
+    }
+
+    let offset = (mac[mac.len() - 1] & 0x0F) as usize;
+    let code = u32::from_be_bytes([mac[offset], mac[offset + 1], mac[offset + 2], mac[offset + 3]]) & 0x7FFF_FFFF;
+    let code = code as u64 % 10u64.pow(suite.digits);
+
+    Ok(format!("{:0width$}", code, width = suite.digits as usize))
+}
+
+// Запрос дополняется нулями справа до 128 байт; числовой запрос сначала
+// переводится в шестнадцатеричную запись
+fn question_bytes(format: QuestionFormat, question: &str) -> Vec<u8> {
+    let mut bytes = match format {
+        QuestionFormat::Alphanumeric => question.as_bytes().to_vec(),
+        QuestionFormat::Numeric => hex_digits_to_bytes(&decimal_to_hex(question)),
+        QuestionFormat::Hex => hex_digits_to_bytes(&question.to_uppercase()),
+    };
+    bytes.resize(QUESTION_BYTES, 0);
+    bytes
+}
+
+fn hex_digits_to_bytes(hex: &str) -> Vec<u8> {
+    let mut digits: Vec<u8> = hex.chars().filter_map(|c| c.to_digit(16)).map(|d| d as u8).collect();
+    if digits.len() % 2 == 1 {
+        digits.push(0);
+    }
+    digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect()
+}
+
+fn decimal_to_hex(decimal: &str) -> String {
+    let mut digits: Vec<u32> = decimal.chars().filter_map(|c| c.to_digit(10)).collect();
+    let mut hex = Vec::new();
+
+    while digits.iter().any(|d| *d != 0) {
+        let mut remainder = 0;
+        for digit in digits.iter_mut() {
+            let value = remainder * 10 + *digit;
+            *digit = value / 16;
+            remainder = value % 16;
+        }
+        hex.push(std::char::from_digit(remainder, 16).unwrap_or('0').to_ascii_uppercase());
+    }
+
+    if hex.is_empty() {
+        return "0".to_string();
+    }
+    hex.iter().rev().collect()
+}
+
+// Векторы RFC 6287, приложение C
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: &str = "3132333435363738393031323334353637383930";
+    const SEED32: &str = "3132333435363738393031323334353637383930313233343536373839303132";
+    const SEED64: &str = "31323334353637383930313233343536373839303132333435363738393031323334353637383930313233343536373839303132333435363738393031323334";
+
+    // T = 132d0b6 минут от эпохи
+    const TIME: u64 = 0x132d0b6 * 60;
+
+    fn ocra(suite: &str, seed: &str, input: OcraInput) -> String {
+        let suite = OcraSuite::parse(suite).unwrap();
+        response(&suite, &hex::decode(seed).unwrap(), &input).unwrap()
+    }
+
+    fn questions() -> impl Iterator<Item = String> {
+        (0..10).map(|digit: u32| digit.to_string().repeat(8))
+    }
+
+    #[test]
+    fn one_way_numeric_question() {
+        let expected = ["237653", "243178", "653583", "740991", "608993", "388898", "816933", "224598", "750600", "294470"];
+        for (question, expected) in questions().zip(expected) {
+            let input = OcraInput { question: &question, ..Default::default() };
+            assert_eq!(ocra("OCRA-1:HOTP-SHA1-6:QN08", SEED, input), expected, "Q={}", question);
+        }
+    }
+
+    #[test]
+    fn counter_with_pin_hash() {
+        let expected = ["65347737", "86775851", "78192410", "71565254", "10104329", "65983500", "70069104", "91771096", "75011558", "08522129"];
+        for (counter, expected) in expected.into_iter().enumerate() {
+            let input = OcraInput { counter: counter as u64, question: "12345678", pin: "1234", ..Default::default() };
+            assert_eq!(ocra("OCRA-1:HOTP-SHA256-8:C-QN08-PSHA1", SEED32, input), expected, "C={}", counter);
+        }
+    }
+
+    #[test]
+    fn pin_hash_without_counter() {
+        let expected = ["83238735", "01501458", "17957585", "86776967", "86807031"];
+        for (question, expected) in questions().zip(expected) {
+            let input = OcraInput { question: &question, pin: "1234", ..Default::default() };
+            assert_eq!(ocra("OCRA-1:HOTP-SHA256-8:QN08-PSHA1", SEED32, input), expected, "Q={}", question);
+        }
+
+        // Другой PIN - другой ответ
+        let input = OcraInput { question: "00000000", pin: "4321", ..Default::default() };
+        assert_ne!(ocra("OCRA-1:HOTP-SHA256-8:QN08-PSHA1", SEED32, input), expected[0]);
+    }
+
+    #[test]
+    fn counter_with_sha512() {
+        let expected = ["07016083", "63947962", "70123924", "25341727", "33203315", "34205738", "44343969", "51946085", "20403879", "31409299"];
+        for ((counter, question), expected) in questions().enumerate().zip(expected) {
+            let input = OcraInput { counter: counter as u64, question: &question, ..Default::default() };
+            assert_eq!(ocra("OCRA-1:HOTP-SHA512-8:C-QN08", SEED64, input), expected, "C={}", counter);
+        }
+    }
+
+    #[test]
+    fn time_step() {
+        let expected = ["95209754", "55907591", "22048402", "24218844", "36209546"];
+        for (question, expected) in questions().zip(expected) {
+            let input = OcraInput { question: &question, unix_time: TIME, ..Default::default() };
+            assert_eq!(ocra("OCRA-1:HOTP-SHA512-8:QN08-T1M", SEED64, input), expected, "Q={}", question);
+        }
+
+        // Внутри шага ответ тот же, соседние шаги (±1 минута) дают другой
+        let at = |unix_time| ocra("OCRA-1:HOTP-SHA512-8:QN08-T1M", SEED64, OcraInput { question: "00000000", unix_time, ..Default::default() });
+        assert_eq!(at(TIME + 59), expected[0]);
+        assert_ne!(at(TIME - 1), expected[0]);
+        assert_ne!(at(TIME + 60), expected[0]);
+    }
+
+    // Счётчик и время входят в сообщение вместе
+    #[test]
+    fn counter_and_time() {
+        let suite = "OCRA-1:HOTP-SHA512-8:C-QN08-T1M";
+        let at = |counter, unix_time| ocra(suite, SEED64, OcraInput { counter, question: "00000000", unix_time, ..Default::default() });
+        let base = at(0, TIME);
+        assert_eq!(base.len(), 8);
+        assert_eq!(at(0, TIME + 30), base);
+        assert_ne!(at(1, TIME), base);
+        assert_ne!(at(0, TIME + 60), base);
+        assert_ne!(base, ocra("OCRA-1:HOTP-SHA512-8:C-QN08", SEED64, OcraInput { question: "00000000", ..Default::default() }));
+    }
+
+    #[test]
+    fn suite_fields() {
+        let suite = OcraSuite::parse("OCRA-1:HOTP-SHA512-8:C-QN08-PSHA1-T1M").unwrap();
+        assert_eq!((suite.hash, suite.digits, suite.counter), (HashFunction::Sha512, 8, true));
+        assert_eq!((suite.question, suite.question_len), (QuestionFormat::Numeric, 8));
+        assert_eq!((suite.pin_hash, suite.time_step), (Some(HashFunction::Sha1), Some(60)));
+        assert!(suite.validate_question("123456789").is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut, Range};
+use std::sync::{Mutex, OnceLock};
+use zeroize::Zeroizing;
+
+pub use zeroize::Zeroize;
+
+// Секреты (BS1, PIN, BS2, ключ шифра, пароль) держатся в буферах, которые
+// при освобождении затираются нулями. Ёмкость задаётся заранее: пока текст в
+// неё помещается, буфер не перевыделяется и не оставляет копий в куче.
+//
+// Строка секрета. Буфер из buffer() закреплён в памяти и при освобождении
+// сначала затирается, затем открепляется.
+#[derive(Default)]
+pub struct SecretString {
+    text: Zeroizing<String>,
+    // Закреплённая область: адрес и ёмкость на момент закрепления
+    locked: Option<(usize, usize)>,
+}
+
+impl Deref for SecretString {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.text
+    }
+}
+
+impl DerefMut for SecretString {
+    fn deref_mut(&mut self) -> &mut String {
+        &mut self.text
+    }
+}
+
+// Строка переходит в буфер без копирования, но не закрепляется
+impl From<String> for SecretString {
+    fn from(text: String) -> Self {
+        SecretString {
+            text: Zeroizing::new(text),
+            locked: None,
+        }
+    }
+}
+
+impl Clone for SecretString {
+    fn clone(&self) -> Self {
+        copy(&self.text)
+    }
+}
+
+impl PartialEq for SecretString {
+    fn eq(&self, other: &Self) -> bool {
+        *self.text == *other.text
+    }
+}
+
+impl Eq for SecretString {}
+
+// Значение в отладочный вывод не попадает
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+impl Zeroize for SecretString {
+    fn zeroize(&mut self) {
+        self.text.zeroize();
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.text.zeroize();
+        if let Some((address, len)) = self.locked {
+            unlock(address, len);
+        }
+    }
+}
+
+// Пустой буфер с запасом ёмкости, закреплённый в памяти
+pub fn buffer(capacity: usize) -> SecretString {
+    let text = String::with_capacity(capacity);
+    let locked = lock(&text);
+    SecretString {
+        text: Zeroizing::new(text),
+        locked,
+    }
+}
+
+pub fn copy(text: &str) -> SecretString {
+    let mut secret = buffer(text.len());
+    secret.push_str(text);
+    secret
+}
+
+// HEX-секреты хранятся в верхнем регистре; копия сразу в буфер, без
+// промежуточной строки to_uppercase()
+pub fn uppercase(text: &str) -> SecretString {
+    let mut secret = buffer(text.len());
+    secret.extend(text.chars().map(|c| c.to_ascii_uppercase()));
+    secret
+}
+
+pub type SecretBytes = Zeroizing<Vec<u8>>;
+
+pub fn decode_hex(text: &str) -> Result<SecretBytes, hex::FromHexError> {
+    hex::decode(text).map(Zeroizing::new)
+}
+
+// Первая ошибка закрепления: без прав или сверх RLIMIT_MEMLOCK страницы
+// остаются обычными и могут уйти в файл подкачки, затирание работает и так.
+// Интерфейс показывает её предупреждением.
+pub fn lock_error() -> Option<&'static str> {
+    LOCK_ERROR.get().map(String::as_str)
+}
+
+static LOCK_ERROR: OnceLock<String> = OnceLock::new();
+
+// Сколько буферов закрепили каждую страницу. Закрепление не считается
+// системой: страница открепляется, когда освобождён последний буфер на ней.
+static PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+// Страницы буфера не уходят в файл подкачки (mlock, VirtualLock)
+fn lock(text: &String) -> Option<(usize, usize)> {
+    if text.capacity() == 0 {
+        return None;
+    }
+    let (address, len) = (text.as_ptr() as usize, text.capacity());
+
+    let mut pages = PAGES.lock().ok()?;
+    if let Err(e) = os_lock(address, len) {
+        let _ = LOCK_ERROR.set(format!("страницы секретов не закреплены в памяти: {}", e));
+        return None;
+    }
+    for page in page_range(address, len) {
+        *pages.entry(page).or_default() += 1;
+    }
+    Some((address, len))
+}
+
+fn unlock(address: usize, len: usize) {
+    let Ok(mut pages) = PAGES.lock() else {
+        return;
+    };
+    for page in page_range(address, len) {
+        match pages.get_mut(&page) {
+            Some(count) if *count > 1 => *count -= 1,
+            _ => {
+                pages.remove(&page);
+                os_unlock(page * page_size(), page_size());
+            }
+        }
+    }
+}
+
+fn page_range(address: usize, len: usize) -> Range<usize> {
+    address / page_size()..(address + len).div_ceil(page_size())
+}
+
+fn page_size() -> usize {
+    static SIZE: OnceLock<usize> = OnceLock::new();
+    *SIZE.get_or_init(|| {
+        #[cfg(unix)]
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.try_into().unwrap_or(4096);
+        #[cfg(windows)]
+        let size = unsafe {
+            let mut info = std::mem::zeroed::<winapi::um::sysinfoapi::SYSTEM_INFO>();
+            winapi::um::sysinfoapi::GetSystemInfo(&mut info);
+            info.dwPageSize as usize
+        };
+        #[cfg(not(any(unix, windows)))]
+        let size = 4096;
+        size
+    })
+}
+
+fn os_lock(address: usize, len: usize) -> io::Result<()> {
+    #[cfg(unix)]
+    let locked = unsafe { libc::mlock(address as *const libc::c_void, len) == 0 };
+    #[cfg(windows)]
+    let locked = unsafe { winapi::um::memoryapi::VirtualLock(address as *mut _, len) != 0 };
+    #[cfg(not(any(unix, windows)))]
+    return Err(io::Error::new(io::ErrorKind::Unsupported, "не поддерживается"));
+
+    #[cfg(any(unix, windows))]
+    if locked {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn os_unlock(address: usize, len: usize) {
+    #[cfg(unix)]
+    unsafe {
+        libc::munlock(address as *const libc::c_void, len);
+    }
+    #[cfg(windows)]
+    unsafe {
+        winapi::um::memoryapi::VirtualUnlock(address as *mut _, len);
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = (address, len);
+}
@@ -0,0 +1,48 @@
+use printpdf::{Mm, PdfDocument};
+
+// Печатный лист (коды восстановления, списки паролей): заголовок и строки
+// моноширинного текста. В PDF встраивается переданный TTF шрифт с
+// кириллицей - стандартные шрифты PDF её не содержат.
+pub struct Sheet {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const TITLE_SIZE: f32 = 14.0;
+const FONT_SIZE: f32 = 10.0;
+const LINE_HEIGHT: f32 = 5.0;
+
+impl Sheet {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n{}\n\n", self.title, "=".repeat(self.title.chars().count()));
+        for line in &self.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn to_pdf(&self, font: &[u8]) -> Result<Vec<u8>, String> {
+        let (doc, page, layer) = PdfDocument::new(&self.title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "text");
+        let font = doc.add_external_font(font).map_err(|e| format!("Шрифт: {}", e))?;
+
+        let mut layer = doc.get_page(page).get_layer(layer);
+        layer.use_text(&self.title, TITLE_SIZE, Mm(MARGIN), Mm(PAGE_HEIGHT - MARGIN), &font);
+        let mut y = PAGE_HEIGHT - MARGIN - 3.0 * LINE_HEIGHT;
+
+        for line in &self.lines {
+            if y < MARGIN {
+                let (page, next) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "text");
+                layer = doc.get_page(page).get_layer(next);
+                y = PAGE_HEIGHT - MARGIN;
+            }
+            layer.use_text(line, FONT_SIZE, Mm(MARGIN), Mm(y), &font);
+            y -= LINE_HEIGHT;
+        }
+
+        doc.save_to_bytes().map_err(|e| format!("PDF: {}", e))
+    }
+}
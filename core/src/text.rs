@@ -0,0 +1,141 @@
+use encoding_rs::WINDOWS_1251;
+use std::fs;
+use std::path::Path;
+
+// Кодировка текстовых файлов. Исходные программы писали Windows-1251; файлы,
+// сохранённые в UTF-8 (с BOM или без), распознаются автоматически.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextEncoding {
+    // Чтение: по BOM и корректности UTF-8; запись: Windows-1251, если все
+    // символы в ней представимы, иначе UTF-8
+    #[default]
+    Auto,
+    Windows1251,
+    Utf8,
+}
+
+// Прочитанный текст и кодировка, в которой он оказался
+pub struct Decoded {
+    pub text: String,
+    pub encoding: TextEncoding,
+    // Байты, недопустимые в выбранной кодировке, заменены на U+FFFD
+    pub lossy: bool,
+}
+
+impl TextEncoding {
+    pub const ALL: [TextEncoding; 3] = [TextEncoding::Auto, TextEncoding::Windows1251, TextEncoding::Utf8];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TextEncoding::Auto => "auto",
+            TextEncoding::Windows1251 => "windows-1251",
+            TextEncoding::Utf8 => "utf-8",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            TextEncoding::Auto => "Автоопределение",
+            TextEncoding::Windows1251 => "Windows-1251",
+            TextEncoding::Utf8 => "UTF-8",
+        }
+    }
+
+    // Кодировка для записи файла, прочитанного как read: в автоматическом
+    // режиме файл в UTF-8 остаётся в UTF-8
+    pub fn keep(self, read: TextEncoding) -> TextEncoding {
+        match (self, read) {
+            (TextEncoding::Auto, TextEncoding::Utf8) => TextEncoding::Utf8,
+            (encoding, _) => encoding,
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> Decoded {
+        let encoding = match self {
+            TextEncoding::Auto => detect(bytes),
+            encoding => encoding,
+        };
+
+        let (text, lossy) = match encoding {
+            TextEncoding::Utf8 => {
+                let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+                (String::from_utf8_lossy(bytes).into_owned(), std::str::from_utf8(bytes).is_err())
+            }
+            _ => {
+                let (text, _, lossy) = WINDOWS_1251.decode(bytes);
+                (text.into_owned(), lossy)
+            }
+        };
+
+        Decoded { text, encoding, lossy }
+    }
+
+    // Кодировка, в которой текст будет записан: auto выбирает Windows-1251,
+    // если все символы в ней представимы, иначе UTF-8
+    pub fn resolve(self, text: &str) -> TextEncoding {
+        match self {
+            TextEncoding::Auto if unencodable(text).is_empty() => TextEncoding::Windows1251,
+            TextEncoding::Auto => TextEncoding::Utf8,
+            encoding => encoding,
+        }
+    }
+
+    // Ошибка, если текст нельзя записать без потерь в явно выбранной
+    // Windows-1251: encoding_rs заменил бы такие символы ссылками &#NNNN;
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, String> {
+        match self.resolve(text) {
+            TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            _ => {
+                let lost = unencodable(text);
+                if !lost.is_empty() {
+                    return Err(format!(
+                        "символы {} не представимы в Windows-1251, выберите кодировку UTF-8",
+                        lost.iter().collect::<String>()
+                    ));
+                }
+                Ok(WINDOWS_1251.encode(text).0.into_owned())
+            }
+        }
+    }
+
+    pub fn read<P: AsRef<Path>>(self, path: P) -> Result<Decoded, String> {
+        let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+        Ok(self.decode(&bytes))
+    }
+
+    pub fn write<P: AsRef<Path>>(self, path: P, content: &str) -> Result<(), String> {
+        let bytes = self.encode(content)?;
+        fs::write(path, bytes).map_err(|e| e.to_string())
+    }
+}
+
+// BOM UTF-8 или корректный UTF-8 с не-ASCII символами; иначе Windows-1251
+// (текст в ней почти никогда не складывается в корректный UTF-8)
+pub fn detect(bytes: &[u8]) -> TextEncoding {
+    if bytes.starts_with(UTF8_BOM) || (!bytes.is_ascii() && std::str::from_utf8(bytes).is_ok()) {
+        TextEncoding::Utf8
+    } else {
+        TextEncoding::Windows1251
+    }
+}
+
+// Символы текста, которых нет в Windows-1251, без повторов
+pub fn unencodable(text: &str) -> Vec<char> {
+    let mut lost: Vec<char> = Vec::new();
+    let mut buffer = [0u8; 4];
+
+    for c in text.chars().filter(|c| !c.is_ascii()) {
+        let (_, _, had_errors) = WINDOWS_1251.encode(c.encode_utf8(&mut buffer));
+        if had_errors && !lost.contains(&c) {
+            lost.push(c);
+        }
+    }
+
+    lost
+}
@@ -0,0 +1,142 @@
+use crate::cipher::Algorithm;
+
+// Слабые и полуслабые ключи DES (FIPS 74). Младший бит каждого байта ключа
+// является битом чётности и в шифровании не участвует, поэтому сравнение
+// выполняется без него.
+const WEAK_KEYS: [u64; 4] = [
+    0x0101010101010101,
+    0xFEFEFEFEFEFEFEFE,
+    0xE0E0E0E0F1F1F1F1,
+    0x1F1F1F1F0E0E0E0E,
+];
+
+const SEMI_WEAK_KEYS: [u64; 12] = [
+    0x01FE01FE01FE01FE, 0xFE01FE01FE01FE01,
+    0x1FE01FE00EF10EF1, 0xE01FE01FF10EF10E,
+    0x01E001E001F101F1, 0xE001E001F101F101,
+    0x1FFE1FFE0EFE0EFE, 0xFE1FFE1FFE0EFE0E,
+    0x011F011F010E010E, 0x1F011F010E010E01,
+    0xE0FEE0FEF1FEF1FE, 0xFEE0FEE0FEF1FEF1,
+];
+
+const PARITY_MASK: u64 = 0xFEFEFEFEFEFEFEFE;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KeyWeakness {
+    Weak,
+    SemiWeak,
+}
+
+// Ключ без битов чётности: два ключа с одинаковым значением шифруют одинаково
+pub fn effective_des_key(key_hex: &str) -> Option<u64> {
+    if key_hex.len() != 16 || !key_hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u64::from_str_radix(key_hex, 16).ok().map(|key| key & PARITY_MASK)
+}
+
+pub fn des_key_weakness(key_hex: &str) -> Option<KeyWeakness> {
+    let key = effective_des_key(key_hex)?;
+
+    if WEAK_KEYS.iter().any(|k| k & PARITY_MASK == key) {
+        Some(KeyWeakness::Weak)
+    } else if SEMI_WEAK_KEYS.iter().any(|k| k & PARITY_MASK == key) {
+        Some(KeyWeakness::SemiWeak)
+    } else {
+        None
+    }
+}
+
+// Проверка ключа с учётом алгоритма. 3DES с совпадающими соседними
+// подключами вырождается в одиночный DES и считается слабым.
+pub fn key_weakness(algorithm: Algorithm, key_hex: &str) -> Option<KeyWeakness> {
+    match algorithm {
+        Algorithm::Des => des_key_weakness(key_hex),
+        Algorithm::TdesEde2 | Algorithm::TdesEde3 => {
+            if key_hex.len() != algorithm.key_hex_len() || !key_hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            let subkeys: Vec<&str> = (0..key_hex.len() / 16).map(|i| &key_hex[i * 16..(i + 1) * 16]).collect();
+
+            let degenerate = subkeys
+                .windows(2)
+                .any(|pair| effective_des_key(pair[0]) == effective_des_key(pair[1]));
+            if degenerate {
+                return Some(KeyWeakness::Weak);
+            }
+
+            let weaknesses: Vec<KeyWeakness> = subkeys.iter().filter_map(|k| des_key_weakness(k)).collect();
+            if weaknesses.contains(&KeyWeakness::Weak) {
+                Some(KeyWeakness::Weak)
+            } else {
+                weaknesses.first().copied()
+            }
+        }
+        Algorithm::Aes128 => None,
+    }
+}
+
+// Ключ без битов чётности (для DES и 3DES) для поиска совпадающих ключей
+pub fn effective_key(algorithm: Algorithm, key_hex: &str) -> Option<Vec<u8>> {
+    if key_hex.len() != algorithm.key_hex_len() || !key_hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mask = match algorithm {
+        Algorithm::Aes128 => 0xFF,
+        _ => 0xFE,
+    };
+
+    (0..key_hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&key_hex[i..i + 2], 16).ok().map(|b| b & mask))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::encrypt;
+
+    const BLOCK: &str = "0123456789ABCDEF";
+
+    #[test]
+    fn des_weak_and_semi_weak_keys() {
+        assert_eq!(des_key_weakness("0101010101010101"), Some(KeyWeakness::Weak));
+        // Биты чётности не учитываются
+        assert_eq!(des_key_weakness("0000000000000000"), Some(KeyWeakness::Weak));
+        assert_eq!(des_key_weakness("e0e0e0e0f1f1f1f1"), Some(KeyWeakness::Weak));
+        assert_eq!(des_key_weakness("01FE01FE01FE01FE"), Some(KeyWeakness::SemiWeak));
+        assert_eq!(des_key_weakness("00FF00FF00FF00FF"), Some(KeyWeakness::SemiWeak));
+        assert_eq!(des_key_weakness("133457799BBCDFF1"), None);
+        assert_eq!(des_key_weakness("0101"), None);
+    }
+
+    // Слабый ключ - инволюция: повторное шифрование возвращает блок; пара
+    // полуслабых ключей расшифровывает друг друга
+    #[test]
+    fn weak_keys_behave_as_documented() {
+        let twice = |first: &str, second: &str| encrypt(Algorithm::Des, &encrypt(Algorithm::Des, BLOCK, first).unwrap(), second).unwrap();
+        assert_eq!(twice("0101010101010101", "0101010101010101"), BLOCK);
+        assert_eq!(twice("01FE01FE01FE01FE", "FE01FE01FE01FE01"), BLOCK);
+        assert_ne!(twice("133457799BBCDFF1", "133457799BBCDFF1"), BLOCK);
+    }
+
+    #[test]
+    fn tdes_subkeys() {
+        let good = "133457799BBCDFF10123456789ABCDEF";
+        assert_eq!(key_weakness(Algorithm::TdesEde2, good), None);
+        // Совпадающие соседние подключи - одиночный DES
+        assert_eq!(key_weakness(Algorithm::TdesEde2, "133457799BBCDFF1123456789BBCDFF0"), Some(KeyWeakness::Weak));
+        assert_eq!(key_weakness(Algorithm::TdesEde3, "0123456789ABCDEF01FE01FE01FE01FE23456789ABCDEF01"), Some(KeyWeakness::SemiWeak));
+        assert_eq!(key_weakness(Algorithm::TdesEde3, "0123456789ABCDEF0101010101010101FE01FE01FE01FE01"), Some(KeyWeakness::Weak));
+        assert_eq!(key_weakness(Algorithm::Aes128, "01010101010101010101010101010101"), None);
+        assert_eq!(key_weakness(Algorithm::Des, "01FE01FE01FE01FE"), Some(KeyWeakness::SemiWeak));
+    }
+
+    #[test]
+    fn effective_key_ignores_parity() {
+        assert_eq!(effective_key(Algorithm::Des, "133457799BBCDFF1"), effective_key(Algorithm::Des, "123456789BBCDFF0"));
+        assert_ne!(effective_key(Algorithm::Aes128, "01010101010101010101010101010101"), effective_key(Algorithm::Aes128, "00000000000000000000000000000000"));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use tonic_build::manual::{Builder, Method, Service};
+
+// Сообщения и служба otp.v1.OtpVerifier строятся из proto/otp_verifier.proto.
+// protoc в сборке не нужен: файл использует только часть proto3 (скалярные
+// поля, перечисления, вложенные и повторяющиеся сообщения, потоковый ответ),
+// её разбирает этот сценарий, а всё прочее останавливает сборку.
+const PROTO: &str = "proto/otp_verifier.proto";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", PROTO);
+    let text = std::fs::read_to_string(PROTO).unwrap_or_else(|e| panic!("{}: {}", PROTO, e));
+    let file = parse(&tokens(&text)).unwrap_or_else(|e| panic!("{}: {}", PROTO, e));
+
+    let out = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out.join(format!("{}.rs", file.package)), messages(&file)).unwrap();
+
+    let mut service = Service::builder().name(&file.service.name).package(&file.package);
+    for rpc in &file.service.rpcs {
+        let method = Method::builder()
+            .name(snake_case(&rpc.name))
+            .route_name(&rpc.name)
+            .input_type(format!("crate::proto::{}", rpc.input))
+            .output_type(format!("crate::proto::{}", rpc.output))
+            .codec_path("tonic_prost::ProstCodec");
+        let method = if rpc.streaming { method.server_streaming() } else { method };
+        service = service.method(method.build());
+    }
+    Builder::new().compile(&[service.build()]);
+}
+
+struct File {
+    package: String,
+    service: ServiceDef,
+    enums: Vec<EnumDef>,
+    messages: Vec<MessageDef>,
+}
+
+#[derive(Default)]
+struct ServiceDef {
+    name: String,
+    rpcs: Vec<Rpc>,
+}
+
+struct Rpc {
+    name: String,
+    input: String,
+    output: String,
+    streaming: bool,
+}
+
+struct EnumDef {
+    name: String,
+    values: Vec<(String, i32)>,
+}
+
+struct MessageDef {
+    name: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    repeated: bool,
+    kind: String,
+    name: String,
+    tag: u32,
+}
+
+// Слова, числа, строки в кавычках и знаки; комментарии // отбрасываются
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for line in text.lines() {
+        let line = line.split("//").next().unwrap_or_default();
+        let mut chars = line.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c.is_alphanumeric() || c == '_' || c == '.' || c == '"' {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '"')) {
+                    token.push(c);
+                    chars.next();
+                }
+                tokens.push(token);
+            } else {
+                tokens.push(c.to_string());
+                chars.next();
+            }
+        }
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Result<&str, String> {
+        let token = self.tokens.get(self.position).ok_or("неожиданный конец файла")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("ожидается {}, найдено {}", expected, token)),
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        self.next().map(str::to_string)
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, String> {
+        let token = self.next()?;
+        token.parse().map_err(|_| format!("ожидается число, найдено {}", token))
+    }
+}
+
+fn parse(tokens: &[String]) -> Result<File, String> {
+    let mut parser = Parser { tokens, position: 0 };
+    let mut file = File {
+        package: String::new(),
+        service: ServiceDef::default(),
+        enums: Vec::new(),
+        messages: Vec::new(),
+    };
+
+    while let Some(keyword) = parser.peek() {
+        match keyword {
+            "syntax" => {
+                parser.next()?;
+                parser.expect("=")?;
+                parser.expect("\"proto3\"")?;
+                parser.expect(";")?;
+            }
+            "package" => {
+                parser.next()?;
+                file.package = parser.name()?;
+                parser.expect(";")?;
+            }
+            "service" => {
+                parser.next()?;
+                file.service.name = parser.name()?;
+                parser.expect("{")?;
+                while parser.peek() != Some("}") {
+                    parser.expect("rpc")?;
+                    let name = parser.name()?;
+                    parser.expect("(")?;
+                    let input = parser.name()?;
+                    parser.expect(")")?;
+                    parser.expect("returns")?;
+                    parser.expect("(")?;
+                    let streaming = parser.peek() == Some("stream");
+                    if streaming {
+                        parser.next()?;
+                    }
+                    let output = parser.name()?;
+                    parser.expect(")")?;
+                    parser.expect(";")?;
+                    file.service.rpcs.push(Rpc { name, input, output, streaming });
+                }
+                parser.expect("}")?;
+            }
+            "enum" => {
+                parser.next()?;
+                let name = parser.name()?;
+                let mut values = Vec::new();
+                parser.expect("{")?;
+                while parser.peek() != Some("}") {
+                    let value = parser.name()?;
+                    parser.expect("=")?;
+                    values.push((value, parser.number()?));
+                    parser.expect(";")?;
+                }
+                parser.expect("}")?;
+                file.enums.push(EnumDef { name, values });
+            }
+            "message" => {
+                parser.next()?;
+                let name = parser.name()?;
+                let mut fields = Vec::new();
+                parser.expect("{")?;
+                while parser.peek() != Some("}") {
+                    let repeated = parser.peek() == Some("repeated");
+                    if repeated {
+                        parser.next()?;
+                    }
+                    let kind = parser.name()?;
+                    let name = parser.name()?;
+                    parser.expect("=")?;
+                    let tag = parser.number()?;
+                    parser.expect(";")?;
+                    fields.push(Field { repeated, kind, name, tag });
+                }
+                parser.expect("}")?;
+                file.messages.push(MessageDef { name, fields });
+            }
+            other => return Err(format!("не поддерживается: {}", other)),
+        }
+    }
+
+    if file.package.is_empty() || file.service.name.is_empty() {
+        return Err("нужны package и service".to_string());
+    }
+    Ok(file)
+}
+
+// Типы Rust и атрибуты prost, как их строит prost-build
+fn messages(file: &File) -> String {
+    let enums: HashSet<&str> = file.enums.iter().map(|e| e.name.as_str()).collect();
+    let messages: HashSet<&str> = file.messages.iter().map(|m| m.name.as_str()).collect();
+    let mut code = String::new();
+
+    for definition in &file.enums {
+        let prefix = format!("{}_", screaming_snake_case(&definition.name));
+        code.push_str("#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]\n");
+        writeln!(code, "#[repr(i32)]\npub enum {} {{", definition.name).unwrap();
+        for (value, number) in &definition.values {
+            let variant = camel_case(value.strip_prefix(&prefix).unwrap_or(value));
+            writeln!(code, "    {} = {},", variant, number).unwrap();
+        }
+        code.push_str("}\n\n");
+    }
+
+    for definition in &file.messages {
+        code.push_str("#[derive(Clone, PartialEq, prost::Message)]\n");
+        writeln!(code, "pub struct {} {{", definition.name).unwrap();
+        for field in &definition.fields {
+            let scalar = match field.kind.as_str() {
+                "string" => Some("String"),
+                "bool" => Some("bool"),
+                "bytes" => Some("Vec<u8>"),
+                "int32" | "sint32" => Some("i32"),
+                "int64" | "sint64" => Some("i64"),
+                "uint32" => Some("u32"),
+                "uint64" => Some("u64"),
+                _ => None,
+            };
+            let (attribute, rust) = match scalar {
+                Some(rust) => (field.kind.clone(), rust.to_string()),
+                None if enums.contains(field.kind.as_str()) => (format!("enumeration = \"{}\"", field.kind), "i32".to_string()),
+                None if messages.contains(field.kind.as_str()) => ("message".to_string(), field.kind.clone()),
+                None => panic!("{}: неизвестный тип {}", PROTO, field.kind),
+            };
+            let (attribute, rust) = match (field.repeated, attribute == "message") {
+                (true, _) => (format!("{}, repeated", attribute), format!("Vec<{}>", rust)),
+                (false, true) => ("message, optional".to_string(), format!("Option<{}>", rust)),
+                (false, false) => (attribute, rust),
+            };
+            writeln!(code, "    #[prost({}, tag = \"{}\")]", attribute, field.tag).unwrap();
+            writeln!(code, "    pub {}: {},", field.name, rust).unwrap();
+        }
+        code.push_str("}\n\n");
+    }
+
+    code
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+fn screaming_snake_case(name: &str) -> String {
+    snake_case(name).to_ascii_uppercase()
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_string() + &chars.as_str().to_ascii_lowercase())
+                .unwrap_or_default()
+        })
+        .collect()
+}
@@ -0,0 +1,321 @@
+pub mod proto;
+
+use chrono::Local;
+use std::net::SocketAddr;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use otp_server::api_keys::{ApiClient, KeyError, KeyStore, Scope};
+use otp_server::audit::{self, Event};
+use otp_server::config::Config;
+use otp_server::database::User;
+use otp_server::tls::Certificates;
+use otp_server::verifier::{self, Reply};
+use proto::otp_verifier_server::{OtpVerifier, OtpVerifierServer};
+use proto::*;
+
+pub use proto::otp_verifier_client::OtpVerifierClient;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Как часто поток подписки WatchAuthEvents проверяет, подключён ли клиент
+const DISCONNECT_CHECK: Duration = Duration::from_millis(200);
+
+// Служба gRPC поверх проверки по файлам (verifier): те же база, состояние,
+// окна, блокировки и журнал, что у модуля PAM и демона
+pub struct Verifier {
+    config: Arc<Config>,
+}
+
+impl Verifier {
+    pub fn new(config: Config) -> Self {
+        Self { config: Arc::new(config) }
+    }
+
+    // Проверка с блокировкой файлов уходит из потоков tokio
+    async fn check(&self, login: String, password: String) -> Result<Reply, Status> {
+        let config = Arc::clone(&self.config);
+        let outcome = tokio::task::spawn_blocking(move || {
+            verifier::verify(&config, None, &login, &password, Local::now().naive_local())
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(Status::unavailable)?;
+        Ok(Reply::from_outcome(&outcome, self.config.denial))
+    }
+
+    // Клиент по ключу из метаданных authorization: Bearer <ключ>. Файл
+    // ключей читается при каждом вызове: выданный или отозванный в GUI ключ
+    // действует сразу.
+    fn authorize<T>(&self, request: &Request<T>, scope: Scope) -> Result<ApiClient, Status> {
+        let key = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.strip_prefix("Bearer ").unwrap_or(value));
+        let keys = KeyStore::load(&self.config.api.keys).map_err(Status::unavailable)?;
+        match keys.authorize(key, scope) {
+            Ok(client) => Ok(client.clone()),
+            Err(e @ KeyError::Forbidden(_)) => Err(Status::permission_denied(e.to_string())),
+            Err(e) => Err(Status::unauthenticated(e.to_string())),
+        }
+    }
+
+    async fn update<T: Send + 'static>(
+        &self,
+        change: impl FnOnce(&mut Vec<User>) -> Option<T> + Send + 'static,
+    ) -> Result<Option<T>, Status> {
+        let config = Arc::clone(&self.config);
+        tokio::task::spawn_blocking(move || verifier::update_users(&config, change))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(Status::unavailable)
+    }
+
+    async fn users(&self) -> Result<Vec<User>, Status> {
+        let config = Arc::clone(&self.config);
+        tokio::task::spawn_blocking(move || verifier::users(&config))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(Status::unavailable)
+    }
+}
+
+#[tonic::async_trait]
+impl OtpVerifier for Verifier {
+    async fn verify(&self, request: Request<VerifyRequest>) -> Result<Response<VerifyResponse>, Status> {
+        self.authorize(&request, Scope::Verify)?;
+        let VerifyRequest { login, password } = request.into_inner();
+        let reply = self.check(login, password).await?;
+        Ok(Response::new(verify_response(&reply)))
+    }
+
+    // Второй пароль проверяется, только если первый совпал далеко впереди
+    async fn resync(&self, request: Request<ResyncRequest>) -> Result<Response<VerifyResponse>, Status> {
+        self.authorize(&request, Scope::Verify)?;
+        let ResyncRequest { login, first, second } = request.into_inner();
+        let reply = match self.check(login.clone(), first).await? {
+            Reply::Next(_) => self.check(login, second).await?,
+            reply => reply,
+        };
+        Ok(Response::new(verify_response(&reply)))
+    }
+
+    async fn get_user(&self, request: Request<GetUserRequest>) -> Result<Response<proto::User>, Status> {
+        self.authorize(&request, Scope::ReadUsers)?;
+        let login = request.into_inner().login;
+        let users = self.users().await?;
+        let user = users
+            .iter()
+            .find(|u| u.login == login)
+            .ok_or_else(|| Status::not_found(format!("пользователь {} не найден", login)))?;
+        Ok(Response::new(user_info(user)))
+    }
+
+    async fn list_users(&self, request: Request<ListUsersRequest>) -> Result<Response<ListUsersResponse>, Status> {
+        self.authorize(&request, Scope::ReadUsers)?;
+        let users = self.users().await?.iter().map(user_info).collect();
+        Ok(Response::new(ListUsersResponse { users }))
+    }
+
+    async fn set_user_disabled(&self, request: Request<SetUserDisabledRequest>) -> Result<Response<proto::User>, Status> {
+        let client = self.authorize(&request, Scope::ManageUsers)?;
+        let SetUserDisabledRequest { login, disabled } = request.into_inner();
+        let target = login.clone();
+        let user = self
+            .update(move |users| {
+                let user = users.iter_mut().find(|u| u.login == target)?;
+                user.disabled = disabled;
+                Some(user_info(user))
+            })
+            .await?
+            .ok_or_else(|| Status::not_found(format!("пользователь {} не найден", login)))?;
+
+        let action = if disabled { "отключён" } else { "включён" };
+        audit::record(&login, &format!("{} клиентом API {}", action, client.name));
+        Ok(Response::new(user))
+    }
+
+    async fn delete_user(&self, request: Request<DeleteUserRequest>) -> Result<Response<DeleteUserResponse>, Status> {
+        let client = self.authorize(&request, Scope::ManageUsers)?;
+        let login = request.into_inner().login;
+        let target = login.clone();
+        self.update(move |users| {
+            let index = users.iter().position(|u| u.login == target)?;
+            Some(users.remove(index))
+        })
+        .await?
+        .ok_or_else(|| Status::not_found(format!("пользователь {} не найден", login)))?;
+
+        audit::record(&login, &format!("удалён клиентом API {}", client.name));
+        Ok(Response::new(DeleteUserResponse {}))
+    }
+
+    type WatchAuthEventsStream = ReceiverStream<Result<AuthEvent, Status>>;
+
+    async fn watch_auth_events(
+        &self,
+        request: Request<WatchAuthEventsRequest>,
+    ) -> Result<Response<Self::WatchAuthEventsStream>, Status> {
+        self.authorize(&request, Scope::ReadAudit)?;
+        let login = request.into_inner().login;
+        let events = audit::subscribe();
+        let (sender, receiver) = mpsc::channel(64);
+
+        // Подписка журнала синхронная. Отключение клиента закрывает канал:
+        // поток замечает это между событиями и удаляет подписку, вместе с ней
+        // завершается чтение файла журнала.
+        thread::spawn(move || {
+            while !sender.is_closed() {
+                let event = match events.recv_timeout(DISCONNECT_CHECK) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if !(login.is_empty() || event.login == login) {
+                    continue;
+                }
+                if sender.blocking_send(Ok(auth_event(&event))).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+// Ключ API для клиента службы: OtpVerifierClient::with_interceptor(канал, ключ)
+#[derive(Clone)]
+pub struct ApiKey(MetadataValue<Ascii>);
+
+impl ApiKey {
+    pub fn new(key: &str) -> Result<Self, String> {
+        format!("Bearer {}", key)
+            .parse()
+            .map(Self)
+            .map_err(|_| "ключ API: недопустимые символы".to_string())
+    }
+}
+
+impl Interceptor for ApiKey {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        request.metadata_mut().insert("authorization", self.0.clone());
+        Ok(request)
+    }
+}
+
+pub fn service(config: Config) -> OtpVerifierServer<Verifier> {
+    OtpVerifierServer::new(Verifier::new(config))
+}
+
+// Служба на всех адресах listen из настроек, с TLS из [tls]
+pub async fn serve(config: Config) -> Result<(), String> {
+    if config.listen.is_empty() {
+        return Err("listen: не задан адрес службы gRPC".to_string());
+    }
+    let certificates = Certificates::load(&config.tls, &[b"h2"])?;
+
+    let mut servers = JoinSet::new();
+    for address in &config.listen {
+        let address: SocketAddr = address.parse().map_err(|_| format!("listen: неверный адрес {}", address))?;
+        let listener = TcpListener::bind(address).await.map_err(|e| format!("{}: {}", address, e))?;
+        servers.spawn(serve_on(listener, service(config.clone()), certificates.clone()));
+    }
+
+    while let Some(result) = servers.join_next().await {
+        result.map_err(|e| e.to_string())??;
+    }
+    Ok(())
+}
+
+pub async fn serve_on(
+    listener: TcpListener,
+    service: OtpVerifierServer<Verifier>,
+    certificates: Option<Arc<Certificates>>,
+) -> Result<(), String> {
+    let address = listener.local_addr().map_err(|e| e.to_string())?;
+    let server = Server::builder().add_service(service);
+    let result = match certificates {
+        Some(certificates) => server.serve_with_incoming(handshakes(listener, certificates)).await,
+        None => server.serve_with_incoming(TcpListenerStream::new(listener)).await,
+    };
+    result.map_err(|e| format!("{}: {}", address, e))
+}
+
+// Рукопожатие каждого соединения идёт отдельной задачей, чтобы медленный
+// клиент не задерживал приём остальных; неудачные только отключаются
+fn handshakes(listener: TcpListener, certificates: Arc<Certificates>) -> ReceiverStream<std::io::Result<TlsStream<TcpStream>>> {
+    let (sender, receiver) = mpsc::channel(16);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = TlsAcceptor::from(certificates.server_config());
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = sender.send(Ok(stream)).await;
+                    }
+                    Ok(Err(e)) => eprintln!("tls: {}", e),
+                    Err(_) => {}
+                }
+            });
+        }
+    });
+    ReceiverStream::new(receiver)
+}
+
+fn verify_response(reply: &Reply) -> VerifyResponse {
+    let result = match reply {
+        Reply::Granted(_) => VerifyResult::Granted,
+        Reply::Next(_) => VerifyResult::Next,
+        Reply::Locked(_) => VerifyResult::Locked,
+        Reply::Unknown(_) => VerifyResult::UnknownUser,
+        Reply::Denied(_) | Reply::Error(_) => VerifyResult::Denied,
+    };
+    VerifyResponse {
+        result: result as i32,
+        message: reply.text().to_string(),
+    }
+}
+
+fn user_info(user: &User) -> proto::User {
+    proto::User {
+        login: user.login.clone(),
+        name: user.name.clone(),
+        mode: user.mode.name().to_string(),
+        algorithm: user.algorithm.name().to_string(),
+        format: user.format.name(),
+        disabled: user.disabled,
+        group: user.group.clone().unwrap_or_default(),
+        counter: user.counter,
+        sequence: user.chain.sequence,
+        recovery_codes: user.recovery.len() as u32,
+    }
+}
+
+fn auth_event(event: &Event) -> AuthEvent {
+    let kind = match event.granted {
+        Some(true) => EventKind::Granted,
+        Some(false) => EventKind::Denied,
+        None => EventKind::Other,
+    };
+    AuthEvent {
+        time: event.time.timestamp(),
+        login: event.login.clone(),
+        kind: kind as i32,
+        text: event.text.clone(),
+    }
+}
@@ -0,0 +1,44 @@
+use otp_server::audit;
+use otp_server::config::{Config, CONFIG_FILE};
+
+// otp_grpc [--config файл] [--listen IP:порт]...
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut config_path = CONFIG_FILE.to_string();
+    let mut listen = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match (arg.as_str(), iter.next()) {
+            ("--config", Some(value)) => config_path = value.clone(),
+            ("--listen", Some(value)) => listen.push(value.clone()),
+            _ => {
+                eprintln!("Использование: otp_grpc [--config файл] [--listen IP:порт]...");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let config = Config::load(&config_path).and_then(|mut config| {
+        if !listen.is_empty() {
+            config.listen = listen;
+        }
+        config.validate()?;
+        Ok(config)
+    });
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    audit::configure(&config);
+
+    println!("Служба gRPC слушает {}", config.listen.join(", "));
+    if let Err(e) = otp_grpc::serve(config).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
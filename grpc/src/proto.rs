@@ -0,0 +1,5 @@
+// Сообщения и служба otp.v1.OtpVerifier, построенные build.rs из
+// proto/otp_verifier.proto
+
+include!(concat!(env!("OUT_DIR"), "/otp.v1.rs"));
+include!(concat!(env!("OUT_DIR"), "/otp.v1.OtpVerifier.rs"));
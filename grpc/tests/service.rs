@@ -0,0 +1,249 @@
+// Служба gRPC на случайном порту и клиент из того же crate
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Local;
+use otp_core::cipher::{encrypt, time_block, Algorithm};
+use otp_core::format::{format_password, OutputFormat};
+use otp_core::text::TextEncoding;
+use otp_grpc::proto::*;
+use otp_grpc::{ApiKey, OtpVerifierClient};
+use otp_server::api_keys::{KeyStore, Scope};
+use otp_server::config::Config;
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::Channel;
+use tonic::Code;
+
+const BS2: &str = "AAAAE2D76510BF24";
+const EPOCH: &str = "06.05.2007 21:24:30";
+
+type Client = OtpVerifierClient<InterceptedService<Channel, ApiKey>>;
+
+struct Fixture {
+    dir: PathBuf,
+    address: String,
+    keys: String,
+    client: Client,
+}
+
+impl Fixture {
+    async fn start(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("otp_grpc_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let database = format!(
+            "Лапин_Е.В. Johnny {bs2} {epoch} group=staff\nСчётчик Alice {bs2} {epoch} mode=counter counter=0\n\
+             Отключён Bob {bs2} {epoch} disabled\n",
+            bs2 = BS2,
+            epoch = EPOCH
+        );
+        fs::write(dir.join("database.txt"), database).unwrap();
+
+        let mut config = Config::default();
+        config.relative_to(&dir);
+        otp_server::audit::configure(&config);
+        let keys = config.api.keys.clone();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(otp_grpc::serve_on(listener, otp_grpc::service(config), None));
+
+        let address = format!("http://{}", address);
+        let mut fixture = Fixture {
+            client: connect(&address, "").await,
+            dir,
+            address,
+            keys,
+        };
+        fixture.client = fixture.client_with(&Scope::ALL).await;
+        fixture
+    }
+
+    // Клиент с новым ключом, которому разрешено только scopes
+    async fn client_with(&self, scopes: &[Scope]) -> Client {
+        let mut store = KeyStore::load(&self.keys).unwrap();
+        let key = store.issue(&format!("client{}", store.clients.len()), scopes).unwrap();
+        store.save(&self.keys).unwrap();
+        connect(&self.address, &key).await
+    }
+
+    async fn verify(&mut self, login: &str, password: &str) -> (VerifyResult, String) {
+        let request = VerifyRequest {
+            login: login.to_string(),
+            password: password.to_string(),
+        };
+        let response = self.client.verify(request).await.unwrap().into_inner();
+        (response.result(), response.message)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+async fn connect(address: &str, key: &str) -> Client {
+    let channel = Channel::from_shared(address.to_string()).unwrap().connect().await.unwrap();
+    OtpVerifierClient::with_interceptor(channel, ApiKey::new(key).unwrap())
+}
+
+fn password(block: u64) -> String {
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, block), BS2).unwrap();
+    format_password(OutputFormat::Hex, &hex).unwrap()
+}
+
+fn time_password() -> String {
+    let epoch = chrono::NaiveDateTime::parse_from_str(EPOCH, "%d.%m.%Y %H:%M:%S").unwrap();
+    password((Local::now().naive_local() - epoch).num_seconds() as u64)
+}
+
+#[tokio::test]
+async fn verifies_once_and_streams_events() {
+    let mut fixture = Fixture::start("verify").await;
+    let mut events = fixture
+        .client
+        .watch_auth_events(WatchAuthEventsRequest { login: "Johnny".to_string() })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let code = time_password();
+    assert_eq!(fixture.verify("Johnny", &code).await.0, VerifyResult::Granted);
+    let (result, message) = fixture.verify("Johnny", &code).await;
+    assert_eq!(result, VerifyResult::Denied);
+    assert!(message.contains("пароль уже использован"), "{}", message);
+
+    // События другого логина в поток не попадают
+    assert_eq!(fixture.verify("nobody", &code).await.0, VerifyResult::UnknownUser);
+
+    let timeout = Duration::from_secs(5);
+    let granted = tokio::time::timeout(timeout, events.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!((granted.login.as_str(), granted.kind()), ("Johnny", EventKind::Granted));
+    let denied = tokio::time::timeout(timeout, events.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(denied.kind(), EventKind::Denied);
+    assert_eq!(denied.text, "отказ: пароль уже использован");
+
+    // Запись другого процесса (модуля PAM) в общий журнал тоже попадает в поток
+    let line = format!("{} Johnny вход разрешён\n", Local::now().format("%d.%m.%Y %H:%M:%S"));
+    let mut audit = fs::OpenOptions::new().append(true).open(fixture.dir.join("audit.log")).unwrap();
+    audit.write_all(&TextEncoding::Windows1251.encode(&line).unwrap()).unwrap();
+    let external = tokio::time::timeout(timeout, events.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!((external.kind(), external.text.as_str()), (EventKind::Granted, "вход разрешён"));
+}
+
+#[tokio::test]
+async fn hostile_login_cannot_forge_events() {
+    let mut fixture = Fixture::start("hostile").await;
+    let mut events = fixture
+        .client
+        .watch_auth_events(WatchAuthEventsRequest { login: String::new() })
+        .await
+        .unwrap()
+        .into_inner();
+
+    // Перевод строки добавил бы запись «вход разрешён» для Johnny, пробел
+    // сдвинул бы поля
+    let forged = format!("x\n{} Johnny вход разрешён", Local::now().format("%d.%m.%Y %H:%M:%S"));
+    for login in [forged.as_str(), "Johnny вход разрешён"] {
+        assert_eq!(fixture.verify(login, "0000000000000000").await.0, VerifyResult::UnknownUser);
+    }
+
+    let timeout = Duration::from_secs(5);
+    for _ in 0..2 {
+        let event = tokio::time::timeout(timeout, events.next()).await.unwrap().unwrap().unwrap();
+        assert_eq!(event.kind(), EventKind::Denied, "{:?}", event.login);
+        assert!(event.login.contains("\\u{"), "{:?}", event.login);
+    }
+    assert!(tokio::time::timeout(Duration::from_secs(1), events.next()).await.is_err());
+
+    let audit = fs::read(fixture.dir.join("audit.log")).unwrap();
+    let audit = TextEncoding::Windows1251.decode(&audit).text;
+    assert_eq!(audit.lines().count(), 2, "{}", audit);
+    assert!(audit.lines().all(|line| line.split(' ').nth(3).is_some_and(|text| text.starts_with("отказ"))), "{}", audit);
+}
+
+#[tokio::test]
+async fn resyncs_counter_with_two_passwords() {
+    let mut fixture = Fixture::start("resync").await;
+
+    // Пароль 50 далеко за окном счётчика: один Verify просит следующий
+    let request = ResyncRequest {
+        login: "Alice".to_string(),
+        first: password(50),
+        second: password(51),
+    };
+    let response = fixture.client.resync(request).await.unwrap().into_inner();
+    assert_eq!(response.result(), VerifyResult::Granted, "{}", response.message);
+
+    let user = fixture.client.get_user(GetUserRequest { login: "Alice".to_string() }).await.unwrap().into_inner();
+    assert_eq!((user.mode.as_str(), user.counter), ("counter", 52));
+    assert_eq!(fixture.verify("Alice", &password(52)).await.0, VerifyResult::Granted);
+
+    // Ожидание следующего пароля хранится по логину: вход другого
+    // пользователя между двумя паролями его не сбрасывает
+    assert_eq!(fixture.verify("Alice", &password(90)).await.0, VerifyResult::Next);
+    assert_eq!(fixture.verify("Johnny", &time_password()).await.0, VerifyResult::Granted);
+    assert_eq!(fixture.verify("Alice", &password(91)).await.0, VerifyResult::Granted);
+}
+
+#[tokio::test]
+async fn lists_users_without_secrets() {
+    let mut fixture = Fixture::start("users").await;
+
+    let users = fixture.client.list_users(ListUsersRequest {}).await.unwrap().into_inner().users;
+    let logins: Vec<&str> = users.iter().map(|u| u.login.as_str()).collect();
+    assert_eq!(logins, ["Johnny", "Alice", "Bob"]);
+    assert_eq!(users[0].group, "staff");
+    assert!(users[2].disabled);
+
+    let missing = fixture.client.get_user(GetUserRequest { login: "nobody".to_string() }).await.unwrap_err();
+    assert_eq!(missing.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn enforces_key_scopes() {
+    let fixture = Fixture::start("keys").await;
+    let code = time_password();
+    let verify = |password: &str| VerifyRequest {
+        login: "Johnny".to_string(),
+        password: password.to_string(),
+    };
+
+    let mut anonymous = connect(&fixture.address, "").await;
+    assert_eq!(anonymous.verify(verify(&code)).await.unwrap_err().code(), Code::Unauthenticated);
+    let mut forged = connect(&fixture.address, "otp_00000000_00").await;
+    assert_eq!(forged.list_users(ListUsersRequest {}).await.unwrap_err().code(), Code::Unauthenticated);
+
+    // Ключ только для проверки: пользователей не видит и не меняет
+    let mut verifier = fixture.client_with(&[Scope::Verify]).await;
+    let response = verifier.verify(verify(&code)).await.unwrap().into_inner();
+    assert_eq!(response.result(), VerifyResult::Granted);
+    assert_eq!(verifier.list_users(ListUsersRequest {}).await.unwrap_err().code(), Code::PermissionDenied);
+    let disable = SetUserDisabledRequest {
+        login: "Johnny".to_string(),
+        disabled: true,
+    };
+    assert_eq!(verifier.set_user_disabled(disable.clone()).await.unwrap_err().code(), Code::PermissionDenied);
+
+    let mut admin = fixture.client_with(&[Scope::ManageUsers]).await;
+    assert!(admin.set_user_disabled(disable).await.unwrap().into_inner().disabled);
+    let response = verifier.verify(verify(&code)).await.unwrap().into_inner();
+    assert!(response.message.contains("учётная запись отключена"), "{}", response.message);
+    admin.delete_user(DeleteUserRequest { login: "Bob".to_string() }).await.unwrap();
+    let missing = admin.delete_user(DeleteUserRequest { login: "Bob".to_string() }).await.unwrap_err();
+    assert_eq!(missing.code(), Code::NotFound);
+
+    // В файле ключей нет самих ключей, отозванный ключ сразу перестаёт действовать
+    let mut store = KeyStore::load(&fixture.keys).unwrap();
+    assert!(!fs::read_to_string(&fixture.keys).unwrap().contains("otp_"));
+    let id = store.clients.iter().find(|c| c.scopes == [Scope::ManageUsers]).unwrap().id.clone();
+    store.revoke(&id).unwrap();
+    store.save(&fixture.keys).unwrap();
+    let revoked = admin.delete_user(DeleteUserRequest { login: "Alice".to_string() }).await.unwrap_err();
+    assert_eq!(revoked.code(), Code::Unauthenticated);
+}
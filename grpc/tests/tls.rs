@@ -0,0 +1,92 @@
+// Служба gRPC с TLS и проверкой сертификата клиента; центры создаются в тесте
+use std::fs;
+use std::path::Path;
+
+use otp_grpc::proto::*;
+use otp_grpc::{ApiKey, OtpVerifierClient};
+use otp_server::api_keys::{KeyStore, Scope};
+use otp_server::config::Config;
+use otp_server::tls::Certificates;
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair};
+use tokio::net::TcpListener;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+
+type Authority = CertifiedIssuer<'static, KeyPair>;
+
+fn authority(name: &str) -> Authority {
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap()
+}
+
+// Сертификат и ключ в PEM для имени localhost
+fn issue(authority: &Authority, name: &str) -> (String, String) {
+    let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    let key = KeyPair::generate().unwrap();
+    (params.signed_by(&key, authority).unwrap().pem(), key.serialize_pem())
+}
+
+// Адрес службы и ключ API для чтения пользователей
+async fn start(dir: &Path, server: &Authority, clients: &Authority) -> (String, String) {
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("database.txt"), "Лапин_Е.В. Johnny AAAAE2D76510BF24 06.05.2007 21:24:30\n").unwrap();
+    let (certificate, key) = issue(server, "server");
+    fs::write(dir.join("server.crt"), certificate).unwrap();
+    fs::write(dir.join("server.key"), key).unwrap();
+    fs::write(dir.join("clients.crt"), clients.pem()).unwrap();
+
+    let mut config = Config::default();
+    config.tls.certificate = "server.crt".to_string();
+    config.tls.key = "server.key".to_string();
+    config.tls.client_ca = "clients.crt".to_string();
+    config.relative_to(dir);
+    otp_server::audit::configure(&config);
+    let mut keys = KeyStore::default();
+    let key = keys.issue("сервис", &[Scope::ReadUsers]).unwrap();
+    keys.save(&config.api.keys).unwrap();
+
+    let certificates = Certificates::load(&config.tls, &[b"h2"]).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(otp_grpc::serve_on(listener, otp_grpc::service(config), certificates));
+    (format!("https://{}", address), key)
+}
+
+async fn client(
+    (address, key): &(String, String),
+    server: &Authority,
+    identity: Option<(String, String)>,
+) -> Result<OtpVerifierClient<InterceptedService<Channel, ApiKey>>, tonic::transport::Error> {
+    let mut tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(server.pem()))
+        .domain_name("localhost");
+    if let Some((certificate, key)) = identity {
+        tls = tls.identity(Identity::from_pem(certificate, key));
+    }
+    let channel = Channel::from_shared(address.to_string()).unwrap().tls_config(tls)?.connect().await?;
+    Ok(OtpVerifierClient::with_interceptor(channel, ApiKey::new(key).unwrap()))
+}
+
+#[tokio::test]
+async fn requires_client_certificate() {
+    let dir = std::env::temp_dir().join(format!("otp_grpc_tls_{}", std::process::id()));
+    let server = authority("Учебный центр");
+    let clients = authority("Центр клиентов");
+    let service = start(&dir, &server, &clients).await;
+
+    let mut trusted = client(&service, &server, Some(issue(&clients, "сервис"))).await.unwrap();
+    let users = trusted.list_users(ListUsersRequest {}).await.unwrap().into_inner().users;
+    assert_eq!(users[0].login, "Johnny");
+
+    // Без сертификата и с сертификатом чужого центра вызовы не проходят
+    for identity in [None, Some(issue(&server, "сервис"))] {
+        if let Ok(mut stranger) = client(&service, &server, identity).await {
+            assert!(stranger.list_users(ListUsersRequest {}).await.is_err());
+        }
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
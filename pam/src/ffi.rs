@@ -0,0 +1,117 @@
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ptr;
+
+// Минимальные привязки Linux-PAM (security/pam_modules.h)
+pub const PAM_SUCCESS: c_int = 0;
+pub const PAM_SERVICE_ERR: c_int = 3;
+pub const PAM_AUTH_ERR: c_int = 7;
+pub const PAM_AUTHINFO_UNAVAIL: c_int = 9;
+pub const PAM_USER_UNKNOWN: c_int = 10;
+pub const PAM_MAXTRIES: c_int = 11;
+pub const PAM_CONV_ERR: c_int = 19;
+
+const PAM_CONV: c_int = 5;
+
+pub const PAM_PROMPT_ECHO_OFF: c_int = 1;
+pub const PAM_ERROR_MSG: c_int = 3;
+pub const PAM_TEXT_INFO: c_int = 4;
+
+#[repr(C)]
+pub struct PamHandle {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct PamMessage {
+    pub msg_style: c_int,
+    pub msg: *const c_char,
+}
+
+#[repr(C)]
+pub struct PamResponse {
+    pub resp: *mut c_char,
+    pub resp_retcode: c_int,
+}
+
+pub type ConvFn = unsafe extern "C" fn(c_int, *mut *const PamMessage, *mut *mut PamResponse, *mut c_void) -> c_int;
+
+#[repr(C)]
+pub struct PamConv {
+    pub conv: Option<ConvFn>,
+    pub appdata_ptr: *mut c_void,
+}
+
+// Пакета libpam-dev может не быть: связываемся с библиотекой времени
+// выполнения напрямую
+#[link(name = "libpam.so.0", kind = "dylib", modifiers = "+verbatim")]
+extern "C" {
+    fn pam_get_user(pamh: *mut PamHandle, user: *mut *const c_char, prompt: *const c_char) -> c_int;
+    fn pam_get_item(pamh: *const PamHandle, item_type: c_int, item: *mut *const c_void) -> c_int;
+    fn pam_syslog(pamh: *const PamHandle, priority: c_int, format: *const c_char, ...);
+}
+
+// Ошибка в системный журнал (authpriv) с именем службы и модуля: у sshd и
+// sudo поток ошибок никто не читает
+pub(crate) unsafe fn log_error(pamh: *mut PamHandle, text: &str) {
+    if let Ok(text) = CString::new(text.replace('\0', "")) {
+        pam_syslog(pamh, libc::LOG_ERR, c"%s".as_ptr(), text.as_ptr());
+    }
+}
+
+// Аргументы модуля из конфигурации PAM: argv - массив из argc строк C
+pub(crate) unsafe fn args(argc: c_int, argv: *const *const c_char) -> Vec<String> {
+    (0..argc.max(0) as usize)
+        .filter_map(|i| argv.add(i).as_ref())
+        .filter(|arg| !arg.is_null())
+        .map(|arg| CStr::from_ptr(*arg).to_string_lossy().into_owned())
+        .collect()
+}
+
+pub(crate) unsafe fn get_user(pamh: *mut PamHandle) -> Result<String, c_int> {
+    let mut user: *const c_char = ptr::null();
+    match pam_get_user(pamh, &mut user, ptr::null()) {
+        PAM_SUCCESS if !user.is_null() => Ok(CStr::from_ptr(user).to_string_lossy().into_owned()),
+        PAM_SUCCESS => Err(PAM_USER_UNKNOWN),
+        code => Err(code),
+    }
+}
+
+// Одно сообщение через функцию диалога приложения; для запроса возвращает
+// ответ пользователя. Память ответа затирается и освобождается здесь.
+pub(crate) unsafe fn converse(pamh: *mut PamHandle, style: c_int, text: &str) -> Result<Option<String>, c_int> {
+    let mut item: *const c_void = ptr::null();
+    if pam_get_item(pamh, PAM_CONV, &mut item) != PAM_SUCCESS || item.is_null() {
+        return Err(PAM_CONV_ERR);
+    }
+    let conv = &*(item as *const PamConv);
+    let Some(function) = conv.conv else {
+        return Err(PAM_CONV_ERR);
+    };
+
+    let text = CString::new(text.replace('\0', "")).map_err(|_| PAM_CONV_ERR)?;
+    let message = PamMessage { msg_style: style, msg: text.as_ptr() };
+    let mut messages = [&message as *const PamMessage];
+    let mut responses: *mut PamResponse = ptr::null_mut();
+
+    let code = function(1, messages.as_mut_ptr(), &mut responses, conv.appdata_ptr);
+    if code != PAM_SUCCESS {
+        return Err(code);
+    }
+    if responses.is_null() {
+        return Ok(None);
+    }
+
+    let response = &mut *responses;
+    let answer = if response.resp.is_null() {
+        None
+    } else {
+        let answer = CStr::from_ptr(response.resp).to_string_lossy().into_owned();
+        let len = libc::strlen(response.resp);
+        ptr::write_bytes(response.resp, 0, len);
+        libc::free(response.resp as *mut c_void);
+        Some(answer)
+    };
+    libc::free(responses as *mut c_void);
+
+    Ok(answer)
+}
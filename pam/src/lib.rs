@@ -0,0 +1,150 @@
+mod ffi;
+
+use chrono::Local;
+use std::ffi::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use ffi::*;
+use otp_server::audit;
+use otp_server::auth::Challenge;
+use otp_server::config::Config;
+use otp_server::daemon::Client;
+use otp_server::verifier::{self, Reply};
+
+// Модуль PAM: запрашивает одноразовый пароль и проверяет его по базе сервера
+// или через демон otp_server serve. Аргументы в конфигурации PAM:
+//   config=<файл>   настройки сервера, проверка в процессе (по умолчанию
+//                   /etc/otp/server.toml; пути в нём - от каталога файла)
+//   socket=<путь>   проверка через демон, база модулю не нужна
+pub const DEFAULT_CONFIG: &str = "/etc/otp/server.toml";
+
+const PROMPT: &str = "Одноразовый пароль: ";
+
+enum Backend {
+    Direct { config: Box<Config>, challenge: Option<Challenge> },
+    Daemon(Client),
+}
+
+impl Backend {
+    fn open(args: &[String]) -> Result<Self, String> {
+        let option = |name: &str| args.iter().find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='));
+
+        if let Some(socket) = option("socket") {
+            return Client::connect(socket).map(Backend::Daemon);
+        }
+
+        let path = Path::new(option("config").unwrap_or(DEFAULT_CONFIG));
+        let mut config = Config::load(path)?;
+        config.relative_to(path.parent().unwrap_or(Path::new("/")));
+        audit::configure(&config);
+        Ok(Backend::Direct { config: Box::new(config), challenge: None })
+    }
+
+    fn challenge(&mut self, login: &str) -> Result<Option<String>, String> {
+        match self {
+            Backend::Direct { config, challenge } => {
+                let (text, issued) = verifier::challenge(config, login)?;
+                *challenge = issued;
+                Ok(text)
+            }
+            Backend::Daemon(client) => client.challenge(login),
+        }
+    }
+
+    fn verify(&mut self, login: &str, password: &str) -> Reply {
+        match self {
+            Backend::Direct { config, challenge } => {
+                match verifier::verify(config, challenge.take(), login, password, Local::now().naive_local()) {
+                    Ok(outcome) => Reply::from_outcome(&outcome, config.denial),
+                    Err(e) => Reply::Error(e),
+                }
+            }
+            Backend::Daemon(client) => client.authenticate(login, password).unwrap_or_else(Reply::Error),
+        }
+    }
+}
+
+/// Точка входа libpam для `auth`.
+///
+/// # Safety
+/// Вызывается libpam с действительным дескриптором и аргументами модуля.
+#[no_mangle]
+pub unsafe extern "C" fn pam_sm_authenticate(
+    pamh: *mut PamHandle,
+    _flags: c_int,
+    argc: c_int,
+    argv: *const *const c_char,
+) -> c_int {
+    let args = ffi::args(argc, argv);
+    // Паника не должна уходить через границу FFI в sshd
+    catch_unwind(AssertUnwindSafe(|| authenticate(pamh, &args))).unwrap_or(PAM_SERVICE_ERR)
+}
+
+/// Учётные данные модуль не выдаёт.
+///
+/// # Safety
+/// Вызывается libpam.
+#[no_mangle]
+pub unsafe extern "C" fn pam_sm_setcred(
+    _pamh: *mut PamHandle,
+    _flags: c_int,
+    _argc: c_int,
+    _argv: *const *const c_char,
+) -> c_int {
+    PAM_SUCCESS
+}
+
+unsafe fn authenticate(pamh: *mut PamHandle, args: &[String]) -> c_int {
+    let login = match ffi::get_user(pamh) {
+        Ok(login) => login,
+        Err(code) => return code,
+    };
+
+    // Ошибки настройки видит администратор в журнале службы, не пользователь
+    let mut backend = match Backend::open(args) {
+        Ok(backend) => backend,
+        Err(e) => {
+            ffi::log_error(pamh, &e);
+            return PAM_AUTHINFO_UNAVAIL;
+        }
+    };
+
+    // При рассинхронизации счётчика запрашивается ещё один пароль
+    for attempt in 0..2 {
+        let prompt = match backend.challenge(&login) {
+            Ok(Some(challenge)) => format!("{}\n{}", challenge, PROMPT),
+            Ok(None) => PROMPT.to_string(),
+            Err(e) => {
+                ffi::log_error(pamh, &e);
+                return PAM_AUTHINFO_UNAVAIL;
+            }
+        };
+
+        let password = match ffi::converse(pamh, PAM_PROMPT_ECHO_OFF, &prompt) {
+            Ok(Some(password)) => password,
+            Ok(None) => return PAM_CONV_ERR,
+            Err(code) => return code,
+        };
+
+        let reply = backend.verify(&login, password.trim());
+        let (style, code) = match &reply {
+            Reply::Granted(_) => return PAM_SUCCESS,
+            Reply::Next(_) if attempt == 0 => (PAM_TEXT_INFO, None),
+            Reply::Locked(_) => (PAM_ERROR_MSG, Some(PAM_MAXTRIES)),
+            Reply::Unknown(_) => (PAM_ERROR_MSG, Some(PAM_USER_UNKNOWN)),
+            Reply::Next(_) | Reply::Denied(_) => (PAM_ERROR_MSG, Some(PAM_AUTH_ERR)),
+            Reply::Error(e) => {
+                ffi::log_error(pamh, e);
+                return PAM_AUTHINFO_UNAVAIL;
+            }
+        };
+
+        let _ = ffi::converse(pamh, style, reply.text());
+        if let Some(code) = code {
+            return code;
+        }
+    }
+
+    PAM_AUTH_ERR
+}
@@ -0,0 +1,307 @@
+// Проверка модуля так же, как это делает pamtester: настоящий libpam
+// загружает собранный libpam_otp.so по файлу службы во временном каталоге
+// (pam_start_confdir), пароль вводит функция диалога теста. Вход в систему
+// и права root не нужны.
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use otp_core::cipher::{encrypt, time_block, Algorithm};
+use otp_core::format::{format_password, OutputFormat};
+use otp_core::skey::{self, ChainHash};
+
+const PAM_SUCCESS: c_int = 0;
+const PAM_AUTH_ERR: c_int = 7;
+const PAM_AUTHINFO_UNAVAIL: c_int = 9;
+const PAM_USER_UNKNOWN: c_int = 10;
+const PAM_MAXTRIES: c_int = 11;
+const PAM_PROMPT_ECHO_OFF: c_int = 1;
+const PAM_PROMPT_ECHO_ON: c_int = 2;
+
+const BS2: &str = "AAAAE2D76510BF24";
+const EPOCH: &str = "06.05.2007 21:24:30";
+
+#[repr(C)]
+struct PamMessage {
+    msg_style: c_int,
+    msg: *const c_char,
+}
+
+#[repr(C)]
+struct PamResponse {
+    resp: *mut c_char,
+    resp_retcode: c_int,
+}
+
+#[repr(C)]
+struct PamConv {
+    conv: unsafe extern "C" fn(c_int, *mut *const PamMessage, *mut *mut PamResponse, *mut c_void) -> c_int,
+    appdata_ptr: *mut c_void,
+}
+
+#[link(name = "libpam.so.0", kind = "dylib", modifiers = "+verbatim")]
+extern "C" {
+    fn pam_start_confdir(
+        service: *const c_char,
+        user: *const c_char,
+        conv: *const PamConv,
+        confdir: *const c_char,
+        pamh: *mut *mut c_void,
+    ) -> c_int;
+    fn pam_authenticate(pamh: *mut c_void, flags: c_int) -> c_int;
+    fn pam_end(pamh: *mut c_void, status: c_int) -> c_int;
+}
+
+// Ответы на запросы пароля по порядку и всё, что модуль показал
+#[derive(Default)]
+struct Dialog {
+    answers: Vec<String>,
+    shown: Vec<String>,
+}
+
+unsafe extern "C" fn conversation(
+    count: c_int,
+    messages: *mut *const PamMessage,
+    responses: *mut *mut PamResponse,
+    appdata: *mut c_void,
+) -> c_int {
+    let dialog = &mut *(appdata as *mut Dialog);
+    let replies = libc::calloc(count as usize, std::mem::size_of::<PamResponse>()) as *mut PamResponse;
+
+    for i in 0..count as usize {
+        let message = &**messages.add(i);
+        dialog.shown.push(CStr::from_ptr(message.msg).to_string_lossy().into_owned());
+        if message.msg_style == PAM_PROMPT_ECHO_OFF || message.msg_style == PAM_PROMPT_ECHO_ON {
+            let answer = if dialog.answers.is_empty() { String::new() } else { dialog.answers.remove(0) };
+            let answer = CString::new(answer).unwrap();
+            (*replies.add(i)).resp = libc::strdup(answer.as_ptr());
+        }
+    }
+
+    *responses = replies;
+    PAM_SUCCESS
+}
+
+struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str, database: &str, settings: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("pam_otp_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("database.txt"), database).unwrap();
+        fs::write(dir.join("server.toml"), settings).unwrap();
+        Fixture { dir }
+    }
+
+    // Файл службы PAM с модулем и его аргументом
+    fn service(&self, argument: &str) {
+        let line = format!("auth required {} {}\n", module().display(), argument);
+        fs::write(self.dir.join("otp-test"), line).unwrap();
+    }
+
+    fn direct(&self) {
+        self.service(&format!("config={}", self.dir.join("server.toml").display()));
+    }
+
+    fn authenticate(&self, login: &str, answers: &[&str]) -> (c_int, Vec<String>) {
+        let mut dialog = Dialog {
+            answers: answers.iter().map(|a| a.to_string()).collect(),
+            shown: Vec::new(),
+        };
+        let conv = PamConv {
+            conv: conversation,
+            appdata_ptr: &mut dialog as *mut Dialog as *mut c_void,
+        };
+        let service = CString::new("otp-test").unwrap();
+        let user = CString::new(login).unwrap();
+        let confdir = CString::new(self.dir.to_str().unwrap()).unwrap();
+
+        let code = unsafe {
+            let mut pamh = ptr::null_mut();
+            assert_eq!(pam_start_confdir(service.as_ptr(), user.as_ptr(), &conv, confdir.as_ptr(), &mut pamh), PAM_SUCCESS);
+            let code = pam_authenticate(pamh, 0);
+            pam_end(pamh, code);
+            code
+        };
+        (code, dialog.shown)
+    }
+
+    fn database(&self) -> String {
+        fs::read_to_string(self.dir.join("database.txt")).unwrap()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// libpam_otp.so лежит в target/<профиль> рядом с каталогом deps теста
+fn module() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let module = exe.parent().and_then(Path::parent).unwrap().join("libpam_otp.so");
+    assert!(module.exists(), "нет {}", module.display());
+    module
+}
+
+fn time_password() -> String {
+    let epoch = chrono::NaiveDateTime::parse_from_str(EPOCH, "%d.%m.%Y %H:%M:%S").unwrap();
+    let elapsed = (Local::now().naive_local() - epoch).num_seconds() as u64;
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, elapsed), BS2).unwrap();
+    format_password(OutputFormat::Hex, &hex).unwrap()
+}
+
+fn time_user() -> String {
+    format!("Лапин_Е.В. Johnny {} {}\n", BS2, EPOCH)
+}
+
+#[test]
+fn accepts_time_password_once() {
+    let fixture = Fixture::new("replay", &time_user(), "");
+    fixture.direct();
+    let password = time_password();
+
+    let (code, shown) = fixture.authenticate("Johnny", &[&password]);
+    assert_eq!(code, PAM_SUCCESS, "{:?}", shown);
+    assert_eq!(shown, ["Одноразовый пароль: "]);
+
+    let (code, shown) = fixture.authenticate("Johnny", &[&password]);
+    assert_eq!(code, PAM_AUTH_ERR);
+    assert!(shown.iter().any(|s| s.contains("пароль уже использован")), "{:?}", shown);
+}
+
+#[test]
+fn rejects_wrong_password_and_unknown_user() {
+    let fixture = Fixture::new("wrong", &time_user(), "");
+    fixture.direct();
+
+    let (code, shown) = fixture.authenticate("Johnny", &["0000000000000000"]);
+    assert_eq!(code, PAM_AUTH_ERR);
+    assert!(shown.iter().any(|s| s.contains("неверный пароль")), "{:?}", shown);
+
+    let (code, _) = fixture.authenticate("nobody", &["0000000000000000"]);
+    assert_eq!(code, PAM_USER_UNKNOWN);
+}
+
+#[test]
+fn locks_out_after_failures() {
+    let fixture = Fixture::new("lockout", &time_user(), "[lockout]\nattempts = 2\nduration = 60\n");
+    fixture.direct();
+
+    for _ in 0..2 {
+        assert_eq!(fixture.authenticate("Johnny", &["0000000000000000"]).0, PAM_AUTH_ERR);
+    }
+
+    // Блокировка сохраняется между процессами в файле состояния
+    let (code, shown) = fixture.authenticate("Johnny", &[&time_password()]);
+    assert_eq!(code, PAM_MAXTRIES);
+    assert!(shown.iter().any(|s| s.contains("заблокирован")), "{:?}", shown);
+    assert!(fixture.dir.join("state.toml").exists());
+}
+
+#[test]
+fn generic_denial_hides_reason() {
+    let fixture = Fixture::new("generic", &time_user(), "denial = \"generic\"\n");
+    fixture.direct();
+
+    let (code, shown) = fixture.authenticate("Johnny", &["0000000000000000"]);
+    assert_eq!(code, PAM_AUTH_ERR);
+    assert_eq!(shown.last().map(String::as_str), Some("❌ Доступ запрещен"));
+}
+
+#[test]
+fn shows_skey_challenge_and_advances_chain() {
+    let database = format!("Лапин_Е.В. Johnny {} {} mode=skey seed=ke1234 seq=499\n", BS2, EPOCH);
+    let fixture = Fixture::new("skey", &database, "");
+    fixture.direct();
+    let words = skey::to_words(skey::otp(ChainHash::Md5, "ke1234", BS2, 499));
+
+    let (code, shown) = fixture.authenticate("Johnny", &[&words]);
+    assert_eq!(code, PAM_SUCCESS, "{:?}", shown);
+    assert_eq!(shown[0], "otp-md5 499 ke1234\nОдноразовый пароль: ");
+    assert!(fixture.database().contains("seq=498"), "{}", fixture.database());
+}
+
+#[test]
+fn accepts_skey_words_shaped_like_recovery_code() {
+    // Шесть слов ответа S/KEY - 12 букв base32 без разделителей групп
+    let response = 0x0080_1002_0040_0803;
+    let words = skey::to_words(response);
+    assert_eq!(words, "AD AD AD AD AD AN");
+    let last = skey::to_hex(skey::step(ChainHash::Md5, response));
+    let database = format!("Лапин_Е.В. Johnny {} {} mode=skey seed=ke1234 seq=499 last={}\n", BS2, EPOCH, last);
+    let fixture = Fixture::new("skey_words", &database, "");
+    fixture.direct();
+
+    let (code, shown) = fixture.authenticate("Johnny", &[&words]);
+    assert_eq!(code, PAM_SUCCESS, "{:?}", shown);
+    assert!(fixture.database().contains("seq=498"), "{}", fixture.database());
+}
+
+#[test]
+fn rejects_counter_at_limit() {
+    let database = format!("Лапин_Е.В. Johnny {} {} mode=counter counter={}\n", BS2, EPOCH, u64::MAX);
+    let fixture = Fixture::new("limit", &database, "");
+    fixture.direct();
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, u64::MAX), BS2).unwrap();
+    let password = format_password(OutputFormat::Hex, &hex).unwrap();
+
+    // Следующего значения счётчика нет: пароль отвергается, база не меняется
+    let (code, shown) = fixture.authenticate("Johnny", &[&password]);
+    assert_eq!(code, PAM_AUTH_ERR, "{:?}", shown);
+    assert_eq!(fixture.database(), database);
+}
+
+#[test]
+fn verifies_through_daemon() {
+    let fixture = Fixture::new("daemon", &time_user(), "");
+    let socket = fixture.dir.join("otp.sock");
+
+    let mut config = otp_server::config::Config::load(fixture.dir.join("server.toml")).unwrap();
+    config.relative_to(&fixture.dir);
+    config.daemon.socket = socket.to_string_lossy().into_owned();
+    otp_server::audit::configure(&config);
+    thread::spawn(move || otp_server::daemon::serve(config));
+    for _ in 0..50 {
+        if socket.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    // Сокет появляется на месте уже с правами 0600
+    assert_eq!(fs::metadata(&socket).unwrap().permissions().mode() & 0o777, 0o600);
+
+    // Логин с пробелом не отправляется и не принимается демоном
+    let mut client = otp_server::daemon::Client::connect(&socket).unwrap();
+    assert!(client.authenticate("Johnny x", "0000000000000000").is_err());
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    writeln!(stream, "CHALLENGE Johnny x").unwrap();
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).unwrap();
+    assert!(reply.starts_with("ERROR"), "{}", reply);
+
+    fixture.service(&format!("socket={}", socket.display()));
+    let password = time_password();
+    assert_eq!(fixture.authenticate("Johnny", &[&password]).0, PAM_SUCCESS);
+    assert_eq!(fixture.authenticate("Johnny", &[&password]).0, PAM_AUTH_ERR);
+}
+
+#[test]
+fn reports_missing_configuration() {
+    let fixture = Fixture::new("missing", &time_user(), "");
+    fixture.service(&format!("socket={}", fixture.dir.join("absent.sock").display()));
+
+    assert_eq!(fixture.authenticate("Johnny", &["0000000000000000"]).0, PAM_AUTHINFO_UNAVAIL);
+}
@@ -0,0 +1,232 @@
+use chrono::Local;
+use eframe::egui;
+
+use otp_server::admins::{self, AdminStore, Role};
+use otp_server::audit;
+use otp_server::config::Config;
+
+// Вход в интерфейс: логин, пароль администратора и одноразовый пароль. Пока
+// администраторов нет, тот же экран назначает первого.
+#[derive(Default)]
+pub struct LoginScreen {
+    // Файл администраторов читается при первом показе, после назначения и
+    // после входа: следующий показ экрана видит изменения из панели
+    store: Option<Result<AdminStore, String>>,
+    login: String,
+    password: String,
+    confirm: String,
+    otp: String,
+    message: String,
+}
+
+impl LoginScreen {
+    // Вошедший логин и его роль
+    pub fn show(&mut self, ui: &mut egui::Ui, config: &Config) -> Option<(String, Role)> {
+        let store = match self.store.get_or_insert_with(|| AdminStore::load(&config.gui.admins)) {
+            Ok(store) => store,
+            Err(e) => {
+                ui.label(egui::RichText::new(e.as_str()).color(egui::Color32::from_rgb(114, 28, 36)));
+                if ui.button("🔄 Прочитать снова").clicked() {
+                    self.store = None;
+                }
+                return None;
+            }
+        };
+        let enroll = store.admins.is_empty();
+
+        if enroll {
+            ui.label(egui::RichText::new("Первый запуск: назначьте администратора").strong());
+            ui.label("Логин - пользователь из базы; вход подтверждается его одноразовым паролем.");
+        } else {
+            ui.label(egui::RichText::new("Вход администратора").strong());
+        }
+        ui.add_space(5.0);
+
+        let mut submit = false;
+        egui::Grid::new("login_grid").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+            ui.label("Логин:");
+            ui.add(egui::TextEdit::singleline(&mut self.login).desired_width(200.0));
+            ui.end_row();
+            ui.label("Пароль администратора:");
+            ui.add(egui::TextEdit::singleline(&mut self.password).password(true).desired_width(200.0));
+            ui.end_row();
+            if enroll {
+                ui.label("Пароль ещё раз:");
+                ui.add(egui::TextEdit::singleline(&mut self.confirm).password(true).desired_width(200.0));
+                ui.end_row();
+            }
+            ui.label("Одноразовый пароль:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.otp)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(200.0),
+            );
+            submit = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.end_row();
+        });
+
+        ui.add_space(5.0);
+        submit |= ui.button(if enroll { "👤 Назначить и войти" } else { "🔓 Войти" }).clicked();
+        if !self.message.is_empty() {
+            ui.label(egui::RichText::new(&self.message).color(egui::Color32::from_rgb(114, 28, 36)));
+        }
+        if !submit {
+            return None;
+        }
+
+        let login = self.login.trim().to_string();
+        let now = Local::now().naive_local();
+        let result = if !enroll {
+            admins::login(config, store, &login, &self.password, self.otp.trim(), now)
+        } else if self.password != self.confirm {
+            Err("пароли не совпадают".to_string())
+        } else {
+            admins::enroll(config, &login, &self.password, self.otp.trim(), now).map(|_| Role::Admin)
+        };
+
+        // Пароли не остаются в полях ни после входа, ни после отказа
+        self.password.clear();
+        self.confirm.clear();
+        self.otp.clear();
+        match result {
+            Ok(role) => {
+                self.store = None;
+                self.message.clear();
+                Some((login, role))
+            }
+            Err(e) => {
+                // Администратора могли назначить в другом окне
+                if enroll {
+                    self.store = None;
+                }
+                self.message = e;
+                None
+            }
+        }
+    }
+}
+
+// Список администраторов для роли «администратор»
+#[derive(Default)]
+pub struct AdminPanel {
+    store: Option<AdminStore>,
+    login: String,
+    role: Option<Role>,
+    password: String,
+    pending_remove: Option<String>,
+    message: String,
+}
+
+impl AdminPanel {
+    pub fn show(&mut self, ui: &mut egui::Ui, path: &str, current: &str) {
+        let store = match self.store.take().map(Ok).unwrap_or_else(|| AdminStore::load(path)) {
+            Ok(store) => store,
+            Err(e) => {
+                ui.label(e);
+                return;
+            }
+        };
+
+        egui::Grid::new("admins_grid").striped(true).num_columns(3).spacing([12.0, 4.0]).show(ui, |ui| {
+            for admin in &store.admins {
+                ui.label(egui::RichText::new(&admin.login).monospace());
+                ui.label(admin.role.title());
+                ui.horizontal(|ui| {
+                    if ui.small_button("✏").on_hover_text("Роль и пароль").clicked() {
+                        self.login = admin.login.clone();
+                        self.role = Some(admin.role);
+                    }
+                    if admin.login != current && ui.small_button("🗑").on_hover_text("Удалить").clicked() {
+                        self.pending_remove = Some(admin.login.clone());
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Логин:");
+            ui.add(egui::TextEdit::singleline(&mut self.login).desired_width(120.0));
+            let role = self.role.get_or_insert(Role::Operator);
+            egui::ComboBox::from_id_source("admin_role")
+                .selected_text(role.title())
+                .show_ui(ui, |ui| {
+                    for option in Role::ALL {
+                        ui.selectable_value(role, option, option.title());
+                    }
+                });
+            ui.label("пароль:");
+            ui.add(egui::TextEdit::singleline(&mut self.password).password(true).desired_width(120.0));
+        });
+        let save = ui.button("💾 Назначить").clicked();
+        if !self.message.is_empty() {
+            ui.label(egui::RichText::new(&self.message).small());
+        }
+
+        self.store = Some(store);
+        if save {
+            self.save(path);
+        }
+        self.remove_window(ui.ctx(), path);
+    }
+
+    fn save(&mut self, path: &str) {
+        let login = self.login.trim().to_string();
+        let role = self.role.unwrap_or(Role::Operator);
+        let result = AdminStore::load(path).and_then(|mut store| {
+            store.set(&login, role, &self.password)?;
+            store.save(path)?;
+            Ok(store)
+        });
+        self.password.clear();
+        match result {
+            Ok(store) => {
+                audit::record(&login, &format!("назначен в интерфейсе сервера: {}", role.title()));
+                self.store = Some(store);
+                self.login.clear();
+                self.message.clear();
+            }
+            Err(e) => self.message = e,
+        }
+    }
+
+    fn remove_window(&mut self, ctx: &egui::Context, path: &str) {
+        let Some(login) = self.pending_remove.clone() else {
+            return;
+        };
+
+        let mut confirm = false;
+        let mut cancel = false;
+
+        egui::Window::new("Удаление администратора")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Лишить {} доступа к интерфейсу?", login));
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    confirm = ui.button("🗑️ Удалить").clicked();
+                    cancel = ui.button("Отмена").clicked();
+                });
+            });
+
+        if confirm {
+            let result = AdminStore::load(path).and_then(|mut store| {
+                store.remove(&login)?;
+                store.save(path)?;
+                Ok(store)
+            });
+            match result {
+                Ok(store) => {
+                    audit::record(&login, "удалён из администраторов интерфейса сервера");
+                    self.store = Some(store);
+                }
+                Err(e) => self.message = e,
+            }
+        }
+        if confirm || cancel {
+            self.pending_remove = None;
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::audit;
+use crate::auth::{Denial, Outcome};
+use crate::config::Config;
+use crate::verifier;
+
+// Администраторы графического интерфейса сервера. Вход - пароль
+// администратора и одноразовый пароль того же логина из базы пользователей
+// (с окнами, защитой от повтора и блокировкой, как у остальных проверок).
+// В admins.toml хранятся только хеши паролей Argon2.
+pub const ADMINS_FILE: &str = "admins.toml";
+
+pub const MIN_PASSWORD: usize = 8;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // Проверка аутентификации пользователей
+    Operator,
+    // Чтение журнала
+    Auditor,
+    // Всё, включая пользователей, BS2, настройки и ключи
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Operator, Role::Auditor, Role::Admin];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Role::Operator => "оператор",
+            Role::Auditor => "аудитор",
+            Role::Admin => "администратор",
+        }
+    }
+
+    pub fn can_test(self) -> bool {
+        matches!(self, Role::Operator | Role::Admin)
+    }
+
+    pub fn can_read_log(self) -> bool {
+        matches!(self, Role::Auditor | Role::Admin)
+    }
+
+    // Пользователи, BS2, настройки, ключи API и администраторы
+    pub fn can_manage(self) -> bool {
+        self == Role::Admin
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Admin {
+    pub login: String,
+    pub role: Role,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+pub struct AdminStore {
+    #[serde(default, rename = "admin")]
+    pub admins: Vec<Admin>,
+}
+
+impl AdminStore {
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path, e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let temp = format!("{}.tmp", path);
+        fs::write(&temp, text)
+            .and_then(|_| fs::rename(&temp, path))
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Новый администратор или новые роль и пароль существующего
+    pub fn set(&mut self, login: &str, role: Role, password: &str) -> Result<(), String> {
+        if password.chars().count() < MIN_PASSWORD {
+            return Err(format!("пароль не короче {} символов", MIN_PASSWORD));
+        }
+        if self.is_last_admin(login) && role != Role::Admin {
+            return Err("последний администратор должен остаться администратором".to_string());
+        }
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| format!("Argon2: {}", e))?
+            .to_string();
+
+        match self.admins.iter_mut().find(|admin| admin.login == login) {
+            Some(admin) => {
+                admin.role = role;
+                admin.password = hash;
+            }
+            None => self.admins.push(Admin {
+                login: login.to_string(),
+                role,
+                password: hash,
+            }),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, login: &str) -> Result<(), String> {
+        let index = self
+            .admins
+            .iter()
+            .position(|admin| admin.login == login)
+            .ok_or_else(|| format!("администратора {} нет", login))?;
+        if self.is_last_admin(login) {
+            return Err("последнего администратора удалить нельзя".to_string());
+        }
+        self.admins.remove(index);
+        Ok(())
+    }
+
+    fn is_last_admin(&self, login: &str) -> bool {
+        let mut admins = self.admins.iter().filter(|admin| admin.role == Role::Admin);
+        matches!((admins.next(), admins.next()), (Some(admin), None) if admin.login == login)
+    }
+
+    fn password_matches(&self, login: &str, password: &str) -> Option<Role> {
+        let admin = self.admins.iter().find(|admin| admin.login == login)?;
+        let hash = PasswordHash::new(&admin.password).ok()?;
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .ok()
+            .map(|_| admin.role)
+    }
+}
+
+// Вход в интерфейс. Пароль администратора проверяется первым, его неудача
+// считается блокировкой логина вместе с неверными одноразовыми паролями, а
+// одноразовый пароль при этом не тратится. Код восстановления вторым
+// фактором не принимается.
+pub fn login(
+    config: &Config,
+    store: &AdminStore,
+    login: &str,
+    password: &str,
+    otp: &str,
+    now: NaiveDateTime,
+) -> Result<Role, String> {
+    let role = store.password_matches(login, password);
+    let outcome = verifier::verify_admin(config, login, role.is_some(), otp, now)?;
+    match (outcome, role) {
+        (Outcome::Granted { .. }, Some(role)) => {
+            audit::record(login, &format!("вход в интерфейс сервера: {}", role.title()));
+            Ok(role)
+        }
+        (Outcome::Denied(Denial::WrongAdminPassword), _) => Err("неверный логин или пароль администратора".to_string()),
+        (outcome, _) => Err(outcome.reply(config.denial)),
+    }
+}
+
+// Первый администратор, пока файл пуст: логин должен быть пользователем базы
+// и подтвердить вход одноразовым паролем
+pub fn enroll(config: &Config, login: &str, password: &str, otp: &str, now: NaiveDateTime) -> Result<AdminStore, String> {
+    let path = &config.gui.admins;
+    let mut store = AdminStore::load(path)?;
+    if !store.admins.is_empty() {
+        return Err("администраторы уже есть, нужен вход".to_string());
+    }
+    if password.chars().count() < MIN_PASSWORD {
+        return Err(format!("пароль не короче {} символов", MIN_PASSWORD));
+    }
+
+    let outcome = verifier::verify_admin(config, login, true, otp, now)?;
+    if !matches!(outcome, Outcome::Granted { .. }) {
+        return Err(outcome.reply(config.denial));
+    }
+    store.set(login, Role::Admin, password)?;
+    store.save(path)?;
+    audit::record(login, "назначен первый администратор интерфейса сервера");
+    Ok(store)
+}
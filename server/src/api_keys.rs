@@ -0,0 +1,181 @@
+use chrono::Local;
+use otp_core::format::passwords_equal;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+
+// Ключи API вызывающих сервисов (служба gRPC). Ключ показывается один раз при
+// выдаче; в api_keys.toml хранятся только его номер и SHA-256. Ключ -
+// 256 случайных бит, поэтому медленный хеш вроде Argon2 не нужен и не
+// замедляет каждый вызов.
+pub const KEYS_FILE: &str = "api_keys.toml";
+
+const PREFIX: &str = "otp";
+
+// Что разрешено клиенту
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    // Verify и Resync
+    Verify,
+    // GetUser и ListUsers
+    ReadUsers,
+    // Отключение и удаление пользователей
+    ManageUsers,
+    // Поток событий журнала
+    ReadAudit,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::Verify, Scope::ReadUsers, Scope::ManageUsers, Scope::ReadAudit];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Verify => "verify",
+            Scope::ReadUsers => "read_users",
+            Scope::ManageUsers => "manage_users",
+            Scope::ReadAudit => "read_audit",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Scope::Verify => "проверка паролей",
+            Scope::ReadUsers => "чтение пользователей",
+            Scope::ManageUsers => "управление пользователями",
+            Scope::ReadAudit => "чтение журнала",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.name() == name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ApiClient {
+    pub name: String,
+    // Открытая часть ключа, по ней клиент находится и отзывается
+    pub id: String,
+    // SHA-256 всего ключа, HEX
+    pub hash: String,
+    pub scopes: Vec<Scope>,
+    pub created: String,
+}
+
+impl ApiClient {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeyError {
+    // Ключ не передан
+    Missing,
+    // Неизвестный, отозванный или искажённый ключ
+    Invalid,
+    // Ключ действует, но без нужного разрешения
+    Forbidden(Scope),
+}
+
+impl std::fmt::Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::Missing => write!(f, "нужен ключ API"),
+            KeyError::Invalid => write!(f, "неверный ключ API"),
+            KeyError::Forbidden(scope) => write!(f, "ключу API не разрешено: {}", scope.title()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+pub struct KeyStore {
+    #[serde(default, rename = "client")]
+    pub clients: Vec<ApiClient>,
+}
+
+impl KeyStore {
+    // Нет файла - нет ни одного клиента
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path, e)),
+        }
+    }
+
+    // Через временный файл, как state.toml: служба читает файл при каждом вызове
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let temp = format!("{}.tmp", path);
+        fs::write(&temp, text)
+            .and_then(|_| fs::rename(&temp, path))
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Новый клиент; возвращается открытый ключ, который больше нигде не хранится
+    pub fn issue(&mut self, name: &str, scopes: &[Scope]) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            return Err("имя клиента без пробелов".to_string());
+        }
+        if self.clients.iter().any(|client| client.name == name) {
+            return Err(format!("клиент {} уже есть", name));
+        }
+        if scopes.is_empty() {
+            return Err("не выбрано ни одного разрешения".to_string());
+        }
+
+        let mut id = [0u8; 4];
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut id);
+        OsRng.fill_bytes(&mut secret);
+        let id = hex::encode(id);
+        let key = format!("{}_{}_{}", PREFIX, id, hex::encode(secret));
+
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+        self.clients.push(ApiClient {
+            name: name.to_string(),
+            id,
+            hash: digest(&key),
+            scopes,
+            created: Local::now().format("%d.%m.%Y %H:%M:%S").to_string(),
+        });
+        Ok(key)
+    }
+
+    pub fn revoke(&mut self, id: &str) -> Option<ApiClient> {
+        let index = self.clients.iter().position(|client| client.id == id)?;
+        Some(self.clients.remove(index))
+    }
+
+    // Клиент по ключу с проверкой разрешения
+    pub fn authorize(&self, key: Option<&str>, scope: Scope) -> Result<&ApiClient, KeyError> {
+        let key = key.map(str::trim).filter(|key| !key.is_empty()).ok_or(KeyError::Missing)?;
+        let id = key_id(key).ok_or(KeyError::Invalid)?;
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id == id && passwords_equal(&client.hash, &digest(key)))
+            .ok_or(KeyError::Invalid)?;
+        if !client.allows(scope) {
+            return Err(KeyError::Forbidden(scope));
+        }
+        Ok(client)
+    }
+}
+
+// otp_<номер>_<секрет>
+fn key_id(key: &str) -> Option<&str> {
+    let rest = key.strip_prefix(PREFIX)?.strip_prefix('_')?;
+    rest.split_once('_').map(|(id, _)| id)
+}
+
+fn digest(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
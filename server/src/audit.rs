@@ -0,0 +1,220 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use encoding_rs::WINDOWS_1251;
+use otp_core::text::{detect, TextEncoding};
+
+use crate::config::{Config, LogConfig};
+
+// Журнал событий безопасности: ДД.ММ.ГГГГ ЧЧ:ММ:СС логин событие
+pub const AUDIT_FILE: &str = "audit.log";
+
+// Назначение журнала из настроек; до configure() - audit.log в Windows-1251
+static DESTINATION: RwLock<Option<(LogConfig, TextEncoding)>> = RwLock::new(None);
+
+// Как часто подписка проверяет, не дописан ли файл журнала
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Подписчики на события этого процесса, когда журнал в файл не пишется
+static SUBSCRIBERS: Mutex<Vec<Sender<Event>>> = Mutex::new(Vec::new());
+
+// Событие журнала; granted - результат попытки входа, None - прочие события
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub time: DateTime<Local>,
+    pub login: String,
+    pub text: String,
+    pub granted: Option<bool>,
+}
+
+pub fn configure(config: &Config) {
+    // В режиме auto журнал продолжается в кодировке существующего файла,
+    // новый журнал пишется в Windows-1251
+    let encoding = match config.encoding {
+        TextEncoding::Auto => std::fs::read(&config.log.audit).map(|bytes| detect(&bytes)).unwrap_or(TextEncoding::Windows1251),
+        encoding => encoding,
+    };
+
+    if let Ok(mut destination) = DESTINATION.write() {
+        *destination = Some((config.log.clone(), encoding));
+    }
+}
+
+// Получатель событий журнала; с его удалением поток чтения файла
+// завершается на следующей проверке, не дожидаясь нового события
+pub struct Subscription {
+    receiver: Receiver<Event>,
+    active: Arc<AtomicBool>,
+}
+
+impl Deref for Subscription {
+    type Target = Receiver<Event>;
+
+    fn deref(&self) -> &Receiver<Event> {
+        &self.receiver
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.active.store(false, Ordering::Relaxed);
+    }
+}
+
+// Новые события журнала по мере записи; подписка снимается, когда
+// получатель удалён. Файл журнала общий для модуля PAM, демона, прокси LDAP,
+// служб и интерфейса сервера, поэтому подписка читает его дописываемый
+// конец и видит события всех процессов. Без файла (log.audit пуст) видны
+// только события этого процесса.
+pub fn subscribe() -> Subscription {
+    let (sender, receiver) = channel();
+    let active = Arc::new(AtomicBool::new(true));
+    let (log, encoding) = destination();
+
+    if log.audit.is_empty() {
+        if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+            subscribers.push(sender);
+        }
+    } else {
+        // Конец файла запоминается до возврата: события после подписки не
+        // теряются, даже если поток чтения запустится позже
+        let position = fs::metadata(&log.audit).map(|metadata| metadata.len()).unwrap_or(0);
+        let active = active.clone();
+        thread::spawn(move || follow(&log.audit, encoding, position, sender, &active));
+    }
+    Subscription { receiver, active }
+}
+
+// Чтение строк, дописанных в журнал после position, пока подписка не удалена
+fn follow(path: &str, encoding: TextEncoding, mut position: u64, sender: Sender<Event>, active: &AtomicBool) {
+    let mut pending = Vec::new();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        if !active.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(mut file) = File::open(path) else {
+            continue;
+        };
+        let length = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        // Журнал обрезан или заменён новым: чтение с начала
+        if length < position {
+            position = 0;
+            pending.clear();
+        }
+        if length == position || file.seek(SeekFrom::Start(position)).is_err() {
+            continue;
+        }
+        let Ok(read) = file.read_to_end(&mut pending) else {
+            continue;
+        };
+        position += read as u64;
+
+        // Неполная последняя строка ждёт следующего чтения
+        while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let Some(event) = parse_line(&encoding.decode(&line).text) else {
+                continue;
+            };
+            if sender.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// Строка журнала: ДД.ММ.ГГГГ ЧЧ:ММ:СС логин событие. Результаты попыток
+// входа записываются со слов «вход разрешён» и «отказ».
+fn parse_line(line: &str) -> Option<Event> {
+    let mut parts = line.trim_end_matches(['\r', '\n']).splitn(4, ' ');
+    let date = parts.next()?;
+    let time = NaiveDateTime::parse_from_str(&format!("{} {}", date, parts.next()?), "%d.%m.%Y %H:%M:%S").ok()?;
+    let login = parts.next()?.to_string();
+    let text = parts.next().unwrap_or_default().to_string();
+    let granted = if text.starts_with("вход разрешён") {
+        Some(true)
+    } else if text.starts_with("отказ") {
+        Some(false)
+    } else {
+        None
+    };
+
+    Some(Event {
+        time: Local.from_local_datetime(&time).earliest()?,
+        login,
+        text,
+        granted,
+    })
+}
+
+pub fn record(login: &str, event: &str) {
+    write(login, event, None);
+}
+
+// Результат попытки входа
+pub fn record_attempt(login: &str, granted: bool, event: &str) {
+    write(login, event, Some(granted));
+}
+
+fn write(login: &str, event: &str, granted: Option<bool>) {
+    let time = Local::now();
+    let (login, event) = (escape(login, true), escape(event, false));
+    let line = format!("{} {} {}\n", time.format("%d.%m.%Y %H:%M:%S"), login, event);
+    let (log, encoding) = destination();
+
+    if log.stderr {
+        eprint!("{}", line);
+    }
+
+    // Ошибка записи журнала не должна мешать аутентификации
+    if !log.audit.is_empty() {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&log.audit) {
+            // Символы вне Windows-1251 записываются ссылками &#NNNN;, строка
+            // журнала не теряется
+            let bytes = encoding.encode(&line).unwrap_or_else(|_| WINDOWS_1251.encode(&line).0.into_owned());
+            let _ = file.write_all(&bytes);
+        }
+    }
+
+    // Подписчики без файла журнала
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        let event = Event { time, login, text: event, granted };
+        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+// Логины приходят из сети (gRPC, DN привязки LDAP, пользователь PAM) и не
+// должны добавлять строки или сдвигать поля журнала: управляющие символы, а в
+// логине и пробелы, записываются как \u{XXXX}, обратная косая черта - как \\.
+// Пустой логин - «-».
+fn escape(text: &str, field: bool) -> String {
+    if field && text.is_empty() {
+        return "-".to_string();
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() || matches!(c, '\u{2028}' | '\u{2029}') || (field && c.is_whitespace()) => {
+                escaped.push_str(&format!("\\u{{{:04X}}}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn destination() -> (LogConfig, TextEncoding) {
+    DESTINATION
+        .read()
+        .ok()
+        .and_then(|destination| destination.clone())
+        .unwrap_or((LogConfig::default(), TextEncoding::Windows1251))
+}
@@ -0,0 +1,522 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
+use otp_core::cipher::{challenge_block, challenge_from_transaction, encrypt, slot_start, time_block, Algorithm};
+use otp_core::format::{format_password, normalize_password, passwords_equal, OutputFormat};
+use otp_core::mode::AuthMode;
+use otp_core::ocra::{self, OcraInput};
+use otp_core::secret;
+use otp_core::skey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::audit;
+use crate::config::{Config, Lockout, Window};
+use crate::database::{parse_user, User};
+use crate::recovery;
+
+pub const CHALLENGE_LIFETIME: Duration = Duration::from_secs(300);
+
+// За пределами окна просмотра вперёд, но в пределах этого окна счётчик
+// синхронизируется только по двум последовательным паролям
+pub const COUNTER_RESYNC_WINDOW: u64 = 100;
+
+pub enum CounterResult {
+    // Пароль принят, в базу записывается следующее значение счётчика
+    Accepted(u64),
+    // Пароль совпал далеко впереди: нужен следующий пароль
+    NeedsNext(u64),
+    Rejected,
+}
+
+// Состояние между попытками входа по логинам: выданные запросы, счётчики,
+// после которых ожидается следующий пароль для синхронизации, неудачные
+// попытки и время последнего принятого пароля по времени. Всё, кроме
+// запросов, сохраняется в файл состояния между запусками.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    #[serde(skip)]
+    pub challenges: HashMap<String, Challenge>,
+    pub resync: HashMap<String, u64>,
+    pub failures: HashMap<String, Failures>,
+    // Начало шага (unix-время) последнего принятого пароля: пароль того же
+    // или более раннего шага - повтор
+    pub used: HashMap<String, i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Failures {
+    pub count: u32,
+    pub last: NaiveDateTime,
+}
+
+impl Session {
+    fn is_locked(&mut self, login: &str, lockout: &Lockout, now: NaiveDateTime) -> bool {
+        let duration = chrono::Duration::seconds(lockout.duration as i64);
+        self.failures.retain(|_, failures| now - failures.last < duration);
+
+        lockout.attempts > 0 && self.failures.get(login).is_some_and(|failures| failures.count >= lockout.attempts)
+    }
+
+    fn register(&mut self, login: &str, outcome: &Outcome, lockout: &Lockout, now: NaiveDateTime) {
+        match outcome {
+            Outcome::Granted { .. } => {
+                self.failures.remove(login);
+            }
+            // Просьба ввести следующий пароль - не ошибка
+            Outcome::Denied(Denial::CounterResync) => {}
+            Outcome::Denied(_) if lockout.attempts > 0 => {
+                let failures = self.failures.entry(login.to_string()).or_insert(Failures { count: 0, last: now });
+                failures.count += 1;
+                failures.last = now;
+            }
+            Outcome::Denied(_) => {}
+        }
+    }
+}
+
+// Что сообщать вызывающей стороне при отказе. Подробная причина всегда
+// пишется в журнал и видна администратору; внешние клиенты при общей
+// политике не могут по ответу отличить несуществующий логин от неверного
+// пароля
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DenialPolicy {
+    #[default]
+    Detailed,
+    Generic,
+}
+
+pub enum Denial {
+    UnknownUser,
+    Disabled,
+    WrongPassword,
+    BadRecoveryCode,
+    RecoveryNotAllowed,
+    WrongAdminPassword,
+    NoChallenge,
+    ChainExhausted,
+    CounterResync,
+    Replayed,
+    LockedOut,
+    InvalidRecord(String),
+}
+
+impl Denial {
+    pub fn reason(&self) -> String {
+        match self {
+            Denial::UnknownUser => "пользователь не найден".to_string(),
+            Denial::Disabled => "учётная запись отключена".to_string(),
+            Denial::WrongPassword => "неверный пароль".to_string(),
+            Denial::BadRecoveryCode => "неверный код восстановления".to_string(),
+            Denial::RecoveryNotAllowed => "код восстановления не заменяет одноразовый пароль при входе в интерфейс".to_string(),
+            Denial::WrongAdminPassword => "неверный пароль администратора".to_string(),
+            Denial::NoChallenge => "нет действующего запроса".to_string(),
+            Denial::ChainExhausted => "цепочка S/KEY исчерпана".to_string(),
+            Denial::CounterResync => "счётчик рассинхронизирован, ожидается следующий пароль".to_string(),
+            Denial::Replayed => "пароль уже использован".to_string(),
+            Denial::LockedOut => "слишком много неудачных попыток, логин временно заблокирован".to_string(),
+            Denial::InvalidRecord(e) => format!("ошибка в записи базы данных: {}", e),
+        }
+    }
+
+    // Просьба ввести следующий пароль сообщается при любой политике: её
+    // получает только тот, кто знает ключ пользователя
+    pub fn reply(&self, policy: DenialPolicy) -> String {
+        match (self, policy) {
+            (Denial::CounterResync, _) => "⚠ Счётчик рассинхронизирован: введите следующий пароль".to_string(),
+            (_, DenialPolicy::Generic) => "❌ Доступ запрещен".to_string(),
+            (_, DenialPolicy::Detailed) => format!("❌ Доступ запрещен: {}", self.reason()),
+        }
+    }
+}
+
+pub enum Outcome {
+    // changed - запись пользователя изменилась и базу нужно сохранить
+    Granted { note: Option<String>, changed: bool },
+    Denied(Denial),
+}
+
+impl Outcome {
+    pub fn reply(&self, policy: DenialPolicy) -> String {
+        match self {
+            Outcome::Granted { note: Some(note), .. } => format!("✅ Доступ разрешен {}", note),
+            Outcome::Granted { note: None, .. } => "✅ Доступ разрешен".to_string(),
+            Outcome::Denied(denial) => denial.reply(policy),
+        }
+    }
+}
+
+// Проверка логина и пароля с записью результата в журнал
+pub fn authenticate(
+    users: &mut [User],
+    session: &mut Session,
+    login: &str,
+    password: &str,
+    config: &Config,
+    now: NaiveDateTime,
+) -> Outcome {
+    attempt(session, login, config, now, |session| check_password(users, session, login, password, config, now))
+}
+
+// Вход в интерфейс сервера. Пароль администратора проверен вызывающей
+// стороной до блокировки файлов; его неудача считается той же блокировкой
+// логина и не тратит одноразовый пароль. Код восстановления вторым фактором
+// не принимается.
+pub fn authenticate_admin(
+    users: &mut [User],
+    session: &mut Session,
+    login: &str,
+    admin_password: bool,
+    otp: &str,
+    config: &Config,
+    now: NaiveDateTime,
+) -> Outcome {
+    attempt(session, login, config, now, |session| {
+        if !admin_password {
+            Outcome::Denied(Denial::WrongAdminPassword)
+        } else if recovery::looks_like_code(otp) {
+            Outcome::Denied(Denial::RecoveryNotAllowed)
+        } else {
+            check_password(users, session, login, otp, config, now)
+        }
+    })
+}
+
+fn attempt(
+    session: &mut Session,
+    login: &str,
+    config: &Config,
+    now: NaiveDateTime,
+    check: impl FnOnce(&mut Session) -> Outcome,
+) -> Outcome {
+    // Заблокированный логин не проверяется: подбор пароля не продвигается
+    let outcome = if session.is_locked(login, &config.lockout, now) {
+        Outcome::Denied(Denial::LockedOut)
+    } else {
+        let outcome = check(session);
+        session.register(login, &outcome, &config.lockout, now);
+        outcome
+    };
+
+    match &outcome {
+        Outcome::Granted { note: Some(note), .. } => {
+            audit::record_attempt(login, true, &format!("вход разрешён {}", note))
+        }
+        Outcome::Granted { note: None, .. } => audit::record_attempt(login, true, "вход разрешён"),
+        Outcome::Denied(denial) => audit::record_attempt(login, false, &format!("отказ: {}", denial.reason())),
+    }
+
+    outcome
+}
+
+fn check_password(
+    users: &mut [User],
+    session: &mut Session,
+    login: &str,
+    password: &str,
+    config: &Config,
+    now: NaiveDateTime,
+) -> Outcome {
+    // Для неизвестного и отключённого логина выполняется такая же проверка,
+    // как для настоящего пароля, чтобы время ответа не выдавало логин
+    let Some(index) = users.iter().position(|u| u.login == login) else {
+        verify_decoy(users, password, now, config);
+        return Outcome::Denied(Denial::UnknownUser);
+    };
+
+    if users[index].disabled {
+        verify_decoy(users, password, now, config);
+        return Outcome::Denied(Denial::Disabled);
+    }
+    let user = &mut users[index];
+
+    // Код восстановления заменяет одноразовый пароль при потере bs1.txt
+    if recovery::looks_like_code(password) {
+        return match recovery::verify_code(&user.recovery, password) {
+            Some(used) => {
+                user.recovery.remove(used);
+                let note = format!("по коду восстановления (осталось {})", user.recovery.len());
+                Outcome::Granted { note: Some(note), changed: true }
+            }
+            None => Outcome::Denied(Denial::BadRecoveryCode),
+        };
+    }
+
+    let granted = |changed: bool| Outcome::Granted { note: None, changed };
+
+    match user.mode {
+        AuthMode::Challenge | AuthMode::Ocra => {
+            let Some(challenge) = session.challenges.remove(&user.login).filter(|c| c.is_valid_for(&user.login)) else {
+                return Outcome::Denied(Denial::NoChallenge);
+            };
+
+            // now - местное время; OCRA с параметром T считает от эпохи UTC
+            let unix_time = Local
+                .from_local_datetime(&now)
+                .earliest()
+                .map_or_else(|| now.and_utc().timestamp(), |time| time.timestamp())
+                .max(0) as u64;
+            if verify_challenge(user, password, &challenge.value, unix_time) {
+                granted(false)
+            } else {
+                Outcome::Denied(Denial::WrongPassword)
+            }
+        }
+        AuthMode::Counter => {
+            let pending = session.resync.remove(&user.login);
+
+            match verify_counter(user, password, config.counter_window, pending) {
+                CounterResult::Accepted(next) => {
+                    user.counter = next;
+                    granted(true)
+                }
+                CounterResult::NeedsNext(counter) => {
+                    session.resync.insert(user.login.clone(), counter);
+                    Outcome::Denied(Denial::CounterResync)
+                }
+                CounterResult::Rejected => Outcome::Denied(Denial::WrongPassword),
+            }
+        }
+        AuthMode::HashChain => {
+            if user.chain.sequence == 0 {
+                return Outcome::Denied(Denial::ChainExhausted);
+            }
+
+            match verify_hash_chain(user, password, config.counter_window) {
+                Some((sequence, accepted)) => {
+                    user.chain.sequence = sequence;
+                    user.chain.last = Some(accepted);
+                    granted(true)
+                }
+                None => Outcome::Denied(Denial::WrongPassword),
+            }
+        }
+        AuthMode::Time => {
+            let start = match user.datetime() {
+                Ok(start) => start,
+                Err(e) => return Outcome::Denied(Denial::InvalidRecord(e)),
+            };
+
+            match verify_time(user, password, start, now, config.window_for(user)) {
+                Some(time) if session.used.get(&user.login).is_some_and(|used| time <= *used) => {
+                    Outcome::Denied(Denial::Replayed)
+                }
+                Some(time) => {
+                    session.used.insert(user.login.clone(), time);
+                    granted(false)
+                }
+                None => Outcome::Denied(Denial::WrongPassword),
+            }
+        }
+    }
+}
+
+// Выданный запрос действует один раз и ограниченное время
+pub struct Challenge {
+    pub login: String,
+    pub value: String,
+    pub issued: Instant,
+}
+
+impl Challenge {
+    pub fn is_valid_for(&self, login: &str) -> bool {
+        self.login == login && self.issued.elapsed() < CHALLENGE_LIFETIME
+    }
+}
+
+pub fn issue_challenge(user: &User, transaction: &str) -> Result<Challenge, String> {
+    let transaction = transaction.trim();
+    let random: [u8; 32] = rand::random();
+
+    let value = match user.mode {
+        AuthMode::Time | AuthMode::Counter | AuthMode::HashChain => {
+            return Err("Пользователю запрос не нужен".to_string())
+        }
+        AuthMode::Challenge if transaction.is_empty() => challenge_block(user.algorithm, &random),
+        AuthMode::Challenge => challenge_from_transaction(user.algorithm, transaction),
+        AuthMode::Ocra if transaction.is_empty() => user.suite.challenge_from_bytes(&random),
+        AuthMode::Ocra => user.suite.challenge_from_transaction(transaction),
+    };
+
+    Ok(Challenge {
+        login: user.login.clone(),
+        value,
+        issued: Instant::now(),
+    })
+}
+
+// Начало шага (unix-время), на который вычислен совпавший пароль
+pub fn verify_time(user: &User, password: &str, start: NaiveDateTime, now: NaiveDateTime, window: Window) -> Option<i64> {
+    let password = normalize_password(user.format, password);
+    let earliest = now - chrono::Duration::seconds(window.past as i64);
+    let latest = now + chrono::Duration::seconds(window.future as i64);
+    let block_value = |time: NaiveDateTime| {
+        (time.and_utc().timestamp() - start.and_utc().timestamp()).max(0) as u64 / user.step.max(1)
+    };
+
+    // С шагом квантования окно в секундах даёт лишь несколько значений блока,
+    // каждое шифруется один раз
+    let mut values: BTreeSet<u64> = (block_value(earliest)..=block_value(latest)).collect();
+
+    // Пароль из печатного списка вычислен на начало интервала: принимаются
+    // интервалы, которые пересекаются с окном
+    if user.slot > 0 {
+        let step = chrono::Duration::seconds(user.slot as i64);
+        let mut slot = slot_start(earliest, user.slot);
+        while slot <= latest {
+            values.insert(block_value(slot));
+            slot += step;
+        }
+    }
+
+    // Проверяются все значения окна без досрочного выхода: время ответа не
+    // выдаёт, в каком месте окна совпал пароль
+    let matched = values.into_iter().fold(None, |found: Option<u64>, value| {
+        let matched = encrypt(user.algorithm, &time_block(user.algorithm, value), &user.bs2)
+            .and_then(|hex| format_password(user.format, &hex))
+            .is_ok_and(|expected| passwords_equal(&normalize_password(user.format, &expected), &password));
+        found.or(matched.then_some(value))
+    });
+
+    matched.map(|value| start.and_utc().timestamp() + (value * user.step.max(1)) as i64)
+}
+
+pub fn verify_counter(user: &User, password: &str, lookahead: u64, pending: Option<u64>) -> CounterResult {
+    let password = normalize_password(user.format, password);
+    let matches = |counter: u64| {
+        encrypt(user.algorithm, &time_block(user.algorithm, counter), &user.bs2)
+            .and_then(|hex| format_password(user.format, &hex))
+            .is_ok_and(|expected| passwords_equal(&normalize_password(user.format, &expected), &password))
+    };
+    // Первое совпадение ищется с проверкой всего диапазона
+    let first_match = |from: u64, to: u64| {
+        (from..=to).fold(None, |found: Option<u64>, counter| {
+            let matched = matches(counter);
+            found.or(matched.then_some(counter))
+        })
+    };
+
+    let start = user.counter;
+    let resync_start = start.saturating_add(lookahead).saturating_add(1);
+    // Счётчик у верхней границы u64 не переполняется: следующего значения
+    // нет, и пароль отвергается
+    let next_after_pending = pending.and_then(|previous| Some((previous.checked_add(2)?, matches(previous + 1))));
+    let in_window = first_match(start, start.saturating_add(lookahead));
+    let in_resync = first_match(resync_start, start.saturating_add(COUNTER_RESYNC_WINDOW));
+
+    if let Some((next, true)) = next_after_pending {
+        return CounterResult::Accepted(next);
+    }
+
+    match (in_window, in_resync) {
+        (Some(counter), _) => counter.checked_add(1).map_or(CounterResult::Rejected, CounterResult::Accepted),
+        (None, Some(counter)) => CounterResult::NeedsNext(counter),
+        (None, None) => CounterResult::Rejected,
+    }
+}
+
+// Пароль S/KEY принимается, если после 1..=lookahead хеширований он даёт
+// последний принятый пароль (клиент мог сгенерировать и не отправить
+// несколько паролей). Возвращает новое состояние цепочки: номер следующего
+// пароля и принятый пароль.
+pub fn verify_hash_chain(user: &User, password: &str, lookahead: u64) -> Option<(u64, u64)> {
+    let chain = &user.chain;
+    let mut value = skey::parse_response(password)?;
+    let last = chain
+        .last
+        .unwrap_or_else(|| skey::otp(chain.hash, &chain.seed, &user.bs2, chain.sequence + 1));
+    let accepted = value;
+    let mut matched = None;
+
+    for skipped in 0..lookahead.max(1).min(chain.sequence) {
+        value = skey::step(chain.hash, value);
+        if matched.is_none() && value == last {
+            matched = Some((chain.sequence - skipped - 1, accepted));
+        }
+    }
+
+    matched
+}
+
+pub fn verify_challenge(user: &User, password: &str, challenge: &str, unix_time: u64) -> bool {
+    match user.mode {
+        AuthMode::Time | AuthMode::Counter | AuthMode::HashChain => false,
+        AuthMode::Challenge => {
+            let password = normalize_password(user.format, password);
+            encrypt(user.algorithm, challenge, &user.bs2)
+                .and_then(|hex| format_password(user.format, &hex))
+                .is_ok_and(|expected| passwords_equal(&normalize_password(user.format, &expected), &password))
+        }
+        AuthMode::Ocra => {
+            let Ok(key) = secret::decode_hex(&user.bs2) else {
+                return false;
+            };
+            let password = normalize_password(OutputFormat::Hex, password);
+
+            // С параметром T допускается расхождение часов на один шаг
+            let step = user.suite.time_step.unwrap_or(0);
+            [unix_time, unix_time.saturating_sub(step), unix_time + step].iter().fold(false, |found, &time| {
+                let input = OcraInput {
+                    question: challenge,
+                    pin: user.bs2.get(..4).unwrap_or_default(),
+                    unix_time: time,
+                    ..Default::default()
+                };
+                found | ocra::response(&user.suite, &key, &input).is_ok_and(|expected| passwords_equal(&expected, &password))
+            })
+        }
+    }
+}
+
+// Та же работа, что и при самой дорогой проверке настоящего пароля (или
+// кода восстановления), но со случайным ключом
+fn verify_decoy(users: &[User], password: &str, now: NaiveDateTime, config: &Config) {
+    if recovery::looks_like_code(password) {
+        let _ = recovery::verify_code(&[], password);
+        return;
+    }
+
+    // Столько шифрований подряд, сколько стоит самая дорогая проверка: окно
+    // из blocks значений с шагом 1
+    let (algorithm, blocks) = decoy_work(users, config);
+    let key: Vec<u8> = (0..algorithm.key_len()).map(|_| rand::random()).collect();
+    let record = format!("- - {} 01.01.2000 00:00:00 alg={}", hex::encode_upper(key), algorithm.name());
+    if let Ok(decoy) = parse_user(&record) {
+        if let Ok(start) = decoy.datetime() {
+            let window = Window { past: blocks.saturating_sub(1), future: 0 };
+            let _ = verify_time(&decoy, password, start, now, window);
+        }
+    }
+}
+
+// Алгоритм и число шифрований самой дорогой проверки: по записям базы с их
+// шагом, интервалом списка и окнами (своими или группы), а также по общему
+// окну и окнам групп для записей с шагом 1. Тройной DES втрое дороже DES и AES.
+fn decoy_work(users: &[User], config: &Config) -> (Algorithm, u64) {
+    let blocks = |window: Window, step: u64| (window.past + window.future) / step.max(1) + 1;
+    let configured = std::iter::once(config.window)
+        .chain(config.groups.values().copied())
+        .map(|window| (Algorithm::default(), blocks(window, 1)));
+    let recorded = users.iter().filter(|user| !user.disabled).map(|user| {
+        let work = match user.mode {
+            AuthMode::Time => {
+                let window = config.window_for(user);
+                let slots = (window.past + window.future).checked_div(user.slot).map_or(0, |slots| slots + 2);
+                blocks(window, user.step) + slots
+            }
+            AuthMode::Counter => COUNTER_RESYNC_WINDOW + 2,
+            AuthMode::HashChain | AuthMode::Challenge | AuthMode::Ocra => 1,
+        };
+        (user.algorithm, work)
+    });
+
+    let cost = |algorithm: Algorithm| match algorithm {
+        Algorithm::Des | Algorithm::Aes128 => 1,
+        Algorithm::TdesEde2 | Algorithm::TdesEde3 => 3,
+    };
+    configured
+        .chain(recorded)
+        .max_by_key(|(algorithm, work)| work * cost(*algorithm))
+        .unwrap_or_default()
+}
@@ -0,0 +1,273 @@
+use chrono::Local;
+use otp_core::text::TextEncoding;
+use std::fs;
+
+use otp_server::api_keys::{KeyStore, Scope};
+use otp_server::audit;
+use otp_server::config::{Config, CONFIG_FILE};
+use otp_server::validator::{has_errors, validate_database, Severity};
+
+// Параметры командной строки: путь к server.toml и значения, которые
+// перекрывают настройки из него
+#[derive(Default)]
+pub struct Options {
+    pub config_path: Option<String>,
+    pub database: Option<String>,
+    pub listen: Vec<String>,
+    pub encoding: Option<TextEncoding>,
+    pub command: Vec<String>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().cloned().ok_or_else(|| format!("{}: нет значения", arg));
+            match arg.as_str() {
+                "--config" => options.config_path = Some(value()?),
+                "--database" => options.database = Some(value()?),
+                "--listen" => options.listen.push(value()?),
+                "--encoding" => options.encoding = Some(parse_encoding(&value()?)?),
+                "--help" | "-h" => options.command.push(arg.clone()),
+                option if option.starts_with("--") && options.command.is_empty() => {
+                    return Err(format!("Неизвестный параметр: {}", option));
+                }
+                _ => options.command.push(arg.clone()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn config_path(&self) -> &str {
+        self.config_path.as_deref().unwrap_or(CONFIG_FILE)
+    }
+
+    // Настройки из файла с учётом параметров командной строки
+    pub fn load_config(&self) -> Result<Config, String> {
+        let mut config = Config::load(self.config_path())?;
+        self.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(database) = &self.database {
+            config.database.path = database.clone();
+        }
+        if !self.listen.is_empty() {
+            config.listen = self.listen.clone();
+        }
+        if let Some(encoding) = self.encoding {
+            config.encoding = encoding;
+        }
+    }
+}
+
+pub fn run(options: &Options) -> i32 {
+    let args = &options.command;
+    if matches!(args[0].as_str(), "help" | "--help" | "-h") {
+        print_usage();
+        return 0;
+    }
+
+    let config = match options.load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    audit::configure(&config);
+
+    match args[0].as_str() {
+        "validate" | "check" => validate(args.get(1).unwrap_or(&config.database.path), &config),
+        "serve" => serve(config),
+        "ldap" => ldap(config),
+        "keys" => keys(&args[1..], &config),
+        "convert" => match args.get(1) {
+            Some(target) => convert(target, args.get(2).unwrap_or(&config.database.path), &config),
+            None => {
+                print_usage();
+                2
+            }
+        },
+        other => {
+            eprintln!("Неизвестная команда: {}", other);
+            print_usage();
+            2
+        }
+    }
+}
+
+pub fn print_usage() {
+    println!("Использование:");
+    println!("  otp_server [параметры]                  запуск графического интерфейса");
+    println!("  otp_server [параметры] validate [файл]  проверка базы данных (по умолчанию из настроек)");
+    println!("  otp_server [параметры] serve            демон проверки паролей для модуля PAM");
+    println!("  otp_server [параметры] ldap             прокси LDAP с проверкой одноразового пароля");
+    println!("  otp_server [параметры] keys             клиенты API службы gRPC");
+    println!("  otp_server [параметры] keys add <имя> <разрешение>...");
+    println!("                                          выдача ключа: verify, read_users, manage_users, read_audit");
+    println!("  otp_server [параметры] keys revoke <номер>");
+    println!("                                          отзыв ключа");
+    println!("  otp_server [параметры] convert <кодировка> [файл]");
+    println!("                                          перекодирование базы в windows-1251 или utf-8,");
+    println!("                                          исходный файл сохраняется с расширением .bak");
+    println!();
+    println!("Параметры (перекрывают {}):", CONFIG_FILE);
+    println!("  --config файл       файл настроек (по умолчанию {})", CONFIG_FILE);
+    println!("  --database файл     база данных");
+    println!("  --listen IP:порт    адрес службы gRPC (можно повторять)");
+    println!("  --encoding имя      кодировка файлов: auto, windows-1251 или utf-8");
+}
+
+fn validate(path: &str, config: &Config) -> i32 {
+    let text = match config.encoding.read(path) {
+        Ok(decoded) => {
+            if decoded.lossy {
+                println!("{}: файл не в кодировке {}, недопустимые байты заменены", path, decoded.encoding.title());
+            }
+            decoded.text
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 2;
+        }
+    };
+
+    let issues = validate_database(&text, Local::now().naive_local(), config);
+    for issue in &issues {
+        println!("{}: {}", path, issue);
+    }
+
+    let errors = issues.iter().filter(|i| i.severity == Severity::Error).count();
+    println!("{}: ошибок: {}, предупреждений: {}", path, errors, issues.len() - errors);
+
+    if has_errors(&issues) { 1 } else { 0 }
+}
+
+#[cfg(unix)]
+fn serve(config: Config) -> i32 {
+    println!("Демон слушает {}", config.daemon.socket);
+    match otp_server::daemon::serve(config) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn serve(_config: Config) -> i32 {
+    eprintln!("Демон на сокете Unix доступен только в Linux");
+    1
+}
+
+fn ldap(config: Config) -> i32 {
+    println!("Прокси LDAP слушает {}, сервер LDAP {}", config.ldap.listen, config.ldap.upstream);
+    match otp_server::ldap::serve(config) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// Ключи API в файле api.keys. Открытый ключ печатается один раз, в файле
+// остаётся только его хеш.
+fn keys(args: &[String], config: &Config) -> i32 {
+    match manage_keys(args, &config.api.keys) {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}: {}", config.api.keys, e);
+            1
+        }
+    }
+}
+
+fn manage_keys(args: &[String], path: &str) -> Result<String, String> {
+    let mut store = KeyStore::load(path)?;
+    match args.first().map(String::as_str) {
+        None | Some("list") if store.clients.is_empty() => Ok("Ключей нет: служба gRPC отклоняет все вызовы".to_string()),
+        None | Some("list") => Ok(store
+            .clients
+            .iter()
+            .map(|client| {
+                let scopes: Vec<&str> = client.scopes.iter().map(|scope| scope.name()).collect();
+                format!("{} {} {} ({})", client.id, client.name, scopes.join(","), client.created)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        Some("add") if args.len() > 2 => {
+            let scopes = args[2..]
+                .iter()
+                .map(|name| Scope::parse(name).ok_or_else(|| format!("неизвестное разрешение: {}", name)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let key = store.issue(&args[1], &scopes)?;
+            store.save(path)?;
+            audit::record(&args[1], "выдан ключ API");
+            Ok(format!("Ключ клиента {} (показывается один раз):\n{}", args[1], key))
+        }
+        Some("revoke") if args.len() == 2 => {
+            let client = store.revoke(&args[1]).ok_or_else(|| format!("нет ключа {}", args[1]))?;
+            store.save(path)?;
+            audit::record(&client.name, "отозван ключ API");
+            Ok(format!("Ключ {} клиента {} отозван", client.id, client.name))
+        }
+        _ => Err("ожидается keys [list], keys add <имя> <разрешение>... или keys revoke <номер>".to_string()),
+    }
+}
+
+// Разовое перекодирование базы: читается в кодировке из настроек (по
+// умолчанию с автоопределением), записывается в target
+fn convert(target: &str, path: &str, config: &Config) -> i32 {
+    match convert_file(target, path, config) {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            1
+        }
+    }
+}
+
+fn convert_file(target: &str, path: &str, config: &Config) -> Result<String, String> {
+    let target = match parse_encoding(target)? {
+        TextEncoding::Auto => return Err("укажите кодировку: windows-1251 или utf-8".to_string()),
+        target => target,
+    };
+
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let decoded = config.encoding.decode(&bytes);
+    if decoded.lossy {
+        return Err(format!("файл не в кодировке {}, укажите исходную через --encoding", decoded.encoding.title()));
+    }
+    let encoded = target.encode(&decoded.text)?;
+
+    let backup = format!("{}.bak", path);
+    fs::write(&backup, &bytes).map_err(|e| format!("{}: {}", backup, e))?;
+    fs::write(path, encoded).map_err(|e| e.to_string())?;
+
+    let mut message = format!(
+        "{}: {} -> {}, исходный файл: {}",
+        path, decoded.encoding.title(), target.title(), backup
+    );
+    if config.encoding != TextEncoding::Auto && config.encoding != target {
+        message.push_str(&format!("\nУкажите encoding = \"{}\" в {}", target.name(), CONFIG_FILE));
+    }
+    Ok(message)
+}
+
+fn parse_encoding(name: &str) -> Result<TextEncoding, String> {
+    TextEncoding::parse(name).ok_or_else(|| format!("Неизвестная кодировка: {}", name))
+}
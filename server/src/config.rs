@@ -0,0 +1,358 @@
+use otp_core::text::TextEncoding;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use crate::admins::ADMINS_FILE;
+use crate::api_keys::KEYS_FILE;
+use crate::audit::AUDIT_FILE;
+use crate::auth::{DenialPolicy, COUNTER_RESYNC_WINDOW};
+use crate::database::{User, DATABASE_FILE};
+
+// Настройки сервера в TOML. Если файла нет, действуют значения по умолчанию,
+// совпадающие с поведением исходного Server.exe.
+pub const CONFIG_FILE: &str = "server.toml";
+
+// Блокировки, повторы паролей и ожидаемая синхронизация счётчика
+pub const STATE_FILE: &str = "state.toml";
+
+pub const SOCKET_FILE: &str = "otp_server.sock";
+
+// Верхняя граница окна: за час пароль по времени теряет смысл
+pub const MAX_WINDOW: u64 = 3600;
+
+// Блокировка дольше суток - уже отключение учётной записи
+pub const MAX_LOCKOUT: u64 = 86_400;
+
+// Допустимое расхождение часов клиента: past - клиент отстаёт, future -
+// спешит, секунд
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct Window {
+    pub past: u64,
+    pub future: u64,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self { past: 20, future: 20 }
+    }
+}
+
+// Хранилище пользователей. Пока есть только текстовый файл database.txt.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Text,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 1] = [Backend::Text];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Backend::Text => "Текстовый файл",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub path: String,
+    pub backend: Backend,
+    // Файл состояния проверок (пустой путь - только в памяти)
+    pub state: String,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: DATABASE_FILE.to_string(),
+            backend: Backend::Text,
+            state: STATE_FILE.to_string(),
+        }
+    }
+}
+
+// Демон проверки паролей для модуля PAM: сокет Unix
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct DaemonConfig {
+    pub socket: String,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            socket: SOCKET_FILE.to_string(),
+        }
+    }
+}
+
+// Прокси LDAP: простая привязка с паролем <пароль LDAP><одноразовый пароль>
+// проверяется здесь и передаётся серверу upstream без одноразового пароля.
+// Логин в базе - значение атрибута attribute в DN (uid=Johnny,ou=...).
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct LdapConfig {
+    pub listen: String,
+    pub upstream: String,
+    pub attribute: String,
+}
+
+impl Default for LdapConfig {
+    fn default() -> Self {
+        Self {
+            listen: "127.0.0.1:3389".to_string(),
+            upstream: String::new(),
+            attribute: "uid".to_string(),
+        }
+    }
+}
+
+// TLS для сетевых интерфейсов (служба gRPC, прокси LDAP): цепочка
+// сертификатов и ключ в PEM. Если задан client_ca, клиент должен предъявить
+// сертификат, выданный этим центром. Файлы перечитываются при изменении.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub certificate: String,
+    pub key: String,
+    pub client_ca: String,
+}
+
+impl TlsConfig {
+    pub fn enabled(&self) -> bool {
+        !self.certificate.is_empty()
+    }
+}
+
+// Ключи API вызывающих сервисов: без действующего ключа с нужным
+// разрешением служба gRPC не выполняет ни одного вызова
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub keys: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            keys: KEYS_FILE.to_string(),
+        }
+    }
+}
+
+// Графический интерфейс: учётные записи администраторов (вход паролем и
+// одноразовым паролем, роли)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct GuiConfig {
+    pub admins: String,
+}
+
+impl Default for GuiConfig {
+    fn default() -> Self {
+        Self {
+            admins: ADMINS_FILE.to_string(),
+        }
+    }
+}
+
+// После attempts неудачных попыток подряд логин блокируется на duration
+// секунд; attempts = 0 - блокировки нет
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct Lockout {
+    pub attempts: u32,
+    pub duration: u64,
+}
+
+impl Default for Lockout {
+    fn default() -> Self {
+        Self { attempts: 0, duration: 300 }
+    }
+}
+
+// Куда пишется журнал: файл (пустой путь - не писать) и поток ошибок
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct LogConfig {
+    pub audit: String,
+    pub stderr: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            audit: AUDIT_FILE.to_string(),
+            stderr: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Config {
+    // Кодировка базы и журнала: auto, windows-1251 или utf-8
+    #[serde(with = "encoding_name")]
+    pub encoding: TextEncoding,
+    // Адреса службы gRPC (otp_grpc)
+    pub listen: Vec<String>,
+    // Окно счётчика и S/KEY: сколько паролей вперёд проверяется
+    pub counter_window: u64,
+    pub denial: DenialPolicy,
+    pub database: DatabaseConfig,
+    // Окно для пользователей без группы и собственных значений
+    pub window: Window,
+    // Именованные группы (политики), запись пользователя ссылается на них group=
+    pub groups: BTreeMap<String, Window>,
+    pub lockout: Lockout,
+    pub log: LogConfig,
+    pub daemon: DaemonConfig,
+    pub ldap: LdapConfig,
+    pub tls: TlsConfig,
+    pub api: ApiConfig,
+    pub gui: GuiConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            encoding: TextEncoding::default(),
+            listen: Vec::new(),
+            counter_window: 10,
+            denial: DenialPolicy::default(),
+            database: DatabaseConfig::default(),
+            window: Window::default(),
+            groups: BTreeMap::new(),
+            lockout: Lockout::default(),
+            log: LogConfig::default(),
+            daemon: DaemonConfig::default(),
+            ldap: LdapConfig::default(),
+            tls: TlsConfig::default(),
+            api: ApiConfig::default(),
+            gui: GuiConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path.as_ref().display(), e)),
+        };
+
+        let config: Config = toml::from_str(&text).map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Относительные пути в настройках отсчитываются от каталога файла
+    // настроек, а не от текущего каталога (для модуля PAM и демона)
+    pub fn relative_to(&mut self, dir: &Path) {
+        for path in [
+            &mut self.database.path,
+            &mut self.database.state,
+            &mut self.log.audit,
+            &mut self.daemon.socket,
+            &mut self.tls.certificate,
+            &mut self.tls.key,
+            &mut self.tls.client_ca,
+            &mut self.api.keys,
+            &mut self.gui.admins,
+        ] {
+            if !path.is_empty() && Path::new(path.as_str()).is_relative() {
+                *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        self.validate()?;
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, text).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.database.path.trim().is_empty() {
+            return Err("database.path: не задан путь к базе данных".to_string());
+        }
+        for address in &self.listen {
+            address
+                .parse::<SocketAddr>()
+                .map_err(|_| format!("listen: неверный адрес {} (ожидается IP:порт)", address))?;
+        }
+        if !self.ldap.listen.is_empty() && self.ldap.listen.parse::<SocketAddr>().is_err() {
+            return Err(format!("ldap.listen: неверный адрес {} (ожидается IP:порт)", self.ldap.listen));
+        }
+        if self.tls.certificate.is_empty() != self.tls.key.is_empty() {
+            return Err("tls: сертификат и ключ задаются вместе".to_string());
+        }
+        if !self.tls.client_ca.is_empty() && !self.tls.enabled() {
+            return Err("tls.client_ca: проверка клиентов требует сертификата сервера".to_string());
+        }
+        if self.api.keys.trim().is_empty() {
+            return Err("api.keys: не задан файл ключей API".to_string());
+        }
+        if self.gui.admins.trim().is_empty() {
+            return Err("gui.admins: не задан файл администраторов".to_string());
+        }
+        if self.counter_window > COUNTER_RESYNC_WINDOW {
+            return Err(format!("counter_window: не больше {}", COUNTER_RESYNC_WINDOW));
+        }
+        if self.lockout.attempts > 0 && !(1..=MAX_LOCKOUT).contains(&self.lockout.duration) {
+            return Err(format!("lockout.duration: от 1 до {} секунд", MAX_LOCKOUT));
+        }
+
+        validate_window("window", &self.window)?;
+        for (name, window) in &self.groups {
+            if name.is_empty() || name.chars().any(char::is_whitespace) {
+                return Err(format!("группа «{}»: имя без пробелов", name));
+            }
+            validate_window(&format!("groups.{}", name), window)?;
+        }
+        Ok(())
+    }
+
+    // Собственные значения пользователя, затем его группа, затем общее окно
+    pub fn window_for(&self, user: &User) -> Window {
+        let base = user
+            .group
+            .as_ref()
+            .and_then(|group| self.groups.get(group))
+            .copied()
+            .unwrap_or(self.window);
+
+        Window {
+            past: user.past.unwrap_or(base.past),
+            future: user.future.unwrap_or(base.future),
+        }
+    }
+}
+
+fn validate_window(section: &str, window: &Window) -> Result<(), String> {
+    if window.past > MAX_WINDOW || window.future > MAX_WINDOW {
+        return Err(format!("{}: окно не больше {} секунд", section, MAX_WINDOW));
+    }
+    Ok(())
+}
+
+mod encoding_name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(encoding: &TextEncoding, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(encoding.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TextEncoding, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        TextEncoding::parse(&name).ok_or_else(|| serde::de::Error::custom(format!("неизвестная кодировка: {}", name)))
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::Local;
+use std::fs::{self, DirBuilder};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::auth::{Challenge, CHALLENGE_LIFETIME};
+use crate::config::Config;
+use crate::verifier::{self, Reply};
+
+// Одновременных соединений с демоном; сверх этого соединение получает
+// ERROR и закрывается, не занимая поток
+const MAX_CONNECTIONS: usize = 64;
+
+// Демон проверки паролей на сокете Unix. Протокол строковый, по строке на
+// запрос и ответ:
+//   CHALLENGE <логин>         -> CHALLENGE <текст запроса> | NONE
+//   AUTH <логин> <пароль>     -> OK|NEXT|LOCKED|UNKNOWN|DENY|ERROR <сообщение>
+// Логин не пустой и без пробельных символов, он заканчивается первым
+// пробелом; пароль - весь остаток строки и может содержать пробелы.
+// Выданный запрос действует в пределах соединения. Сокет доступен только
+// владельцу (0600): модулю PAM, работающему от root.
+pub fn serve(config: Config) -> Result<(), String> {
+    let path = config.daemon.socket.clone();
+    if path.is_empty() {
+        return Err("daemon.socket: не задан путь к сокету".to_string());
+    }
+
+    // Сокет от предыдущего запуска мешает bind
+    if Path::new(&path).exists() && UnixStream::connect(&path).is_err() {
+        let _ = fs::remove_file(&path);
+    }
+    let listener = bind(&path).map_err(|e| format!("{}: {}", path, e))?;
+
+    let config = Arc::new(config);
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            let _ = writeln!(stream, "{}", Reply::Error("демон занят, повторите позже".to_string()).to_line());
+            continue;
+        }
+        let config = Arc::clone(&config);
+        let connections = Arc::clone(&connections);
+        thread::spawn(move || {
+            let _ = handle(&config, stream);
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+
+    Ok(())
+}
+
+// Сокет создаётся в каталоге 0700 рядом с путём и переносится на место уже
+// с правами 0600: между bind и chmod к нему никто не подключится
+fn bind(path: &str) -> std::io::Result<UnixListener> {
+    if Path::new(path).exists() {
+        return Err(std::io::ErrorKind::AddrInUse.into());
+    }
+    let private = format!("{}.{}", path, std::process::id());
+    let _ = fs::remove_dir_all(&private);
+    DirBuilder::new().mode(0o700).create(&private)?;
+    let socket = Path::new(&private).join("socket");
+
+    let listener = UnixListener::bind(&socket)
+        .and_then(|listener| fs::set_permissions(&socket, fs::Permissions::from_mode(0o600)).map(|_| listener))
+        .and_then(|listener| fs::rename(&socket, path).map(|_| listener));
+    let _ = fs::remove_dir_all(&private);
+    listener
+}
+
+// Логин запроса: непустой, без пробельных и управляющих символов
+fn valid_login(login: &str) -> Result<&str, String> {
+    if login.is_empty() || login.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err("логин пуст или содержит пробелы".to_string());
+    }
+    Ok(login)
+}
+
+fn handle(config: &Config, mut stream: UnixStream) -> std::io::Result<()> {
+    // Между запросом и паролем пользователь вводит ответ вручную
+    stream.set_read_timeout(Some(CHALLENGE_LIFETIME))?;
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut challenge: Option<Challenge> = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        let reply = match line.split_once(' ') {
+            Some(("CHALLENGE", login)) => match valid_login(login).and_then(|login| verifier::challenge(config, login)) {
+                Ok((Some(text), issued)) => {
+                    challenge = issued;
+                    format!("CHALLENGE {}", text)
+                }
+                Ok((None, _)) => "NONE".to_string(),
+                Err(e) => Reply::Error(e).to_line(),
+            },
+            Some(("AUTH", rest)) => {
+                let (login, password) = rest.split_once(' ').unwrap_or((rest, ""));
+                let now = Local::now().naive_local();
+                match valid_login(login).and_then(|login| verifier::verify(config, challenge.take(), login, password, now)) {
+                    Ok(outcome) => Reply::from_outcome(&outcome, config.denial).to_line(),
+                    Err(e) => Reply::Error(e).to_line(),
+                }
+            }
+            _ => Reply::Error("неизвестная команда".to_string()).to_line(),
+        };
+        writeln!(stream, "{}", reply)?;
+    }
+
+    Ok(())
+}
+
+// Клиент демона для модуля PAM: одно соединение на попытку входа
+pub struct Client {
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+}
+
+impl Client {
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let stream = UnixStream::connect(&path).map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        stream.set_read_timeout(Some(CHALLENGE_LIFETIME)).map_err(|e| e.to_string())?;
+        let reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+        Ok(Self { stream, reader })
+    }
+
+    pub fn challenge(&mut self, login: &str) -> Result<Option<String>, String> {
+        let login = valid_login(login)?;
+        let line = self.request(&format!("CHALLENGE {}", login))?;
+        match line.split_once(' ') {
+            Some(("CHALLENGE", text)) => Ok(Some(text.to_string())),
+            _ if line == "NONE" => Ok(None),
+            _ => Err(Reply::parse(&line).text().to_string()),
+        }
+    }
+
+    pub fn authenticate(&mut self, login: &str, password: &str) -> Result<Reply, String> {
+        let login = valid_login(login)?;
+        let line = self.request(&format!("AUTH {} {}", login, password))?;
+        Ok(Reply::parse(&line))
+    }
+
+    fn request(&mut self, line: &str) -> Result<String, String> {
+        if line.contains(['\r', '\n']) {
+            return Err("перевод строки в запросе".to_string());
+        }
+        writeln!(self.stream, "{}", line).map_err(|e| e.to_string())?;
+
+        let mut reply = String::new();
+        match self.reader.read_line(&mut reply) {
+            Ok(0) => Err("демон закрыл соединение".to_string()),
+            Ok(_) => Ok(reply.trim_end().to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use chrono::NaiveDateTime;
+use otp_core::cipher::{Algorithm, MAX_SLOT, MAX_TIME_STEP};
+use otp_core::format::OutputFormat;
+use otp_core::mode::AuthMode;
+use otp_core::ocra::{OcraSuite, DEFAULT_SUITE};
+use otp_core::secret::{self, SecretString};
+use otp_core::skey::{self, ChainHash};
+
+use crate::config::MAX_WINDOW;
+use crate::recovery;
+
+// Строка базы: Фамилия_И.О. Логин BS2 ДД.ММ.ГГГГ ЧЧ:ММ:СС [атрибуты...]
+// Первые пять полей совместимы с оригинальным Server.exe, дополнительные
+// атрибуты дописываются в конец строки и старыми программами игнорируются.
+const FLAG_DISABLED: &str = "disabled";
+const ATTR_ALGORITHM: &str = "alg";
+const ATTR_FORMAT: &str = "fmt";
+const ATTR_MODE: &str = "mode";
+const ATTR_SUITE: &str = "suite";
+const ATTR_COUNTER: &str = "counter";
+const ATTR_HASH: &str = "hash";
+const ATTR_SEED: &str = "seed";
+const ATTR_SEQUENCE: &str = "seq";
+const ATTR_LAST: &str = "last";
+const ATTR_RECOVERY: &str = "rc";
+const ATTR_SLOT: &str = "slot";
+const ATTR_STEP: &str = "step";
+const ATTR_GROUP: &str = "group";
+const ATTR_PAST: &str = "past";
+const ATTR_FUTURE: &str = "future";
+
+pub const DATABASE_FILE: &str = "database.txt";
+
+#[derive(Clone)]
+pub struct User {
+    pub name: String,
+    pub login: String,
+    pub bs2: SecretString,
+    pub date: String,
+    pub time: String,
+    pub disabled: bool,
+    pub algorithm: Algorithm,
+    pub format: OutputFormat,
+    pub mode: AuthMode,
+    pub suite: OcraSuite,
+    pub counter: u64,
+    // Шаг квантования времени, секунд (1 - исходная схема)
+    pub step: u64,
+    // Группа с политикой окна из server.toml и собственные границы окна,
+    // секунд; не заданные значения берутся из группы или общего окна
+    pub group: Option<String>,
+    pub past: Option<u64>,
+    pub future: Option<u64>,
+    // Интервал печатного списка паролей (0 - списком не пользуется)
+    pub slot: u64,
+    pub chain: HashChain,
+    // Хеши Argon2 неиспользованных кодов восстановления
+    pub recovery: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+// Состояние цепочки S/KEY: sequence - номер пароля, который клиент должен
+// ввести следующим; last - последний принятый пароль. Пока last не задан,
+// он вычисляется из BS2 как пароль с номером sequence + 1.
+#[derive(Clone, PartialEq, Debug)]
+pub struct HashChain {
+    pub hash: ChainHash,
+    pub seed: String,
+    pub sequence: u64,
+    pub last: Option<u64>,
+}
+
+impl Default for HashChain {
+    fn default() -> Self {
+        Self {
+            hash: ChainHash::Md5,
+            seed: String::new(),
+            sequence: skey::DEFAULT_SEQUENCE,
+            last: None,
+        }
+    }
+}
+
+impl User {
+    // Строка с BS2 собирается сразу в буфер нужной длины: атрибуты отдельно,
+    // затем пять полей и атрибуты
+    pub fn to_line(&self) -> SecretString {
+        let mut attributes = String::new();
+
+        if self.disabled {
+            attributes.push(' ');
+            attributes.push_str(FLAG_DISABLED);
+        }
+
+        if self.algorithm != Algorithm::Des {
+            attributes.push_str(&format!(" {}={}", ATTR_ALGORITHM, self.algorithm.name()));
+        }
+
+        if self.format != OutputFormat::Hex {
+            attributes.push_str(&format!(" {}={}", ATTR_FORMAT, self.format.name()));
+        }
+
+        if self.mode != AuthMode::Time {
+            attributes.push_str(&format!(" {}={}", ATTR_MODE, self.mode.name()));
+        }
+
+        if self.suite.as_str() != DEFAULT_SUITE {
+            attributes.push_str(&format!(" {}={}", ATTR_SUITE, self.suite.as_str()));
+        }
+
+        if self.mode == AuthMode::Counter || self.counter != 0 {
+            attributes.push_str(&format!(" {}={}", ATTR_COUNTER, self.counter));
+        }
+
+        if self.step != 1 {
+            attributes.push_str(&format!(" {}={}", ATTR_STEP, self.step));
+        }
+
+        if let Some(group) = &self.group {
+            attributes.push_str(&format!(" {}={}", ATTR_GROUP, group));
+        }
+
+        if let Some(past) = self.past {
+            attributes.push_str(&format!(" {}={}", ATTR_PAST, past));
+        }
+
+        if let Some(future) = self.future {
+            attributes.push_str(&format!(" {}={}", ATTR_FUTURE, future));
+        }
+
+        if self.slot != 0 {
+            attributes.push_str(&format!(" {}={}", ATTR_SLOT, self.slot));
+        }
+
+        if self.mode == AuthMode::HashChain {
+            attributes.push_str(&format!(
+                " {}={} {}={} {}={}",
+                ATTR_HASH, self.chain.hash.name(),
+                ATTR_SEED, self.chain.seed,
+                ATTR_SEQUENCE, self.chain.sequence
+            ));
+            if let Some(last) = self.chain.last {
+                attributes.push_str(&format!(" {}={}", ATTR_LAST, skey::to_hex(last)));
+            }
+        }
+
+        for hash in &self.recovery {
+            attributes.push_str(&format!(" {}={}", ATTR_RECOVERY, hash));
+        }
+
+        for attr in &self.extra {
+            attributes.push(' ');
+            attributes.push_str(attr);
+        }
+
+        let fields = [self.name.as_str(), &self.login, &self.bs2, &self.date, &self.time];
+        let mut line = secret::buffer(fields.iter().map(|field| field.len() + 1).sum::<usize>() + attributes.len());
+        for field in fields {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(field);
+        }
+        line.push_str(&attributes);
+
+        line
+    }
+
+    pub fn datetime(&self) -> Result<NaiveDateTime, String> {
+        parse_datetime(&format!("{} {}", self.date, self.time))
+    }
+}
+
+// Возвращает разобранных пользователей и нераспознанные строки; причины
+// ошибок по строкам сообщает validator::validate_database
+pub fn parse_database(text: &str) -> (Vec<User>, Vec<String>) {
+    let mut users = Vec::new();
+    let mut invalid = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match parse_user(line) {
+            Ok(user) => users.push(user),
+            Err(_) => invalid.push(line.to_string()),
+        }
+    }
+
+    (users, invalid)
+}
+
+pub fn parse_user(line: &str) -> Result<User, String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 5 {
+        return Err(format!("ожидается 5 полей, найдено {}", parts.len()));
+    }
+
+    let mut user = User {
+        name: parts[0].to_string(),
+        login: parts[1].to_string(),
+        bs2: secret::uppercase(parts[2]),
+        date: parts[3].to_string(),
+        time: parts[4].to_string(),
+        disabled: false,
+        algorithm: Algorithm::Des,
+        format: OutputFormat::Hex,
+        mode: AuthMode::Time,
+        suite: default_suite(),
+        counter: 0,
+        step: 1,
+        group: None,
+        past: None,
+        future: None,
+        slot: 0,
+        chain: HashChain::default(),
+        recovery: Vec::new(),
+        extra: Vec::new(),
+    };
+
+    for attr in &parts[5..] {
+        match attr.split_once('=') {
+            Some((ATTR_ALGORITHM, value)) => {
+                user.algorithm = Algorithm::parse(value)
+                    .ok_or_else(|| format!("неизвестный алгоритм {}", value))?;
+            }
+            Some((ATTR_FORMAT, value)) => {
+                user.format = OutputFormat::parse(value)
+                    .ok_or_else(|| format!("неизвестный формат пароля {}", value))?;
+            }
+            Some((ATTR_MODE, value)) => {
+                user.mode = AuthMode::parse(value)
+                    .ok_or_else(|| format!("неизвестный режим {}", value))?;
+            }
+            Some((ATTR_SUITE, value)) => user.suite = OcraSuite::parse(value)?,
+            Some((ATTR_COUNTER, value)) => {
+                user.counter = value
+                    .parse()
+                    .map_err(|_| format!("неверное значение счётчика {}", value))?;
+            }
+            Some((ATTR_STEP, value)) => {
+                user.step = value
+                    .parse()
+                    .ok()
+                    .filter(|step| (1..=MAX_TIME_STEP).contains(step))
+                    .ok_or_else(|| format!("шаг времени {}: от 1 до {} секунд", value, MAX_TIME_STEP))?;
+            }
+            Some((ATTR_GROUP, value)) if !value.is_empty() => user.group = Some(value.to_string()),
+            Some((ATTR_PAST, value)) => user.past = Some(parse_window(value)?),
+            Some((ATTR_FUTURE, value)) => user.future = Some(parse_window(value)?),
+            Some((ATTR_SLOT, value)) => {
+                user.slot = value
+                    .parse()
+                    .ok()
+                    .filter(|slot| *slot <= MAX_SLOT)
+                    .ok_or_else(|| format!("интервал списка паролей {}: от 0 до {} секунд", value, MAX_SLOT))?;
+            }
+            Some((ATTR_HASH, value)) => {
+                user.chain.hash = ChainHash::parse(value)
+                    .ok_or_else(|| format!("неизвестный хеш цепочки {}", value))?;
+            }
+            Some((ATTR_SEED, value)) => {
+                skey::validate_seed(value)?;
+                user.chain.seed = value.to_string();
+            }
+            Some((ATTR_SEQUENCE, value)) => {
+                user.chain.sequence = value
+                    .parse()
+                    .map_err(|_| format!("неверный номер в цепочке {}", value))?;
+            }
+            Some((ATTR_LAST, value)) => {
+                user.chain.last = Some(
+                    u64::from_str_radix(value, 16)
+                        .ok()
+                        .filter(|_| value.len() == 16)
+                        .ok_or_else(|| format!("неверный последний пароль цепочки {}", value))?,
+                );
+            }
+            Some((ATTR_RECOVERY, value)) => {
+                recovery::validate_hash(value)?;
+                user.recovery.push(value.to_string());
+            }
+            _ if *attr == FLAG_DISABLED => user.disabled = true,
+            _ => user.extra.push(attr.to_string()),
+        }
+    }
+
+    if user.mode == AuthMode::HashChain && user.chain.seed.is_empty() {
+        return Err("для цепочки S/KEY не задан seed".to_string());
+    }
+
+    Ok(user)
+}
+
+fn parse_window(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|window| *window <= MAX_WINDOW)
+        .ok_or_else(|| format!("окно {}: от 0 до {} секунд", value, MAX_WINDOW))
+}
+
+// Счётчик и данные сессии OCRA в этой системе не используются
+pub fn validate_suite(suite: &OcraSuite) -> Result<(), String> {
+    if suite.counter || suite.session_len.is_some() {
+        return Err(format!("OCRASuite {}: параметры C и S не поддерживаются", suite.as_str()));
+    }
+    Ok(())
+}
+
+pub fn default_suite() -> OcraSuite {
+    OcraSuite::parse(DEFAULT_SUITE).expect("DEFAULT_SUITE")
+}
+
+pub fn format_database(users: &[User], invalid: &[String]) -> SecretString {
+    let lines: Vec<SecretString> = users.iter().map(User::to_line).collect();
+    let length = lines.iter().map(|line| line.len() + 1).chain(invalid.iter().map(|line| line.len() + 1)).sum();
+    let mut text = secret::buffer(length);
+
+    for line in &lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    // Нераспознанные строки не теряются при сохранении из таблицы
+    for line in invalid {
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    text
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Укажите ФИО".to_string());
+    }
+    if name.chars().any(char::is_whitespace) {
+        return Err("ФИО без пробелов (используйте _)".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_login(login: &str) -> Result<(), String> {
+    if login.is_empty() {
+        return Err("Укажите логин".to_string());
+    }
+    if login.chars().any(char::is_whitespace) {
+        return Err("Логин без пробелов".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_bs2(bs2: &str, algorithm: Algorithm) -> Result<(), String> {
+    let expected = algorithm.key_hex_len();
+    if bs2.len() != expected || !bs2.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "BS2 ({}): {} HEX символов, получено {}",
+            algorithm.title(), expected, bs2.chars().count()
+        ));
+    }
+    Ok(())
+}
+
+pub fn parse_datetime(s: &str) -> Result<NaiveDateTime, String> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    if parts.len() != 2 {
+        return Err("Неверный формат".to_string());
+    }
+
+    let date_parts: Vec<&str> = parts[0].split('.').collect();
+    let time_parts: Vec<&str> = parts[1].split(':').collect();
+
+    if date_parts.len() != 3 || time_parts.len() != 3 {
+        return Err("Неверный формат".to_string());
+    }
+
+    let day: u32 = date_parts[0].parse().map_err(|_| "Неверный день")?;
+    let month: u32 = date_parts[1].parse().map_err(|_| "Неверный месяц")?;
+    let year: i32 = date_parts[2].parse().map_err(|_| "Неверный год")?;
+
+    let hour: u32 = time_parts[0].parse().map_err(|_| "Неверный час")?;
+    let minute: u32 = time_parts[1].parse().map_err(|_| "Неверная минута")?;
+    let second: u32 = time_parts[2].parse().map_err(|_| "Неверная секунда")?;
+
+    NaiveDateTime::parse_from_str(
+        &format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second),
+        "%Y-%m-%d %H:%M:%S"
+    ).map_err(|e| format!("Ошибка: {}", e))
+}
@@ -0,0 +1,203 @@
+use eframe::egui;
+
+use otp_server::api_keys::{KeyStore, Scope};
+use otp_server::audit;
+
+// Клиенты API службы gRPC: выдача и отзыв ключей. Изменения записываются в
+// файл сразу, служба читает его при каждом вызове.
+pub struct KeyPanel {
+    // Файл, с которым работает панель; новый путь из настроек - после их
+    // сохранения
+    path: String,
+    store: KeyStore,
+    name: String,
+    scopes: [bool; Scope::ALL.len()],
+    pending_revoke: Option<usize>,
+    // Выданный ключ показывается один раз, до закрытия окна
+    issued: Option<(String, String)>,
+    message: String,
+}
+
+impl KeyPanel {
+    pub fn new(path: &str) -> Self {
+        let mut panel = Self {
+            path: path.to_string(),
+            store: KeyStore::default(),
+            name: String::new(),
+            scopes: [true, false, false, false],
+            pending_revoke: None,
+            issued: None,
+            message: String::new(),
+        };
+        panel.load();
+        panel
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn open(&mut self, path: &str) {
+        self.path = path.to_string();
+        self.load();
+    }
+
+    fn load(&mut self) {
+        match KeyStore::load(&self.path) {
+            Ok(store) => {
+                self.store = store;
+                self.message.clear();
+            }
+            Err(e) => self.message = e,
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if self.store.clients.is_empty() {
+            ui.label("Ключей нет: служба gRPC отклоняет все вызовы.");
+        }
+
+        egui::Grid::new("api_keys_grid").striped(true).num_columns(5).spacing([12.0, 4.0]).show(ui, |ui| {
+            if !self.store.clients.is_empty() {
+                ui.strong("Номер");
+                ui.strong("Клиент");
+                ui.strong("Разрешения");
+                ui.strong("Выдан");
+                ui.label("");
+                ui.end_row();
+            }
+            for (index, client) in self.store.clients.iter().enumerate() {
+                ui.label(egui::RichText::new(&client.id).monospace());
+                ui.label(&client.name);
+                let scopes: Vec<&str> = client.scopes.iter().map(|scope| scope.title()).collect();
+                ui.label(scopes.join(", "));
+                ui.label(&client.created);
+                if ui.small_button("🗑").on_hover_text("Отозвать").clicked() {
+                    self.pending_revoke = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Клиент:");
+            ui.add(egui::TextEdit::singleline(&mut self.name).hint_text("vpn-gateway").desired_width(140.0));
+            for (scope, enabled) in Scope::ALL.iter().zip(self.scopes.iter_mut()) {
+                ui.checkbox(enabled, scope.title());
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("🔑 Выдать ключ").clicked() {
+                self.issue();
+            }
+            if ui.button("📂 Перечитать").clicked() {
+                self.load();
+            }
+        });
+        if !self.message.is_empty() {
+            ui.label(egui::RichText::new(&self.message).small());
+        }
+
+        self.revoke_window(ui.ctx());
+        self.issued_window(ui.ctx());
+    }
+
+    fn issue(&mut self) {
+        let scopes: Vec<Scope> = Scope::ALL
+            .iter()
+            .zip(self.scopes)
+            .filter(|(_, enabled)| *enabled)
+            .map(|(scope, _)| *scope)
+            .collect();
+        // Файл перечитывается: ключи могли выдать из командной строки
+        let result = KeyStore::load(&self.path).and_then(|mut store| {
+            let key = store.issue(&self.name, &scopes)?;
+            store.save(&self.path)?;
+            Ok((store, key))
+        });
+        match result {
+            Ok((store, key)) => {
+                let name = self.name.trim().to_string();
+                audit::record(&name, "выдан ключ API");
+                self.store = store;
+                self.issued = Some((name, key));
+                self.name.clear();
+                self.message.clear();
+            }
+            Err(e) => self.message = e,
+        }
+    }
+
+    fn revoke_window(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.pending_revoke else {
+            return;
+        };
+
+        let mut confirm = false;
+        let mut cancel = false;
+
+        egui::Window::new("Отзыв ключа API")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let client = &self.store.clients[index];
+                ui.label(format!("Отозвать ключ {} клиента {}?", client.id, client.name));
+                ui.label("Вызовы с этим ключом сразу перестанут выполняться.");
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    confirm = ui.button("🗑️ Отозвать").clicked();
+                    cancel = ui.button("Отмена").clicked();
+                });
+            });
+
+        if confirm {
+            let id = self.store.clients[index].id.clone();
+            let result = KeyStore::load(&self.path).and_then(|mut store| {
+                let client = store.revoke(&id);
+                store.save(&self.path)?;
+                Ok((store, client))
+            });
+            match result {
+                Ok((store, client)) => {
+                    if let Some(client) = client {
+                        audit::record(&client.name, "отозван ключ API");
+                    }
+                    self.store = store;
+                }
+                Err(e) => self.message = e,
+            }
+        }
+        if confirm || cancel {
+            self.pending_revoke = None;
+        }
+    }
+
+    fn issued_window(&mut self, ctx: &egui::Context) {
+        let Some((name, key)) = self.issued.as_ref() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut close = false;
+
+        egui::Window::new(format!("Ключ API: {}", name))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Ключ показывается один раз: сервер хранит только его хеш.");
+                ui.add(
+                    egui::TextEdit::singleline(&mut key.as_str())
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(560.0),
+                );
+                ui.add_space(10.0);
+                close = ui.button("Закрыть").clicked();
+            });
+
+        if !open || close {
+            self.issued = None;
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use chrono::Local;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::config::Config;
+use crate::tls::{self, Certificates};
+use crate::verifier::{self, Reply};
+
+// Прокси LDAP для приложений, которые проверяют пароль простой привязкой
+// (simple bind). Пароль привязки - <пароль LDAP><одноразовый пароль>:
+// одноразовый пароль отрезается с конца по длине формата пользователя
+// (16 HEX символов по умолчанию) и проверяется по базе, остаток уходит
+// серверу LDAP. Остальные операции RFC 4511 передаются без изменений; на
+// неразобранное сообщение или неизвестную операцию прокси отвечает
+// уведомлением о разрыве и закрывает соединение, чтобы привязка, которую не
+// понял прокси, но понял бы каталог, не прошла мимо проверки.
+//
+// После отказа соединение закрывается: иначе сервер LDAP продолжил бы
+// обслуживать его от имени предыдущей успешной привязки.
+
+pub const SUCCESS: u8 = 0;
+pub const PROTOCOL_ERROR: u8 = 2;
+pub const AUTH_METHOD_NOT_SUPPORTED: u8 = 7;
+pub const INVALID_CREDENTIALS: u8 = 49;
+pub const UNAVAILABLE: u8 = 52;
+
+pub const BIND_REQUEST: u8 = 0x60;
+pub const BIND_RESPONSE: u8 = 0x61;
+pub const EXTENDED_REQUEST: u8 = 0x77;
+pub const EXTENDED_RESPONSE: u8 = 0x78;
+
+// Операции, которые прокси передаёт каталогу как есть: unbind, search,
+// modify, add, delete, modifyDN, compare, abandon и extended
+const FORWARDED: [u8; 9] = [0x42, 0x63, 0x66, 0x68, 0x4A, 0x6C, 0x6E, 0x50, EXTENDED_REQUEST];
+
+// Запрос клиента: привязке и обычным операциям хватает нескольких КиБ.
+// Ответ каталога с найденными записями бывает больше, его предел - как у
+// записей с фотографиями.
+pub const MAX_REQUEST: usize = 16 << 10;
+pub const MAX_RESPONSE: usize = 16 << 20;
+
+const START_TLS: &[u8] = b"1.3.6.1.4.1.1466.20037";
+const NOTICE_OF_DISCONNECTION: &[u8] = b"1.3.6.1.4.1.1466.20036";
+
+pub fn serve(config: Config) -> Result<(), String> {
+    if config.ldap.upstream.is_empty() {
+        return Err("ldap.upstream: не задан сервер LDAP".to_string());
+    }
+    let certificates = Certificates::load(&config.tls, &[])?;
+    let listener = TcpListener::bind(&config.ldap.listen).map_err(|e| format!("{}: {}", config.ldap.listen, e))?;
+
+    let config = Arc::new(config);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let config = Arc::clone(&config);
+        let certificates = certificates.clone();
+        thread::spawn(move || {
+            if let Err(e) = proxy(&config, certificates.as_deref(), stream) {
+                eprintln!("ldap: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+// Что делать с сообщением клиента
+enum Action {
+    Forward(Vec<u8>),
+    Reply(Vec<u8>),
+    // Ответ клиенту и закрытие соединения
+    Close(Vec<u8>),
+}
+
+fn proxy(config: &Config, certificates: Option<&Certificates>, client: TcpStream) -> io::Result<()> {
+    let closer = client.try_clone()?;
+    let (reader, writer) = tls::split(client, certificates)?;
+    let mut upstream = TcpStream::connect(&config.ldap.upstream)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", config.ldap.upstream, e)))?;
+    let writer = Arc::new(Mutex::new(writer));
+
+    // Ответы сервера LDAP передаются клиенту целыми сообщениями, чтобы не
+    // перемешаться с ответами самого прокси
+    let relay = {
+        let mut reader = BufReader::new(upstream.try_clone()?);
+        let writer = Arc::clone(&writer);
+        thread::spawn(move || {
+            while let Ok(Some(message)) = read_message(&mut reader, MAX_RESPONSE) {
+                if writer.lock().unwrap().write_all(&message).is_err() {
+                    break;
+                }
+            }
+            let _ = closer.shutdown(Shutdown::Both);
+        })
+    };
+
+    let mut reader = BufReader::new(reader);
+    let result = loop {
+        let message = match read_message(&mut reader, MAX_REQUEST) {
+            Ok(Some(message)) => message,
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        };
+        let sent = match intercept(config, message) {
+            Action::Forward(message) => upstream.write_all(&message),
+            Action::Reply(message) => writer.lock().unwrap().write_all(&message),
+            Action::Close(message) => {
+                let _ = writer.lock().unwrap().write_all(&message);
+                break Ok(());
+            }
+        };
+        if let Err(e) = sent {
+            break Err(e);
+        }
+    };
+
+    let _ = upstream.shutdown(Shutdown::Both);
+    let _ = relay.join();
+    result
+}
+
+fn intercept(config: &Config, message: Vec<u8>) -> Action {
+    let Some(envelope) = parse_message(&message) else {
+        return Action::Close(disconnection(PROTOCOL_ERROR, "неразборчивое сообщение LDAP"));
+    };
+    let id = envelope.id;
+
+    match envelope.op {
+        BIND_REQUEST => {
+            let Some(bind) = BindRequest::parse(envelope.body) else {
+                return Action::Close(ldap_result(id, BIND_RESPONSE, PROTOCOL_ERROR, "неверный запрос привязки"));
+            };
+            let Some(password) = bind.password else {
+                return Action::Close(ldap_result(
+                    id,
+                    BIND_RESPONSE,
+                    AUTH_METHOD_NOT_SUPPORTED,
+                    "поддерживается только простая привязка",
+                ));
+            };
+            // Анонимная привязка ничего не даёт и передаётся без проверки,
+            // но в том виде, в каком её понял прокси
+            if bind.name.is_empty() && password.is_empty() {
+                return Action::Forward(encode_message(id, BIND_REQUEST, &bind_body(bind.version, "", b""), envelope.controls));
+            }
+
+            let Ok(password) = String::from_utf8(password) else {
+                return Action::Close(ldap_result(id, BIND_RESPONSE, INVALID_CREDENTIALS, "пароль не в UTF-8"));
+            };
+            match check(config, &bind.name, &password) {
+                Ok(remainder) => Action::Forward(encode_message(
+                    id,
+                    BIND_REQUEST,
+                    &bind_body(bind.version, &bind.name, remainder.as_bytes()),
+                    envelope.controls,
+                )),
+                Err((code, text)) => Action::Close(ldap_result(id, BIND_RESPONSE, code, &text)),
+            }
+        }
+        // После StartTLS поток шифруется и прокси не смог бы найти привязку
+        EXTENDED_REQUEST if tlv(envelope.body).is_some_and(|(tag, name, _)| tag == 0x80 && name == START_TLS) => {
+            Action::Reply(ldap_result(id, EXTENDED_RESPONSE, PROTOCOL_ERROR, "StartTLS через прокси не поддерживается"))
+        }
+        op if FORWARDED.contains(&op) => Action::Forward(message),
+        _ => Action::Close(disconnection(PROTOCOL_ERROR, "неизвестная операция LDAP")),
+    }
+}
+
+// Проверка одноразового пароля в конце пароля привязки; возвращает пароль
+// для сервера LDAP или код и текст отказа
+fn check(config: &Config, dn: &str, password: &str) -> Result<String, (u8, String)> {
+    let unavailable = |e: String| {
+        eprintln!("ldap: {}", e);
+        (UNAVAILABLE, "проверка одноразового пароля недоступна".to_string())
+    };
+
+    let login = login_from_dn(dn, &config.ldap.attribute).map_err(|e| (INVALID_CREDENTIALS, e))?;
+    let length = verifier::find_user(config, &login)
+        .map_err(unavailable)?
+        .map_or(16, |user| user.format.length());
+    let split = password.char_indices().rev().nth(length - 1).map_or(0, |(i, _)| i);
+    let (remainder, otp) = password.split_at(split);
+
+    let outcome = verifier::verify(config, None, &login, otp, Local::now().naive_local()).map_err(unavailable)?;
+    match Reply::from_outcome(&outcome, config.denial) {
+        Reply::Granted(_) => Ok(remainder.to_string()),
+        Reply::Error(e) => Err(unavailable(e)),
+        reply => Err((INVALID_CREDENTIALS, reply.text().to_string())),
+    }
+}
+
+// Значение атрибута attribute в DN: uid=Johnny,ou=people,dc=example ->
+// Johnny. Без такого атрибута (или при пустом attribute) логин - весь DN.
+// Экранирование RFC 4514 снимается, RDN из нескольких пар (cn=a+uid=b)
+// разбирается по парам. Значения в кавычках и в виде #BER, а также
+// повтор атрибута в DN отклоняются: логин не должен зависеть от того, как
+// разные программы поймут такой DN.
+pub fn login_from_dn(dn: &str, attribute: &str) -> Result<String, String> {
+    // Привязка по имени без пар атрибут=значение
+    if !dn.contains('=') {
+        return Ok(dn.trim().to_string());
+    }
+    let pairs = parse_dn(dn)?;
+    if attribute.is_empty() {
+        return Ok(dn.trim().to_string());
+    }
+
+    let mut found = None;
+    for (_, value) in pairs.into_iter().filter(|(name, _)| name.eq_ignore_ascii_case(attribute)) {
+        if found.replace(value).is_some() {
+            return Err(format!("атрибут {} повторяется в DN", attribute));
+        }
+    }
+    Ok(found.unwrap_or_else(|| dn.trim().to_string()))
+}
+
+// Символы, которые в значении DN экранируются обратной косой чертой
+const DN_SPECIAL: &str = " \"#+,;<=>\\";
+
+// Пары атрибут-значение DN (RFC 4514); пустой DN - пустой список
+fn parse_dn(dn: &str) -> Result<Vec<(String, String)>, String> {
+    let invalid = || format!("неверный DN: {}", dn);
+    let mut pairs = Vec::new();
+    if dn.trim().is_empty() {
+        return Ok(pairs);
+    }
+
+    let mut chars = dn.chars().peekable();
+    loop {
+        let mut name = String::new();
+        loop {
+            match chars.next().ok_or_else(invalid)? {
+                '=' => break,
+                c => name.push(c),
+            }
+        }
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.') {
+            return Err(invalid());
+        }
+
+        while chars.next_if_eq(&' ').is_some() {}
+        if matches!(chars.peek(), Some('#' | '"')) {
+            return Err(format!("DN со значением в кавычках или #BER не поддерживается: {}", dn));
+        }
+
+        // Значение до неэкранированного разделителя; пробелы в конце
+        // значимы, только если экранированы
+        let mut value = Vec::new();
+        let mut significant = 0;
+        let mut utf8 = [0u8; 4];
+        let last = loop {
+            match chars.next() {
+                None => break true,
+                Some(',' | ';' | '+') => break false,
+                Some('"') => return Err(format!("DN со значением в кавычках или #BER не поддерживается: {}", dn)),
+                Some('\\') => {
+                    match chars.next().ok_or_else(invalid)? {
+                        high if high.is_ascii_hexdigit() => {
+                            let low = chars.next().filter(|c| c.is_ascii_hexdigit()).ok_or_else(invalid)?;
+                            let byte = high.to_digit(16).unwrap_or_default() << 4 | low.to_digit(16).unwrap_or_default();
+                            value.push(byte as u8);
+                        }
+                        c if DN_SPECIAL.contains(c) => value.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes()),
+                        _ => return Err(invalid()),
+                    }
+                    significant = value.len();
+                }
+                Some(c) => {
+                    value.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                    if c != ' ' {
+                        significant = value.len();
+                    }
+                }
+            }
+        };
+        value.truncate(significant);
+        pairs.push((name.to_string(), String::from_utf8(value).map_err(|_| invalid())?));
+        if last {
+            return Ok(pairs);
+        }
+    }
+}
+
+// --- BER (X.690) в объёме LDAPMessage из RFC 4511 ---
+
+// Одно сообщение LDAP целиком, не длиннее limit; None - соединение закрыто.
+// Содержимое читается по мере прихода: длина из заголовка не выделяется
+// заранее.
+pub fn read_message<R: Read>(reader: &mut R, limit: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 2];
+    match reader.read_exact(&mut header[..1]) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    reader.read_exact(&mut header[1..])?;
+    if header[0] != 0x30 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "не сообщение LDAP"));
+    }
+
+    let mut message = header.to_vec();
+    let length = match header[1] {
+        short if short < 0x80 => short as usize,
+        long @ 0x81..=0x84 => {
+            let mut bytes = vec![0u8; (long & 0x7F) as usize];
+            reader.read_exact(&mut bytes)?;
+            message.extend_from_slice(&bytes);
+            bytes.iter().fold(0usize, |length, byte| length << 8 | *byte as usize)
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "неверная длина сообщения LDAP")),
+    };
+    if length > limit {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "слишком длинное сообщение LDAP"));
+    }
+
+    let start = message.len();
+    reader.take(length as u64).read_to_end(&mut message)?;
+    if message.len() - start < length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some(message))
+}
+
+// Разобранный конверт LDAPMessage: номер, тег операции, её содержимое и
+// необязательные элементы управления (controls) в исходном виде
+pub struct Envelope<'a> {
+    pub id: i64,
+    pub op: u8,
+    pub body: &'a [u8],
+    pub controls: &'a [u8],
+}
+
+pub fn parse_message(message: &[u8]) -> Option<Envelope<'_>> {
+    let (0x30, content, _) = tlv(message)? else {
+        return None;
+    };
+    let (0x02, id, rest) = tlv(content)? else {
+        return None;
+    };
+    let (op, body, controls) = tlv(rest)?;
+    Some(Envelope { id: integer(id)?, op, body, controls })
+}
+
+pub struct BindRequest {
+    pub version: i64,
+    pub name: String,
+    // None - привязка SASL
+    pub password: Option<Vec<u8>>,
+}
+
+impl BindRequest {
+    pub fn parse(body: &[u8]) -> Option<Self> {
+        let (0x02, version, rest) = tlv(body)? else {
+            return None;
+        };
+        let (0x04, name, rest) = tlv(rest)? else {
+            return None;
+        };
+        let (tag, password, _) = tlv(rest)?;
+        Some(Self {
+            version: integer(version)?,
+            name: String::from_utf8(name.to_vec()).ok()?,
+            password: (tag == 0x80).then(|| password.to_vec()),
+        })
+    }
+}
+
+pub fn bind_request(id: i64, name: &str, password: &[u8]) -> Vec<u8> {
+    encode_message(id, BIND_REQUEST, &bind_body(3, name, password), &[])
+}
+
+// LDAPResult: код, matchedDN и диагностическое сообщение
+pub fn ldap_result(id: i64, op: u8, code: u8, text: &str) -> Vec<u8> {
+    let mut body = encode(0x0A, &[code]);
+    body.extend(encode(0x04, b""));
+    body.extend(encode(0x04, text.as_bytes()));
+    encode_message(id, op, &body, &[])
+}
+
+// Уведомление о разрыве соединения (RFC 4511, 4.4.1): номер сообщения 0
+pub fn disconnection(code: u8, text: &str) -> Vec<u8> {
+    let mut body = encode(0x0A, &[code]);
+    body.extend(encode(0x04, b""));
+    body.extend(encode(0x04, text.as_bytes()));
+    body.extend(encode(0x8A, NOTICE_OF_DISCONNECTION));
+    encode_message(0, EXTENDED_RESPONSE, &body, &[])
+}
+
+// Код результата из содержимого ответа (BindResponse, ExtendedResponse ...)
+pub fn result_code(body: &[u8]) -> Option<u8> {
+    match tlv(body)? {
+        (0x0A, [code], _) => Some(*code),
+        _ => None,
+    }
+}
+
+fn bind_body(version: i64, name: &str, password: &[u8]) -> Vec<u8> {
+    let mut body = encode(0x02, &integer_bytes(version));
+    body.extend(encode(0x04, name.as_bytes()));
+    body.extend(encode(0x80, password));
+    body
+}
+
+fn encode_message(id: i64, op: u8, body: &[u8], controls: &[u8]) -> Vec<u8> {
+    let mut content = encode(0x02, &integer_bytes(id));
+    content.extend(encode(op, body));
+    content.extend_from_slice(controls);
+    encode(0x30, &content)
+}
+
+fn encode(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let length = value.len();
+    if length < 0x80 {
+        out.push(length as u8);
+    } else {
+        let bytes: Vec<u8> = length.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+// Тег, значение и остаток после элемента
+fn tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = match first {
+        short if short < 0x80 => (short as usize, rest),
+        long @ 0x81..=0x84 => {
+            let count = (long & 0x7F) as usize;
+            let bytes = rest.get(..count)?;
+            (bytes.iter().fold(0usize, |length, byte| length << 8 | *byte as usize), &rest[count..])
+        }
+        _ => return None,
+    };
+    let value = rest.get(..length)?;
+    Some((tag, value, &rest[length..]))
+}
+
+fn integer(bytes: &[u8]) -> Option<i64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    let sign = if bytes[0] & 0x80 != 0 { -1i64 } else { 0 };
+    Some(bytes.iter().fold(sign, |value, byte| value << 8 | *byte as i64))
+}
+
+// Минимальная запись целого в дополнительном коде
+fn integer_bytes(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    while start < 7
+        && ((bytes[start] == 0 && bytes[start + 1] & 0x80 == 0) || (bytes[start] == 0xFF && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
@@ -0,0 +1,14 @@
+// Проверка паролей без GUI: общая часть сервера, модуля PAM и демона
+pub mod admins;
+pub mod api_keys;
+pub mod audit;
+pub mod auth;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod database;
+pub mod ldap;
+pub mod recovery;
+pub mod tls;
+pub mod validator;
+pub mod verifier;
@@ -0,0 +1,71 @@
+use eframe::egui;
+
+use otp_server::config::Config;
+
+// Последние записи журнала, без изменения файла
+const MAX_LINES: usize = 1000;
+
+#[derive(Default)]
+pub struct LogView {
+    lines: Option<Vec<String>>,
+    filter: String,
+    message: String,
+}
+
+impl LogView {
+    pub fn show(&mut self, ui: &mut egui::Ui, config: &Config) {
+        if config.log.audit.is_empty() {
+            ui.label("Журнал в файл не пишется (log.audit пуст).");
+            return;
+        }
+        if self.lines.is_none() {
+            self.load(config);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("🔍 Фильтр:");
+            ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("логин или текст").desired_width(200.0));
+            if ui.button("🔄 Обновить").clicked() {
+                self.load(config);
+            }
+        });
+        if !self.message.is_empty() {
+            ui.label(egui::RichText::new(&self.message).small());
+        }
+
+        let filter = self.filter.trim().to_lowercase();
+        egui::ScrollArea::vertical()
+            .id_source("audit_log")
+            .max_height(240.0)
+            .auto_shrink([false, true])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in self.lines.iter().flatten() {
+                    if filter.is_empty() || line.to_lowercase().contains(&filter) {
+                        ui.label(egui::RichText::new(line).monospace());
+                    }
+                }
+            });
+    }
+
+    fn load(&mut self, config: &Config) {
+        let path = &config.log.audit;
+        if !std::path::Path::new(path).exists() {
+            self.message = format!("{}: записей нет", path);
+            self.lines = Some(Vec::new());
+            return;
+        }
+        match config.encoding.read(path) {
+            Ok(decoded) => {
+                let lines: Vec<&str> = decoded.text.lines().collect();
+                let skip = lines.len().saturating_sub(MAX_LINES);
+                self.message = format!("{}: записей {}, показаны последние {}", path, lines.len(), lines.len() - skip);
+                self.lines = Some(lines[skip..].iter().map(|line| line.to_string()).collect());
+            }
+            Err(e) => {
+                self.message = format!("{}: {}", path, e);
+                self.lines = Some(Vec::new());
+            }
+        }
+    }
+}
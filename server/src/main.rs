@@ -1,19 +1,60 @@
+mod admin_panel;
+mod cli;
+mod key_panel;
+mod log_view;
+mod user_table;
+
 use eframe::egui;
-use des::cipher::{BlockEncrypt, KeyInit};
-use des::Des;
-use hex::{encode, decode};
-use chrono::{NaiveDateTime, Local};
+use chrono::Local;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
 use std::time::Instant;
-use encoding_rs::WINDOWS_1251;
 
-const DATABASE_FILE: &str = "database.txt";
+use otp_server::auth::{issue_challenge, Challenge, DenialPolicy, Outcome, COUNTER_RESYNC_WINDOW};
+use otp_core::mode::AuthMode;
+use otp_core::secret::{self, SecretString, Zeroize};
+use otp_core::skey;
+use otp_core::text::TextEncoding;
+use otp_server::admins::Role;
+use otp_server::config::{Backend, Config, Window, MAX_LOCKOUT, MAX_WINDOW};
+use otp_server::database::{format_database, parse_database, User};
+use otp_server::{audit, verifier};
+use admin_panel::{AdminPanel, LoginScreen};
+use key_panel::KeyPanel;
+use log_view::LogView;
+use user_table::UserTable;
+use otp_server::validator::{validate_database, Issue, Severity};
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match cli::Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            cli::print_usage();
+            std::process::exit(2);
+        }
+    };
+    if !options.command.is_empty() {
+        std::process::exit(cli::run(&options));
+    }
+    
+    // При ошибке в настройках интерфейс показывает только её: со значениями
+    // по умолчанию вход и назначение администратора шли бы по чужим файлам
+    let (config, config_error) = match options.load_config() {
+        Ok(config) => (config, None),
+        Err(e) => {
+            let mut config = Config::default();
+            options.apply(&mut config);
+            (config, Some(e))
+        }
+    };
+    audit::configure(&config);
+    let config_path = options.config_path().to_string();
+    
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([650.0, 750.0])
+            .with_inner_size([800.0, 800.0])
             .with_resizable(true),
         ..Default::default()
     };
@@ -21,32 +62,63 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Сервер аутентификации",
         options,
-        Box::new(|_cc| Box::<ServerApp>::default()),
+        Box::new(|_cc| Box::new(ServerApp::new(config, config_path, config_error))),
     )
 }
 
-#[derive(Clone)]
-struct User {
-    name: String,
-    login: String,
-    bs2: String,
-    date: String,
-    time: String,
-}
-
 struct ServerApp {
-    database_text: String,
+    // Вошедший логин и роль; до входа интерфейс показывает только вход
+    signed_in: Option<(String, Role)>,
+    login_screen: LoginScreen,
+    // Ошибка чтения server.toml: ни входа, ни назначения администратора
+    config_error: Option<String>,
+    // BS2 в таблице и текст базы видны только по явному включению
+    reveal_secrets: bool,
+    // Затирается при очистке и замене, но копии от правки в TextEdit, его
+    // истории отмены и пересборки текста из таблицы этим не покрыты
+    database_text: SecretString,
     login: String,
     password: String,
-    time_window: String,
+    transaction: String,
+    // Выданный из интерфейса запрос; счётчики, повторы и блокировки
+    // проверяются по файлам, как у демона и модуля PAM
+    challenge: Option<Challenge>,
+    config: Config,
+    config_path: String,
+    // База, с которой сейчас работает сервер; путь в настройках вступает в
+    // силу после их сохранения
+    database_path: String,
+    // Кодировка, в которой база прочитана или последний раз записана
+    database_encoding: TextEncoding,
+    listen_text: String,
+    // Группы окон в порядке редактирования: имя можно менять, в настройки
+    // они переносятся при сохранении
+    group_rows: Vec<(String, Window)>,
     result_message: String,
+    result_detail: String,
     result_type: ResultType,
     response_time: String,
     users: Vec<User>,
+    // Записи в том виде, в каком они прочитаны из файла или записаны в него:
+    // по ним сохранение отличает правки интерфейса от изменений проверок
+    loaded_users: Vec<User>,
+    invalid_lines: Vec<String>,
+    issues: Vec<Issue>,
+    database_view: DatabaseView,
+    user_table: UserTable,
+    key_panel: KeyPanel,
+    admin_panel: AdminPanel,
+    log_view: LogView,
     error_message: String,
     success_message: String,
 }
 
+#[derive(PartialEq)]
+enum DatabaseView {
+    Table,
+    Text,
+}
+
 #[derive(PartialEq)]
 enum ResultType {
     None,
@@ -54,40 +126,87 @@ enum ResultType {
     Error,
 }
 
-impl Default for ServerApp {
-    fn default() -> Self {
+impl ServerApp {
+    fn new(config: Config, config_path: String, config_error: Option<String>) -> Self {
+        let key_panel = KeyPanel::new(&config.api.keys);
         let mut app = Self {
-            database_text: String::new(),
+            signed_in: None,
+            login_screen: LoginScreen::default(),
+            config_error,
+            reveal_secrets: false,
+            database_text: SecretString::default(),
             login: String::new(),
             password: String::new(),
-            time_window: "20".to_string(),
+            transaction: String::new(),
+            challenge: None,
+            database_path: config.database.path.clone(),
+            database_encoding: TextEncoding::Windows1251,
+            listen_text: config.listen.join("\n"),
+            group_rows: config.groups.iter().map(|(name, window)| (name.clone(), *window)).collect(),
+            config,
+            config_path,
             result_message: "Ожидание аутентификации...".to_string(),
+            result_detail: String::new(),
             result_type: ResultType::None,
             response_time: "-".to_string(),
             users: Vec::new(),
+            loaded_users: Vec::new(),
+            invalid_lines: Vec::new(),
+            issues: Vec::new(),
+            database_view: DatabaseView::Table,
+            user_table: UserTable::default(),
+            key_panel,
+            admin_panel: AdminPanel::default(),
+            log_view: LogView::default(),
             error_message: String::new(),
             success_message: String::new(),
         };
         
-        app.load_database();
+        if app.config_error.is_none() {
+            app.load_database();
+        }
         app
     }
-}
-
-impl ServerApp {
+    
     fn load_database(&mut self) {
-        if let Ok(content) = read_file_windows1251(DATABASE_FILE) {
-            self.database_text = content;
+        if let Ok(decoded) = self.config.encoding.read(&self.database_path) {
+            self.database_text = decoded.text.into();
+            self.database_encoding = decoded.encoding;
             self.parse_database();
-            self.success_message = format!("База загружена: {} пользователей", self.users.len());
+            self.loaded_users = self.users.clone();
+            self.success_message = format!(
+                "База загружена ({}): {} пользователей", decoded.encoding.title(), self.users.len()
+            );
+            if decoded.lossy {
+                self.error_message = format!(
+                    "База не в кодировке {}: недопустимые байты заменены, проверьте ФИО перед сохранением",
+                    decoded.encoding.title()
+                );
+            }
         }
     }
     
+    // Настройки с базой, с которой сейчас работает интерфейс
+    fn database_config(&self) -> Config {
+        let mut config = self.config.clone();
+        config.database.path = self.database_path.clone();
+        config
+    }
+    
+    // Запись базы в кодировке из настроек под блокировкой проверок; в режиме
+    // auto файл остаётся в той кодировке, в которой был прочитан, а
+    // Windows-1251 сменяется на UTF-8, только если иначе символы потеряются
     fn save_database(&mut self) {
-        match write_file_windows1251(DATABASE_FILE, &self.database_text) {
-            Ok(_) => {
+        let config = self.database_config();
+        match verifier::save_database(&config, &self.database_text, self.database_encoding, &self.loaded_users) {
+            Ok((encoding, text)) => {
+                self.database_encoding = encoding;
+                self.database_text = text;
                 self.parse_database();
-                self.success_message = format!("База сохранена: {} пользователей", self.users.len());
+                self.loaded_users = self.users.clone();
+                self.success_message = format!(
+                    "База сохранена ({}): {} пользователей", self.database_encoding.title(), self.users.len()
+                );
                 self.error_message.clear();
             }
             Err(e) => {
@@ -97,43 +216,71 @@ impl ServerApp {
         }
     }
     
+    fn save_config(&mut self) {
+        self.config.listen = self.listen_text.split_whitespace().map(str::to_string).collect();
+        let mut groups = BTreeMap::new();
+        for (name, window) in &self.group_rows {
+            if groups.insert(name.trim().to_string(), *window).is_some() {
+                self.error_message = format!("Ошибка сохранения настроек: группа «{}» указана дважды", name.trim());
+                self.success_message.clear();
+                return;
+            }
+        }
+        self.config.groups = groups;
+        
+        match self.config.save(&self.config_path) {
+            Ok(()) => {
+                audit::configure(&self.config);
+                self.success_message = format!("Настройки сохранены в {}", self.config_path);
+                self.error_message.clear();
+                // Замечания о группах пользователей зависят от настроек
+                self.issues = validate_database(&self.database_text, Local::now().naive_local(), &self.config);
+                if self.config.database.path != self.database_path {
+                    self.database_path = self.config.database.path.clone();
+                    self.load_database();
+                }
+                if self.config.api.keys != self.key_panel.path() {
+                    self.key_panel.open(&self.config.api.keys);
+                }
+            }
+            Err(e) => {
+                self.error_message = format!("Ошибка сохранения настроек: {}", e);
+                self.success_message.clear();
+            }
+        }
+    }
+    
     fn clear_database(&mut self) {
-        self.database_text.clear();
+        self.database_text.zeroize();
         self.users.clear();
-        let _ = fs::remove_file(DATABASE_FILE);
+        self.loaded_users.clear();
+        self.invalid_lines.clear();
+        self.issues.clear();
+        let _ = fs::remove_file(&self.database_path);
         self.success_message = "База данных очищена".to_string();
         self.error_message.clear();
     }
     
     fn parse_database(&mut self) {
-        self.users.clear();
-        
-        for line in self.database_text.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 5 {
-                self.users.push(User {
-                    name: parts[0].to_string(),
-                    login: parts[1].to_string(),
-                    bs2: parts[2].to_uppercase(),
-                    date: parts[3].to_string(),
-                    time: parts[4].to_string(),
-                });
-            }
-        }
+        let (users, invalid_lines) = parse_database(&self.database_text);
+        self.users = users;
+        self.invalid_lines = invalid_lines;
+        self.issues = validate_database(&self.database_text, Local::now().naive_local(), &self.config);
+    }
+    
+    fn sync_database_text(&mut self) {
+        self.database_text = format_database(&self.users, &self.invalid_lines);
+        self.issues = validate_database(&self.database_text, Local::now().naive_local(), &self.config);
     }
     
     fn authenticate(&mut self) {
         let start_time = Instant::now();
         self.error_message.clear();
         self.success_message.clear();
+        self.result_detail.clear();
         
         let login = self.login.trim().to_string();  // .to_string() клонирует
-        let password = self.password.trim().to_uppercase();
+        let password = self.password.trim().to_string();
         
         if login.is_empty() || password.is_empty() {
             self.show_result("Заполните логин и пароль!", ResultType::Error, start_time);
@@ -149,54 +296,80 @@ impl ServerApp {
             return;
         }
         
-        let user = match self.users.iter().find(|u| u.login == *login) {
-            Some(u) => u,
-            None => {
-                self.show_result("❌ Доступ запрещен: пользователь не найден", ResultType::Error, start_time);
-                return;
-            }
-        };
-        
-        let time_window: i64 = match self.time_window.parse() {
-            Ok(v) => v,
-            Err(_) => {
-                self.show_result("Неверное временное окно!", ResultType::Error, start_time);
-                return;
-            }
-        };
-        
-        let datetime_str = format!("{} {}", user.date, user.time);
-        let start_datetime = match parse_datetime(&datetime_str) {
-            Ok(dt) => dt,
+        // Та же проверка, что у демона и модуля PAM: база и состояние
+        // перечитываются и записываются под блокировкой файла
+        let now = Local::now().naive_local();
+        let outcome = match verifier::verify(&self.database_config(), self.challenge.take(), &login, &password, now) {
+            Ok(outcome) => outcome,
             Err(e) => {
-                self.show_result(&format!("Ошибка формата даты в БД: {}", e), ResultType::Error, start_time);
+                self.show_result(&format!("Ошибка проверки: {}", e), ResultType::Error, start_time);
                 return;
             }
         };
         
-        let current_time = Local::now().naive_local();
-        let mut authenticated = false;
-        
-        for offset in -time_window..=time_window {
-            let test_time = current_time + chrono::Duration::seconds(offset);
-            let time_diff = (test_time.and_utc().timestamp() - start_datetime.and_utc().timestamp()).max(0) as u64;
-            let time_block = format!("{:016X}", time_diff);
-            
-            if let Ok(expected_password) = des_encrypt(&time_block, &user.bs2) {
-                if expected_password == password {
-                    authenticated = true;
-                    break;
+        let reply = outcome.reply(self.config.denial);
+        match outcome {
+            Outcome::Granted { changed, .. } => {
+                // Счётчик, цепочка или коды восстановления в файле
+                // изменились: в таблицу переносятся только они, несохранённые
+                // правки остаются
+                if changed {
+                    match verifier::refresh_user(&self.database_config(), &login, &mut self.users, &mut self.loaded_users) {
+                        Ok(()) => self.sync_database_text(),
+                        Err(e) => self.error_message = format!("Запись {} не перечитана: {}", login, e),
+                    }
+                }
+                self.show_result(&reply, ResultType::Success, start_time);
+            }
+            Outcome::Denied(denial) => {
+                // Администратор видит причину, даже если ответ общий
+                if self.config.denial == DenialPolicy::Generic {
+                    self.result_detail = denial.reason();
                 }
+                self.show_result(&reply, ResultType::Error, start_time);
             }
         }
+    }
+    
+    fn issue_challenge(&mut self) {
+        self.error_message.clear();
+        self.success_message.clear();
+        
+        let login = self.login.trim();
+        let Some(user) = self.users.iter().find(|u| u.login == login) else {
+            self.error_message = "Пользователь не найден".to_string();
+            return;
+        };
         
-        if authenticated {
-            self.show_result("✅ Доступ разрешен", ResultType::Success, start_time);
-        } else {
-            self.show_result("❌ Доступ запрещен: неверный пароль", ResultType::Error, start_time);
+        match issue_challenge(user, &self.transaction) {
+            Ok(challenge) => self.challenge = Some(challenge),
+            Err(e) => self.error_message = e,
         }
     }
     
+    // Вход изменил счётчики в базе: она перечитывается
+    fn sign_in(&mut self, signed_in: (String, Role)) {
+        self.signed_in = Some(signed_in);
+        self.load_database();
+    }
+
+    fn sign_out(&mut self) {
+        if let Some((login, _)) = self.signed_in.take() {
+            audit::record(&login, "выход из интерфейса сервера");
+        }
+        self.reveal_secrets = false;
+        self.user_table = UserTable::default();
+        self.admin_panel = AdminPanel::default();
+        self.log_view = LogView::default();
+        self.login.clear();
+        self.password.clear();
+        self.error_message.clear();
+        self.success_message.clear();
+        self.result_message = "Ожидание аутентификации...".to_string();
+        self.result_detail.clear();
+        self.result_type = ResultType::None;
+    }
+
     fn show_result(&mut self, message: &str, result_type: ResultType, start_time: Instant) {
         self.result_message = message.to_string();
         self.result_type = result_type;
@@ -207,243 +380,416 @@ impl ServerApp {
 impl eframe::App for ServerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.add_space(10.0);
-            
-            ui.vertical_centered(|ui| {
-                ui.heading("🔒 Сервер аутентификации");
-            });
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add_space(10.0);
             
-            ui.add_space(10.0);
-            ui.separator();
-            ui.add_space(10.0);
+                ui.vertical_centered(|ui| {
+                    ui.heading("🔒 Сервер аутентификации");
+                });
             
-            // Инфо-блок
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(231, 243, 255))
-                .inner_margin(10.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    ui.label(egui::RichText::new("📋 Формат базы данных:").color(egui::Color32::from_rgb(8, 66, 152)));
-                    ui.label(egui::RichText::new("Фамилия_И.О. Логин БазовыйСекрет2 ДД.ММ.ГГГГ ЧЧ:ММ:СС").color(egui::Color32::from_rgb(8, 66, 152)));
-                    ui.label(egui::RichText::new("Пример: Іваненко_І.І. Johnny AAAAE2D76510BF24 06.05.2007 21:24:30").italics().color(egui::Color32::from_rgb(8, 66, 152)));
+                ui.add_space(10.0);
+                ui.separator();
+                ui.add_space(10.0);
+
+                if let Some(e) = &self.config_error {
+                    ui.label(egui::RichText::new(format!("Ошибка настроек: {}", e)).color(egui::Color32::from_rgb(114, 28, 36)));
+                    ui.label(format!("Исправьте {} и перезапустите сервер.", self.config_path));
+                    return;
+                }
+
+                // Без входа виден только экран входа
+                let Some((login, role)) = self.signed_in.clone() else {
+                    if let Some(signed_in) = self.login_screen.show(ui, &self.config) {
+                        self.sign_in(signed_in);
+                    }
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("👤 {} ({})", login, role.title()));
+                    if role.can_manage() {
+                        ui.checkbox(&mut self.reveal_secrets, "👁 Показывать BS2");
+                    }
+                    if ui.button("🚪 Выйти").clicked() {
+                        self.sign_out();
+                    }
                 });
+                // BS2 пользователей могут попасть в файл подкачки
+                if let Some(e) = secret::lock_error() {
+                    ui.label(egui::RichText::new(format!("⚠ {}", e)).small().color(egui::Color32::from_rgb(133, 100, 4)));
+                }
+                ui.add_space(10.0);
+
+                if role.can_manage() {
+                    // Инфо-блок
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(231, 243, 255))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new("📋 Формат базы данных:").color(egui::Color32::from_rgb(8, 66, 152)));
+                            ui.label(egui::RichText::new("Фамилия_И.О. Логин БазовыйСекрет2 ДД.ММ.ГГГГ ЧЧ:ММ:СС").color(egui::Color32::from_rgb(8, 66, 152)));
+                            ui.label(egui::RichText::new("Пример: Іваненко_І.І. Johnny AAAAE2D76510BF24 06.05.2007 21:24:30").italics().color(egui::Color32::from_rgb(8, 66, 152)));
+                        });
             
-            ui.add_space(10.0);
+                    ui.add_space(10.0);
             
-            // База данных
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(248, 249, 250))
-                .inner_margin(10.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    ui.label(egui::RichText::new("📊 База данных пользователей").strong());
-                    ui.add_space(5.0);
+                    // База данных
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(248, 249, 250))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new("📊 База данных пользователей").strong());
+                            ui.add_space(5.0);
                     
-                    ui.add(
-                        egui::TextEdit::multiline(&mut self.database_text)
-                            .desired_rows(8)
-                            .font(egui::TextStyle::Monospace)
-                            .hint_text("Лапин_Е.В. Johnny AE23e2d76510bf24 06.05.2007 21:24:30\nМатюшенко_Н.В. mtkolya ED7240deba345612 14.12.1985 18:00:00")
-                    );
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(&mut self.database_view, DatabaseView::Table, "📋 Таблица");
+                                ui.selectable_value(&mut self.database_view, DatabaseView::Text, "📝 Текст");
+                            });
+                            ui.add_space(5.0);
                     
-                    ui.add_space(5.0);
-                    ui.label(format!("Загружено пользователей: {}", self.users.len()));
-                    ui.add_space(5.0);
+                            match self.database_view {
+                                DatabaseView::Table => {
+                                    let groups: Vec<String> = self.config.groups.keys().cloned().collect();
+                                    if self.user_table.show(ui, &mut self.users, &groups, self.reveal_secrets) {
+                                        self.sync_database_text();
+                                    }
+                                }
+                                // В тексте базы BS2 не скрыть
+                                DatabaseView::Text if !self.reveal_secrets => {
+                                    ui.label("Текст базы содержит BS2: включите «👁 Показывать BS2».");
+                                }
+                                DatabaseView::Text => {
+                                    let response = ui.add(
+                                        egui::TextEdit::multiline(&mut *self.database_text)
+                                            .desired_rows(8)
+                                            .desired_width(f32::INFINITY)
+                                            .font(egui::TextStyle::Monospace)
+                                            .hint_text("Лапин_Е.В. Johnny AE23e2d76510bf24 06.05.2007 21:24:30\nМатюшенко_Н.В. mtkolya ED7240deba345612 14.12.1985 18:00:00")
+                                    );
+                                    if response.changed() {
+                                        self.parse_database();
+                                    }
+                                }
+                            }
                     
-                    ui.horizontal(|ui| {
-                        if ui.button("💾 Сохранить").clicked() {
-                            self.save_database();
-                        }
-                        if ui.button("📂 Загрузить").clicked() {
-                            self.load_database();
-                        }
-                        if ui.button("🗑️ Очистить").clicked() {
-                            self.clear_database();
-                        }
-                    });
-                });
+                            ui.add_space(5.0);
+                            ui.label(format!("Загружено пользователей: {}", self.users.len()));
+                    
+                            for issue in &self.issues {
+                                let (icon, color) = match issue.severity {
+                                    Severity::Error => ("❌", egui::Color32::from_rgb(114, 28, 36)),
+                                    Severity::Warning => ("⚠", egui::Color32::from_rgb(133, 100, 4)),
+                                };
+                                ui.label(egui::RichText::new(format!("{} {}", icon, issue)).color(color));
+                            }
+                            ui.add_space(5.0);
+                    
+                            ui.horizontal(|ui| {
+                                if ui.button("💾 Сохранить").clicked() {
+                                    self.save_database();
+                                }
+                                if ui.button("📂 Загрузить").clicked() {
+                                    self.load_database();
+                                }
+                                if ui.button("🗑️ Очистить").clicked() {
+                                    self.clear_database();
+                                }
+                            });
+                        });
             
-            ui.add_space(15.0);
+                    ui.add_space(15.0);
             
-            // Аутентификация
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(102, 126, 234))
-                .inner_margin(15.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+                }
+
+                if role.can_test() {
+                    // Аутентификация
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(102, 126, 234))
+                        .inner_margin(15.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
                     
-                    ui.label(egui::RichText::new("🔐 Аутентификация").strong().size(16.0));
-                    ui.add_space(10.0);
+                            ui.label(egui::RichText::new("🔐 Аутентификация").strong().size(16.0));
+                            ui.add_space(10.0);
                     
-                    ui.label("Введите логин:");
-                    ui.add(
-                        egui::TextEdit::singleline(&mut self.login)
-                            .hint_text("Johnny")
-                            .font(egui::TextStyle::Monospace)
-                    );
+                            ui.label("Введите логин:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.login)
+                                    .hint_text("Johnny")
+                                    .font(egui::TextStyle::Monospace)
+                            );
                     
-                    ui.add_space(5.0);
+                            ui.add_space(5.0);
+                        
+                            let selected_user = self.users.iter().find(|u| u.login == self.login.trim());
+                            let challenge_mode = selected_user.map(|u| u.mode).filter(|mode| mode.uses_challenge());
+                        
+                            if let Some(user) = selected_user.filter(|u| u.mode == AuthMode::HashChain) {
+                                let chain = &user.chain;
+                                ui.horizontal(|ui| {
+                                    ui.label("Запрос S/KEY:");
+                                    ui.label(egui::RichText::new(skey::challenge(chain.hash, chain.sequence, &chain.seed))
+                                        .font(egui::FontId::monospace(18.0))
+                                        .strong());
+                                });
+                                ui.add_space(5.0);
+                            }
+                        
+                            if let Some(mode) = challenge_mode {
+                                ui.label(format!("Данные транзакции ({}, необязательно):", mode.title()));
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.transaction)
+                                        .hint_text("Перевод 100 грн на счёт 26001234")
+                                );
+                                ui.add_space(5.0);
+                            
+                                ui.horizontal(|ui| {
+                                    if ui.button("🎲 Выдать запрос").clicked() {
+                                        self.issue_challenge();
+                                    }
+                                    if let Some(challenge) = self.challenge.as_ref().filter(|c| c.is_valid_for(self.login.trim())) {
+                                        ui.label(egui::RichText::new(&challenge.value).font(egui::FontId::monospace(18.0)).strong());
+                                    }
+                                });
+                                ui.add_space(5.0);
+                            }
                     
-                    ui.label("Введите пароль:");
-                    ui.add(
-                        egui::TextEdit::singleline(&mut self.password)
-                            .hint_text("0200000061290047")
-                            .font(egui::TextStyle::Monospace)
-                    );
+                            ui.label("Введите пароль:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.password)
+                                    .hint_text("0200000061290047")
+                                    .font(egui::TextStyle::Monospace)
+                            );
                     
-                    ui.add_space(10.0);
+                            ui.add_space(10.0);
                     
-                    if ui.add_sized([ui.available_width(), 40.0], 
-                        egui::Button::new(egui::RichText::new("🚀 Получить доступ").strong())
-                    ).clicked() {
-                        self.authenticate();
-                    }
-                });
+                            if ui.add_sized([ui.available_width(), 40.0], 
+                                egui::Button::new(egui::RichText::new("🚀 Получить доступ").strong())
+                            ).clicked() {
+                                self.authenticate();
+                            }
+                        });
             
-            ui.add_space(15.0);
+                    ui.add_space(15.0);
             
-            // Сообщения
-            if !self.error_message.is_empty() {
-                egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(248, 215, 218))
-                    .inner_margin(10.0)
-                    .rounding(5.0)
-                    .show(ui, |ui| {
-                        ui.label(egui::RichText::new(&self.error_message).color(egui::Color32::from_rgb(114, 28, 36)));
-                    });
-                ui.add_space(10.0);
-            }
+                }
+
+                // Сообщения
+                if !self.error_message.is_empty() {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(248, 215, 218))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(&self.error_message).color(egui::Color32::from_rgb(114, 28, 36)));
+                        });
+                    ui.add_space(10.0);
+                }
             
-            if !self.success_message.is_empty() {
-                egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(212, 237, 218))
-                    .inner_margin(10.0)
-                    .rounding(5.0)
-                    .show(ui, |ui| {
-                        ui.label(egui::RichText::new(&self.success_message).color(egui::Color32::from_rgb(21, 87, 36)));
-                    });
-                ui.add_space(10.0);
-            }
+                if !self.success_message.is_empty() {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(212, 237, 218))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(&self.success_message).color(egui::Color32::from_rgb(21, 87, 36)));
+                        });
+                    ui.add_space(10.0);
+                }
             
-            // Результат
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(248, 249, 250))
-                .inner_margin(15.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    let color = match self.result_type {
-                        ResultType::Success => egui::Color32::from_rgb(212, 237, 218),
-                        ResultType::Error => egui::Color32::from_rgb(248, 215, 218),
-                        ResultType::None => egui::Color32::from_rgb(233, 236, 239),
-                    };
-                    
-                    let text_color = match self.result_type {
-                        ResultType::Success => egui::Color32::from_rgb(21, 87, 36),
-                        ResultType::Error => egui::Color32::from_rgb(114, 28, 36),
-                        ResultType::None => egui::Color32::from_rgb(108, 117, 125),
-                    };
-                    
+                if role.can_test() {
+                    // Результат
                     egui::Frame::none()
-                        .fill(color)
+                        .fill(egui::Color32::from_rgb(248, 249, 250))
                         .inner_margin(15.0)
                         .rounding(5.0)
                         .show(ui, |ui| {
-                            ui.vertical_centered(|ui| {
-                                ui.label(egui::RichText::new(&self.result_message)
-                                    .color(text_color)
-                                    .strong()
-                                    .size(16.0));
-                            });
-                        });
+                            let color = match self.result_type {
+                                ResultType::Success => egui::Color32::from_rgb(212, 237, 218),
+                                ResultType::Error => egui::Color32::from_rgb(248, 215, 218),
+                                ResultType::None => egui::Color32::from_rgb(233, 236, 239),
+                            };
                     
-                    ui.add_space(10.0);
+                            let text_color = match self.result_type {
+                                ResultType::Success => egui::Color32::from_rgb(21, 87, 36),
+                                ResultType::Error => egui::Color32::from_rgb(114, 28, 36),
+                                ResultType::None => egui::Color32::from_rgb(108, 117, 125),
+                            };
+                    
+                            egui::Frame::none()
+                                .fill(color)
+                                .inner_margin(15.0)
+                                .rounding(5.0)
+                                .show(ui, |ui| {
+                                    ui.vertical_centered(|ui| {
+                                        ui.label(egui::RichText::new(&self.result_message)
+                                            .color(text_color)
+                                            .strong()
+                                            .size(16.0));
+                                        if !self.result_detail.is_empty() {
+                                            ui.label(egui::RichText::new(format!("Причина (видна только администратору): {}", self.result_detail))
+                                                .color(text_color)
+                                                .small());
+                                        }
+                                    });
+                                });
                     
-                    ui.horizontal(|ui| {
-                        ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
-                            ui.label(egui::RichText::new("Время ответа").color(egui::Color32::from_gray(100)));
-                            ui.label(egui::RichText::new(&self.response_time).strong().size(18.0));
+                            ui.add_space(10.0);
+                    
+                            ui.horizontal(|ui| {
+                                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                                    ui.label(egui::RichText::new("Время ответа").color(egui::Color32::from_gray(100)));
+                                    ui.label(egui::RichText::new(&self.response_time).strong().size(18.0));
+                                });
+                            });
                         });
-                    });
-                });
             
-            ui.add_space(15.0);
+                    ui.add_space(15.0);
             
-            // Настройки
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgb(248, 249, 250))
-                .inner_margin(10.0)
-                .rounding(5.0)
-                .show(ui, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("⚙️ Временное окно (секунды):");
-                        ui.add(
-                            egui::TextEdit::singleline(&mut self.time_window)
-                                .desired_width(80.0)
-                        );
-                    });
-                });
-        });
-    }
-}
+                }
 
-fn parse_datetime(s: &str) -> Result<NaiveDateTime, String> {
-    let parts: Vec<&str> = s.split_whitespace().collect();
-    if parts.len() != 2 {
-        return Err("Неверный формат".to_string());
-    }
-    
-    let date_parts: Vec<&str> = parts[0].split('.').collect();
-    let time_parts: Vec<&str> = parts[1].split(':').collect();
-    
-    if date_parts.len() != 3 || time_parts.len() != 3 {
-        return Err("Неверный формат".to_string());
-    }
-    
-    let day: u32 = date_parts[0].parse().map_err(|_| "Неверный день")?;
-    let month: u32 = date_parts[1].parse().map_err(|_| "Неверный месяц")?;
-    let year: i32 = date_parts[2].parse().map_err(|_| "Неверный год")?;
-    
-    let hour: u32 = time_parts[0].parse().map_err(|_| "Неверный час")?;
-    let minute: u32 = time_parts[1].parse().map_err(|_| "Неверная минута")?;
-    let second: u32 = time_parts[2].parse().map_err(|_| "Неверная секунда")?;
-    
-    NaiveDateTime::parse_from_str(
-        &format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second),
-        "%Y-%m-%d %H:%M:%S"
-    ).map_err(|e| format!("Ошибка: {}", e))
-}
+                if role.can_read_log() {
+                    // Журнал
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(248, 249, 250))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new("📜 Журнал событий").strong());
+                            ui.add_space(5.0);
+                            self.log_view.show(ui, &self.config);
+                        });
 
-fn des_encrypt(data_hex: &str, key_hex: &str) -> Result<String, String> {
-    let data_bytes = decode(data_hex).map_err(|e| format!("Данные: {}", e))?;
-    let key_bytes = decode(key_hex).map_err(|e| format!("Ключ: {}", e))?;
-    
-    if data_bytes.len() != 8 {
-        return Err(format!("Данные: 8 байт, получено {}", data_bytes.len()));
-    }
-    
-    if key_bytes.len() != 8 {
-        return Err(format!("Ключ: 8 байт, получено {}", key_bytes.len()));
-    }
-    
-    let cipher = Des::new_from_slice(&key_bytes)
-        .map_err(|e| format!("Cipher: {}", e))?;
-    
-    let mut block = [0u8; 8];
-    block.copy_from_slice(&data_bytes);
-    let mut block_array = block.into();
-    cipher.encrypt_block(&mut block_array);
-    
-    Ok(encode(block_array).to_uppercase())
-}
+                    ui.add_space(15.0);
+                }
 
-fn read_file_windows1251<P: AsRef<Path>>(path: P) -> Result<String, std::io::Error> {
-    let bytes = fs::read(path)?;
-    let (decoded, _, _) = WINDOWS_1251.decode(&bytes);
-    Ok(decoded.into_owned())
-}
+                if role.can_manage() {
+                    // Настройки
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(248, 249, 250))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(format!("⚙️ Настройки ({})", self.config_path)).strong());
+                            ui.add_space(5.0);
+                        
+                            ui.horizontal(|ui| {
+                                ui.label("База данных:");
+                                ui.add(egui::TextEdit::singleline(&mut self.config.database.path).desired_width(200.0));
+                                egui::ComboBox::from_id_source("database_backend")
+                                    .selected_text(self.config.database.backend.title())
+                                    .show_ui(ui, |ui| {
+                                        for backend in Backend::ALL {
+                                            ui.selectable_value(&mut self.config.database.backend, backend, backend.title());
+                                        }
+                                    });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Кодировка файлов:");
+                                egui::ComboBox::from_id_source("text_encoding")
+                                    .selected_text(self.config.encoding.title())
+                                    .show_ui(ui, |ui| {
+                                        for encoding in TextEncoding::ALL {
+                                            ui.selectable_value(&mut self.config.encoding, encoding, encoding.title());
+                                        }
+                                    });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Адреса службы gRPC (IP:порт через пробел):");
+                                ui.add(egui::TextEdit::singleline(&mut self.listen_text).hint_text("127.0.0.1:7070").desired_width(200.0));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Прокси LDAP:");
+                                ui.add(egui::TextEdit::singleline(&mut self.config.ldap.listen).hint_text("127.0.0.1:3389").desired_width(120.0));
+                                ui.label("сервер LDAP");
+                                ui.add(egui::TextEdit::singleline(&mut self.config.ldap.upstream).hint_text("ldap.example.com:389").desired_width(160.0));
+                                ui.label("логин из атрибута");
+                                ui.add(egui::TextEdit::singleline(&mut self.config.ldap.attribute).desired_width(60.0));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Файл ключей API:");
+                                ui.add(egui::TextEdit::singleline(&mut self.config.api.keys).desired_width(200.0));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Временное окно (секунды): назад");
+                                ui.add(egui::DragValue::new(&mut self.config.window.past).clamp_range(0..=MAX_WINDOW));
+                                ui.label("вперёд");
+                                ui.add(egui::DragValue::new(&mut self.config.window.future).clamp_range(0..=MAX_WINDOW));
+                            });
+                            let mut remove = None;
+                            for (index, (name, window)) in self.group_rows.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.label("    группа");
+                                    ui.add(egui::TextEdit::singleline(name).desired_width(100.0));
+                                    ui.label("назад");
+                                    ui.add(egui::DragValue::new(&mut window.past).clamp_range(0..=MAX_WINDOW));
+                                    ui.label("вперёд");
+                                    ui.add(egui::DragValue::new(&mut window.future).clamp_range(0..=MAX_WINDOW));
+                                    if ui.small_button("🗑").on_hover_text("Удалить группу").clicked() {
+                                        remove = Some(index);
+                                    }
+                                });
+                            }
+                            if let Some(index) = remove {
+                                self.group_rows.remove(index);
+                            }
+                            if ui.small_button("➕ Группа").on_hover_text("Окно для пользователей с атрибутом group=").clicked() {
+                                self.group_rows.push((String::new(), self.config.window));
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label("Окно счётчика и S/KEY (паролей вперёд):");
+                                ui.add(egui::DragValue::new(&mut self.config.counter_window).clamp_range(0..=COUNTER_RESYNC_WINDOW));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Блокировка после неудачных попыток (0 - нет):");
+                                ui.add(egui::DragValue::new(&mut self.config.lockout.attempts));
+                                ui.label("на");
+                                ui.add(egui::DragValue::new(&mut self.config.lockout.duration).clamp_range(1..=MAX_LOCKOUT).suffix(" с"));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Журнал (пусто - не писать):");
+                                ui.add(egui::TextEdit::singleline(&mut self.config.log.audit).desired_width(200.0));
+                                ui.checkbox(&mut self.config.log.stderr, "и в stderr");
+                            });
+                            let mut generic = self.config.denial == DenialPolicy::Generic;
+                            if ui.checkbox(&mut generic, "Не сообщать причину отказа (общий ответ для внешних клиентов)").changed() {
+                                self.config.denial = if generic { DenialPolicy::Generic } else { DenialPolicy::Detailed };
+                            }
+                            if ui.button("💾 Сохранить настройки").clicked() {
+                                self.save_config();
+                            }
+                        });
+
+                    ui.add_space(15.0);
+
+                    // Ключи API
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(248, 249, 250))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(format!("🔑 Ключи API службы gRPC ({})", self.key_panel.path())).strong());
+                            ui.add_space(5.0);
+                            self.key_panel.show(ui);
+                        });
 
-fn write_file_windows1251<P: AsRef<Path>>(path: P, content: &str) -> Result<(), std::io::Error> {
-    let (encoded, _, _) = WINDOWS_1251.encode(content);
-    fs::write(path, encoded.as_ref())
+                    ui.add_space(15.0);
+
+                    // Администраторы
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(248, 249, 250))
+                        .inner_margin(10.0)
+                        .rounding(5.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(format!("👤 Администраторы интерфейса ({})", self.config.gui.admins)).strong());
+                            ui.add_space(5.0);
+                            self.admin_panel.show(ui, &self.config.gui.admins, &login);
+                        });
+                }
+            });
+        });
+    }
 }
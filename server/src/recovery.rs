@@ -0,0 +1,99 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use otp_core::format::{normalize_password, OutputFormat, CROCKFORD};
+use otp_core::sheet::Sheet;
+use rand::Rng;
+
+use crate::database::User;
+
+// Одноразовые коды восстановления на случай потери bs1.txt: 12 символов
+// base32 Crockford (60 бит). В базе хранятся только хеши Argon2.
+pub const CODE_COUNT: usize = 10;
+const CODE_LEN: usize = 12;
+const GROUP_LEN: usize = 4;
+const DECOY_SALT: &str = "AAAAAAAAAAAAAAAAAAAAAA";
+
+pub fn generate_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..CODE_COUNT)
+        .map(|_| {
+            let chars: Vec<char> = (0..CODE_LEN).map(|_| CROCKFORD[rng.gen_range(0..32)] as char).collect();
+            let groups: Vec<String> = chars.chunks(GROUP_LEN).map(|g| g.iter().collect()).collect();
+            groups.join("-")
+        })
+        .collect()
+}
+
+pub fn hash_code(code: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(normalize_code(code).as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Argon2: {}", e))
+}
+
+pub fn validate_hash(hash: &str) -> Result<(), String> {
+    PasswordHash::new(hash)
+        .map(|_| ())
+        .map_err(|e| format!("неверный хеш кода восстановления: {}", e))
+}
+
+// Введённый пароль похож на код восстановления: 12 символов одной группой
+// или тремя по 4 (XXXX-XXXX-XXXX). Ни один формат пароля не даёт 12
+// символов; ответ S/KEY из шести коротких слов может дать 12 букв, но в нём
+// шесть групп.
+pub fn looks_like_code(input: &str) -> bool {
+    let groups: Vec<usize> = input
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter(|group| !group.is_empty())
+        .map(|group| group.chars().count())
+        .collect();
+    let shaped = groups == [CODE_LEN] || groups == [GROUP_LEN; CODE_LEN / GROUP_LEN];
+    let code = normalize_code(input);
+    shaped && code.len() == CODE_LEN && code.bytes().all(|b| CROCKFORD.contains(&b))
+}
+
+// Индекс совпавшего хеша. Всегда выполняется CODE_COUNT вычислений Argon2:
+// недостающие коды заменяются холостым хешированием, чтобы время ответа не
+// выдавало ни число оставшихся кодов, ни наличие пользователя
+pub fn verify_code(hashes: &[String], input: &str) -> Option<usize> {
+    let code = normalize_code(input);
+    let mut matched = None;
+
+    for index in 0..CODE_COUNT.max(hashes.len()) {
+        let valid = match hashes.get(index).map(|hash| PasswordHash::new(hash)) {
+            Some(Ok(hash)) => Argon2::default().verify_password(code.as_bytes(), &hash).is_ok(),
+            _ => {
+                let _ = Argon2::default().hash_password(code.as_bytes(), &SaltString::from_b64(DECOY_SALT).ok()?);
+                false
+            }
+        };
+        if valid && matched.is_none() {
+            matched = Some(index);
+        }
+    }
+
+    matched
+}
+
+pub fn sheet(user: &User, codes: &[String]) -> Sheet {
+    let mut lines = vec![
+        format!("Пользователь: {} ({})", user.name, user.login),
+        format!("Выданы: {}", chrono::Local::now().format("%d.%m.%Y %H:%M:%S")),
+        String::new(),
+    ];
+    lines.extend(codes.iter().enumerate().map(|(i, code)| format!("{:2}. {}", i + 1, code)));
+    lines.push(String::new());
+    lines.push("Каждый код действует один раз вместо одноразового пароля.".to_string());
+    lines.push("Храните лист отдельно от компьютера с bs1.txt.".to_string());
+
+    Sheet {
+        title: "Коды восстановления".to_string(),
+        lines,
+    }
+}
+
+fn normalize_code(input: &str) -> String {
+    normalize_password(OutputFormat::Base32, input)
+}
@@ -0,0 +1,188 @@
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig, ServerConnection};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::config::TlsConfig;
+
+// TLS для сетевых интерфейсов. Сертификат, ключ и центр клиентов
+// перечитываются, когда меняется время изменения или размер одного из
+// файлов: новые соединения получают новый сертификат без перезапуска, уже
+// открытые остаются на старом.
+
+// Клиент, который не закончил рукопожатие за это время, отключается
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Stamps = Vec<Option<(SystemTime, u64)>>;
+
+pub struct Certificates {
+    config: TlsConfig,
+    // Протоколы ALPN (h2 для gRPC)
+    alpn: Vec<Vec<u8>>,
+    current: Mutex<(Arc<ServerConfig>, Stamps)>,
+}
+
+impl Certificates {
+    // None - TLS в настройках не включён
+    pub fn load(config: &TlsConfig, alpn: &[&[u8]]) -> Result<Option<Arc<Self>>, String> {
+        if !config.enabled() {
+            return Ok(None);
+        }
+        let alpn: Vec<Vec<u8>> = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+        let server = build(config, &alpn)?;
+        Ok(Some(Arc::new(Self {
+            config: config.clone(),
+            alpn,
+            current: Mutex::new((Arc::new(server), stamps(config))),
+        })))
+    }
+
+    // Настройки для очередного соединения. Если файлы изменились, но
+    // прочитать их не удалось (например, записан только сертификат), до
+    // следующего изменения действуют прежние.
+    pub fn server_config(&self) -> Arc<ServerConfig> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let stamps = stamps(&self.config);
+        if stamps != current.1 {
+            match build(&self.config, &self.alpn) {
+                Ok(server) => current.0 = Arc::new(server),
+                Err(e) => eprintln!("tls: {}", e),
+            }
+            current.1 = stamps;
+        }
+        Arc::clone(&current.0)
+    }
+}
+
+fn stamps(config: &TlsConfig) -> Stamps {
+    [&config.certificate, &config.key, &config.client_ca]
+        .iter()
+        .map(|path| fs::metadata(path).ok().map(|m| (m.modified().unwrap_or(SystemTime::UNIX_EPOCH), m.len())))
+        .collect()
+}
+
+fn build(config: &TlsConfig, alpn: &[Vec<u8>]) -> Result<ServerConfig, String> {
+    let certificates = read_certificates(&config.certificate)?;
+    let key = PrivateKeyDer::from_pem_file(&config.key).map_err(|e| format!("{}: {}", config.key, e))?;
+
+    let provider = Arc::new(default_provider());
+    let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?;
+
+    let builder = if config.client_ca.is_empty() {
+        builder.with_no_client_auth()
+    } else {
+        let mut roots = RootCertStore::empty();
+        for certificate in read_certificates(&config.client_ca)? {
+            roots.add(certificate).map_err(|e| format!("{}: {}", config.client_ca, e))?;
+        }
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+            .build()
+            .map_err(|e| format!("{}: {}", config.client_ca, e))?;
+        builder.with_client_cert_verifier(verifier)
+    };
+
+    let mut server = builder
+        .with_single_cert(certificates, key)
+        .map_err(|e| format!("{}: {}", config.certificate, e))?;
+    server.alpn_protocols = alpn.to_vec();
+    Ok(server)
+}
+
+fn read_certificates(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certificates = CertificateDer::pem_file_iter(path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{}: {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("{}: нет сертификатов", path));
+    }
+    Ok(certificates)
+}
+
+// Блокирующее соединение клиента (прокси LDAP), разделённое на чтение и
+// запись для двух потоков. Без TLS - две копии сокета.
+pub fn split(stream: TcpStream, tls: Option<&Certificates>) -> io::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+    let Some(certificates) = tls else {
+        return Ok((Box::new(stream.try_clone()?), Box::new(stream)));
+    };
+
+    let mut connection = ServerConnection::new(certificates.server_config()).map_err(io::Error::other)?;
+    let mut stream = stream;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    while connection.is_handshaking() {
+        connection.complete_io(&mut stream)?;
+    }
+    stream.set_read_timeout(None)?;
+
+    let connection = Arc::new(Mutex::new(connection));
+    let reader = TlsReader {
+        connection: Arc::clone(&connection),
+        stream: stream.try_clone()?,
+    };
+    Ok((Box::new(reader), Box::new(TlsWriter { connection, stream })))
+}
+
+// Сокет читается без блокировки соединения TLS, чтобы запись из другого
+// потока не ждала данных от клиента
+struct TlsReader {
+    connection: Arc<Mutex<ServerConnection>>,
+    stream: TcpStream,
+}
+
+impl Read for TlsReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut raw = [0u8; 16 * 1024];
+        loop {
+            match lock(&self.connection).reader().read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+
+            let count = self.stream.read(&mut raw)?;
+            if count == 0 {
+                return Ok(0);
+            }
+            let mut connection = lock(&self.connection);
+            let mut data = &raw[..count];
+            while !data.is_empty() {
+                connection.read_tls(&mut data)?;
+                connection.process_new_packets().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+            // Служебные записи TLS (обновление ключей, оповещения)
+            while connection.wants_write() {
+                connection.write_tls(&mut self.stream)?;
+            }
+        }
+    }
+}
+
+struct TlsWriter {
+    connection: Arc<Mutex<ServerConnection>>,
+    stream: TcpStream,
+}
+
+impl Write for TlsWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut connection = lock(&self.connection);
+        let count = connection.writer().write(buf)?;
+        while connection.wants_write() {
+            connection.write_tls(&mut self.stream)?;
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn lock(connection: &Mutex<ServerConnection>) -> std::sync::MutexGuard<'_, ServerConnection> {
+    connection.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,734 @@
+use eframe::egui;
+
+use otp_core::cipher::{Algorithm, MAX_SLOT, MAX_TIME_STEP};
+use otp_core::format::{OutputFormat, MAX_DIGITS, MIN_DIGITS};
+use otp_core::mode::AuthMode;
+use otp_core::ocra::{OcraSuite, DEFAULT_SUITE};
+use otp_core::secret::{self, SecretString};
+use otp_core::sheet::Sheet;
+use otp_core::skey::{self, ChainHash};
+use otp_core::text::TextEncoding;
+use std::fs;
+use otp_core::weak_keys::{effective_key, key_weakness, KeyWeakness};
+
+use otp_server::audit;
+use otp_server::config::MAX_WINDOW;
+use otp_server::database::{parse_datetime, validate_bs2, validate_login, validate_name, validate_suite, HashChain, User};
+use otp_server::recovery;
+
+// Запас буфера BS2 в редакторе: ввод до этой длины не перевыделяет буфер
+const BS2_CAPACITY: usize = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortColumn {
+    Line,
+    Name,
+    Login,
+    Date,
+}
+
+pub struct UserTable {
+    filter: String,
+    sort: SortColumn,
+    ascending: bool,
+    editor: Option<UserEditor>,
+    pending_delete: Option<usize>,
+    pending_recovery: Option<usize>,
+    recovery_sheet: Option<RecoverySheet>,
+}
+
+// Открытые коды показываются один раз, сразу после выдачи
+struct RecoverySheet {
+    login: String,
+    sheet: Sheet,
+    message: String,
+}
+
+struct UserEditor {
+    index: Option<usize>,
+    name: String,
+    login: String,
+    bs2: SecretString,
+    datetime: String,
+    disabled: bool,
+    algorithm: Algorithm,
+    format: OutputFormat,
+    mode: AuthMode,
+    suite: String,
+    counter: u64,
+    step: u64,
+    slot: u64,
+    group: Option<String>,
+    past: Option<u64>,
+    future: Option<u64>,
+    chain_hash: ChainHash,
+    seed: String,
+    sequence: u64,
+    // Исходная цепочка и BS2: последний принятый пароль сохраняется, только
+    // если цепочку не переинициализировали
+    original_chain: Option<(HashChain, SecretString)>,
+    recovery: Vec<String>,
+    extra: Vec<String>,
+    name_error: Option<String>,
+    login_error: Option<String>,
+    bs2_error: Option<String>,
+    datetime_error: Option<String>,
+    suite_error: Option<String>,
+    seed_error: Option<String>,
+}
+
+impl Default for UserTable {
+    fn default() -> Self {
+        Self {
+            filter: String::new(),
+            sort: SortColumn::Line,
+            ascending: true,
+            editor: None,
+            pending_delete: None,
+            pending_recovery: None,
+            recovery_sheet: None,
+        }
+    }
+}
+
+impl UserEditor {
+    fn new() -> Self {
+        Self {
+            index: None,
+            name: String::new(),
+            login: String::new(),
+            bs2: secret::buffer(BS2_CAPACITY),
+            datetime: chrono::Local::now().format("%d.%m.%Y %H:%M:%S").to_string(),
+            disabled: false,
+            algorithm: Algorithm::Des,
+            format: OutputFormat::Hex,
+            mode: AuthMode::Time,
+            suite: DEFAULT_SUITE.to_string(),
+            counter: 0,
+            step: 1,
+            slot: 0,
+            group: None,
+            past: None,
+            future: None,
+            chain_hash: ChainHash::Md5,
+            seed: String::new(),
+            sequence: skey::DEFAULT_SEQUENCE,
+            original_chain: None,
+            recovery: Vec::new(),
+            extra: Vec::new(),
+            name_error: None,
+            login_error: None,
+            bs2_error: None,
+            datetime_error: None,
+            suite_error: None,
+            seed_error: None,
+        }
+    }
+
+    fn edit(index: usize, user: &User) -> Self {
+        let mut bs2 = secret::buffer(BS2_CAPACITY);
+        bs2.push_str(&user.bs2);
+        Self {
+            index: Some(index),
+            name: user.name.clone(),
+            login: user.login.clone(),
+            bs2,
+            datetime: format!("{} {}", user.date, user.time),
+            disabled: user.disabled,
+            algorithm: user.algorithm,
+            format: user.format,
+            mode: user.mode,
+            suite: user.suite.as_str().to_string(),
+            counter: user.counter,
+            step: user.step,
+            slot: user.slot,
+            group: user.group.clone(),
+            past: user.past,
+            future: user.future,
+            chain_hash: user.chain.hash,
+            seed: user.chain.seed.clone(),
+            sequence: user.chain.sequence,
+            original_chain: Some((user.chain.clone(), user.bs2.clone())),
+            recovery: user.recovery.clone(),
+            extra: user.extra.clone(),
+            ..Self::new()
+        }
+    }
+
+    fn validate(&mut self, users: &[User]) -> Option<User> {
+        let name = self.name.trim().to_string();
+        let login = self.login.trim().to_string();
+        let bs2 = secret::uppercase(self.bs2.trim());
+        let datetime = self.datetime.trim().to_string();
+
+        self.name_error = validate_name(&name).err();
+        self.login_error = validate_login(&login).err();
+        self.bs2_error = validate_bs2(&bs2, self.algorithm).err();
+        self.datetime_error = parse_datetime(&datetime)
+            .err()
+            .map(|e| format!("{}. Формат: ДД.ММ.ГГГГ ЧЧ:ММ:СС", e));
+
+        if self.login_error.is_none() {
+            let duplicate = users
+                .iter()
+                .enumerate()
+                .any(|(i, u)| Some(i) != self.index && u.login == login);
+            if duplicate {
+                self.login_error = Some("Логин уже существует".to_string());
+            }
+        }
+
+        let suite = OcraSuite::parse(self.suite.trim());
+        self.suite_error = match &suite {
+            Ok(suite) if self.mode == AuthMode::Ocra => validate_suite(suite).err(),
+            Ok(_) => None,
+            Err(e) => Some(e.clone()),
+        };
+
+        let seed = self.seed.trim().to_string();
+        self.seed_error = match self.mode {
+            AuthMode::HashChain => skey::validate_seed(&seed).err(),
+            _ => None,
+        };
+
+        if self.bs2_error.is_none() {
+            self.bs2_error = check_key_strength(&bs2, self.algorithm, self.index, users);
+        }
+
+        if self.name_error.is_some()
+            || self.login_error.is_some()
+            || self.bs2_error.is_some()
+            || self.datetime_error.is_some()
+            || self.suite_error.is_some()
+            || self.seed_error.is_some()
+        {
+            return None;
+        }
+
+        let (date, time) = datetime.split_once(char::is_whitespace).unwrap_or_default();
+
+        let last = self
+            .original_chain
+            .as_ref()
+            .filter(|(chain, original_bs2)| {
+                chain.hash == self.chain_hash && chain.seed == seed && chain.sequence == self.sequence && *original_bs2 == bs2
+            })
+            .and_then(|(chain, _)| chain.last);
+
+        Some(User {
+            name,
+            login,
+            bs2,
+            date: date.to_string(),
+            time: time.trim().to_string(),
+            disabled: self.disabled,
+            algorithm: self.algorithm,
+            format: self.format,
+            mode: self.mode,
+            suite: suite.ok()?,
+            counter: self.counter,
+            step: self.step,
+            slot: self.slot,
+            group: self.group.clone(),
+            past: self.past,
+            future: self.future,
+            chain: HashChain {
+                hash: self.chain_hash,
+                seed,
+                sequence: self.sequence,
+                last,
+            },
+            recovery: self.recovery.clone(),
+            extra: self.extra.clone(),
+        })
+    }
+}
+
+impl UserTable {
+    // Возвращает true, если список пользователей изменился
+    // groups - имена групп окна из server.toml для выбора в редакторе
+    // reveal - BS2 показываются открыто, иначе маской
+    pub fn show(&mut self, ui: &mut egui::Ui, users: &mut Vec<User>, groups: &[String], reveal: bool) -> bool {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label("🔍 Фильтр:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("ФИО или логин")
+                    .desired_width(200.0)
+            );
+            if ui.button("➕ Добавить").clicked() {
+                self.editor = Some(UserEditor::new());
+            }
+        });
+
+        ui.add_space(5.0);
+
+        let rows = self.visible_rows(users);
+
+        egui::ScrollArea::vertical()
+            .max_height(220.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                egui::Grid::new("users_grid")
+                    .striped(true)
+                    .num_columns(7)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        self.header(ui, "#", SortColumn::Line);
+                        self.header(ui, "ФИО", SortColumn::Name);
+                        self.header(ui, "Логин", SortColumn::Login);
+                        ui.strong("Схема");
+                        ui.strong("BS2");
+                        self.header(ui, "Начальная настройка", SortColumn::Date);
+                        ui.strong("Активен");
+                        ui.end_row();
+
+                        for index in rows {
+                            let user = &mut users[index];
+                            ui.label(format!("{}", index + 1));
+                            ui.label(&user.name);
+                            ui.label(egui::RichText::new(&user.login).monospace());
+                            ui.label(match user.mode {
+                                AuthMode::Time if user.step > 1 => {
+                                    format!("{}, {}, шаг {} с", user.algorithm.title(), user.format.name(), user.step)
+                                }
+                                AuthMode::Time => format!("{}, {}", user.algorithm.title(), user.format.name()),
+                                AuthMode::Challenge => format!("{}, {}, запрос", user.algorithm.title(), user.format.name()),
+                                AuthMode::Ocra => user.suite.as_str().to_string(),
+                                AuthMode::Counter => format!("{}, {}, счётчик {}", user.algorithm.title(), user.format.name(), user.counter),
+                                AuthMode::HashChain => skey::challenge(user.chain.hash, user.chain.sequence, &user.chain.seed),
+                            });
+                            let bs2 = if reveal { user.bs2.to_string() } else { mask(&user.bs2) };
+                            ui.label(egui::RichText::new(bs2).monospace());
+                            ui.label(format!("{} {}", user.date, user.time));
+
+                            ui.horizontal(|ui| {
+                                let mut enabled = !user.disabled;
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    user.disabled = !enabled;
+                                    changed = true;
+                                }
+                                if ui.small_button("✏").on_hover_text("Изменить").clicked() {
+                                    self.editor = Some(UserEditor::edit(index, user));
+                                }
+                                let recovery_hint = format!("Коды восстановления (осталось {})", user.recovery.len());
+                                if ui.small_button("🔑").on_hover_text(recovery_hint).clicked() {
+                                    self.pending_recovery = Some(index);
+                                }
+                                if ui.small_button("🗑").on_hover_text("Удалить").clicked() {
+                                    self.pending_delete = Some(index);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        changed |= self.editor_window(ui.ctx(), users, groups, reveal);
+        changed |= self.delete_window(ui.ctx(), users);
+        changed |= self.recovery_window(ui.ctx(), users);
+        self.recovery_sheet_window(ui.ctx());
+
+        changed
+    }
+
+    fn header(&mut self, ui: &mut egui::Ui, title: &str, column: SortColumn) {
+        let arrow = match (self.sort == column, self.ascending) {
+            (true, true) => " ⏶",
+            (true, false) => " ⏷",
+            (false, _) => "",
+        };
+
+        let label = egui::RichText::new(format!("{}{}", title, arrow)).strong();
+        if ui.add(egui::Label::new(label).sense(egui::Sense::click())).clicked() {
+            if self.sort == column {
+                self.ascending = !self.ascending;
+            } else {
+                self.sort = column;
+                self.ascending = true;
+            }
+        }
+    }
+
+    fn visible_rows(&self, users: &[User]) -> Vec<usize> {
+        let filter = self.filter.trim().to_lowercase();
+
+        let mut rows: Vec<usize> = (0..users.len())
+            .filter(|&i| {
+                filter.is_empty()
+                    || users[i].name.to_lowercase().contains(&filter)
+                    || users[i].login.to_lowercase().contains(&filter)
+            })
+            .collect();
+
+        match self.sort {
+            SortColumn::Line => {}
+            SortColumn::Name => rows.sort_by_key(|&i| users[i].name.to_lowercase()),
+            SortColumn::Login => rows.sort_by_key(|&i| users[i].login.to_lowercase()),
+            SortColumn::Date => rows.sort_by_key(|&i| users[i].datetime().ok()),
+        }
+
+        if !self.ascending {
+            rows.reverse();
+        }
+
+        rows
+    }
+
+    fn editor_window(&mut self, ctx: &egui::Context, users: &mut Vec<User>, groups: &[String], reveal: bool) -> bool {
+        let Some(editor) = self.editor.as_mut() else {
+            return false;
+        };
+
+        let title = if editor.index.is_some() { "Изменить пользователя" } else { "Новый пользователь" };
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                field(ui, "ФИО (Фамилия_И.О.):", &mut editor.name, "Лапин_Е.В.", &editor.name_error);
+                field(ui, "Логин:", &mut editor.login, "Johnny", &editor.login_error);
+                ui.horizontal(|ui| {
+                    ui.label("Алгоритм:");
+                    egui::ComboBox::from_id_source("user_algorithm")
+                        .selected_text(editor.algorithm.title())
+                        .show_ui(ui, |ui| {
+                            for algorithm in Algorithm::ALL {
+                                ui.selectable_value(&mut editor.algorithm, algorithm, algorithm.title());
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Формат пароля:");
+                    format_selector(ui, &mut editor.format);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Режим:");
+                    egui::ComboBox::from_id_source("user_mode")
+                        .selected_text(editor.mode.title())
+                        .show_ui(ui, |ui| {
+                            for mode in AuthMode::ALL {
+                                ui.selectable_value(&mut editor.mode, mode, mode.title());
+                            }
+                        });
+                });
+                if editor.mode == AuthMode::Ocra {
+                    ui.add_space(5.0);
+                    field(ui, "OCRASuite:", &mut editor.suite, DEFAULT_SUITE, &editor.suite_error);
+                }
+                if editor.mode == AuthMode::Counter {
+                    ui.horizontal(|ui| {
+                        ui.label("Следующее значение счётчика:");
+                        ui.add(egui::DragValue::new(&mut editor.counter));
+                    });
+                }
+                if editor.mode == AuthMode::Time {
+                    ui.horizontal(|ui| {
+                        ui.label("Шаг времени:");
+                        ui.add(egui::DragValue::new(&mut editor.step).clamp_range(1..=MAX_TIME_STEP).suffix(" с"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Интервал печатного списка (0 - нет):");
+                        ui.add(egui::DragValue::new(&mut editor.slot).clamp_range(0..=MAX_SLOT).suffix(" с"));
+                    });
+                    // Группа из записи, которой нет в server.toml, остаётся в списке
+                    let mut choices = groups.to_vec();
+                    if let Some(group) = editor.group.as_ref().filter(|g| !groups.contains(g)) {
+                        choices.push(group.clone());
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Группа окна:");
+                        egui::ComboBox::from_id_source("user_group")
+                            .selected_text(editor.group.as_deref().unwrap_or("— общее окно —"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut editor.group, None, "— общее окно —");
+                                for group in choices {
+                                    let label = group.clone();
+                                    ui.selectable_value(&mut editor.group, Some(group), label);
+                                }
+                            });
+                    });
+                    window_override(ui, "Своё окно назад:", &mut editor.past);
+                    window_override(ui, "Своё окно вперёд:", &mut editor.future);
+                }
+                if editor.mode == AuthMode::HashChain {
+                    ui.horizontal(|ui| {
+                        ui.label("Хеш цепочки:");
+                        egui::ComboBox::from_id_source("user_chain_hash")
+                            .selected_text(editor.chain_hash.title())
+                            .show_ui(ui, |ui| {
+                                for hash in ChainHash::ALL {
+                                    ui.selectable_value(&mut editor.chain_hash, hash, hash.title());
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Номер следующего пароля:");
+                        ui.add(egui::DragValue::new(&mut editor.sequence));
+                    });
+                    ui.add_space(5.0);
+                    field(ui, "Seed (до 16 букв и цифр):", &mut editor.seed, "ke1234", &editor.seed_error);
+                }
+                ui.add_space(5.0);
+
+                let bs2_label = format!("Базовый секрет 2 (PIN + BS1, {} HEX):", editor.algorithm.key_hex_len());
+                secret_field(ui, &bs2_label, &mut editor.bs2, "AAAAE2D76510BF24", &editor.bs2_error, !reveal);
+                field(ui, "Начальная настройка (ДД.ММ.ГГГГ ЧЧ:ММ:СС):", &mut editor.datetime, "06.05.2007 21:24:30", &editor.datetime_error);
+
+                ui.checkbox(&mut editor.disabled, "Учётная запись отключена");
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    save = ui.button("💾 Сохранить").clicked();
+                    cancel = ui.button("Отмена").clicked();
+                });
+            });
+
+        if save {
+            if let Some(user) = editor.validate(users) {
+                match editor.index {
+                    Some(index) => users[index] = user,
+                    None => users.push(user),
+                }
+                self.editor = None;
+                return true;
+            }
+        }
+
+        if !open || cancel {
+            self.editor = None;
+        }
+
+        false
+    }
+
+    fn delete_window(&mut self, ctx: &egui::Context, users: &mut Vec<User>) -> bool {
+        let Some(index) = self.pending_delete else {
+            return false;
+        };
+
+        let mut confirm = false;
+        let mut cancel = false;
+
+        egui::Window::new("Удаление пользователя")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Удалить {} ({})?", users[index].name, users[index].login));
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    confirm = ui.button("🗑️ Удалить").clicked();
+                    cancel = ui.button("Отмена").clicked();
+                });
+            });
+
+        if confirm {
+            users.remove(index);
+            self.pending_delete = None;
+            return true;
+        }
+
+        if cancel {
+            self.pending_delete = None;
+        }
+
+        false
+    }
+}
+
+impl UserTable {
+    fn recovery_window(&mut self, ctx: &egui::Context, users: &mut [User]) -> bool {
+        let Some(index) = self.pending_recovery else {
+            return false;
+        };
+
+        let mut confirm = false;
+        let mut cancel = false;
+
+        egui::Window::new("Коды восстановления")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let user = &users[index];
+                ui.label(format!("Выдать {} новых кодов для {} ({})?", recovery::CODE_COUNT, user.name, user.login));
+                if !user.recovery.is_empty() {
+                    ui.label(format!("Неиспользованные коды ({}) перестанут действовать.", user.recovery.len()));
+                }
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    confirm = ui.button("🔑 Выдать").clicked();
+                    cancel = ui.button("Отмена").clicked();
+                });
+            });
+
+        if cancel {
+            self.pending_recovery = None;
+        }
+
+        if !confirm {
+            return false;
+        }
+
+        self.pending_recovery = None;
+        let codes = recovery::generate_codes();
+        let hashes: Result<Vec<String>, String> = codes.iter().map(|code| recovery::hash_code(code)).collect();
+        let user = &mut users[index];
+
+        let (codes, message) = match hashes {
+            Ok(hashes) => {
+                user.recovery = hashes;
+                audit::record(&user.login, &format!("выданы коды восстановления: {}", codes.len()));
+                (codes, "Коды начнут действовать после сохранения базы".to_string())
+            }
+            // Коды без сохранённых хешей недействительны и не показываются
+            Err(e) => (Vec::new(), e),
+        };
+
+        self.recovery_sheet = Some(RecoverySheet {
+            login: user.login.clone(),
+            sheet: recovery::sheet(user, &codes),
+            message,
+        });
+
+        !codes.is_empty()
+    }
+
+    fn recovery_sheet_window(&mut self, ctx: &egui::Context) {
+        let Some(recovery) = self.recovery_sheet.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut close = false;
+
+        egui::Window::new(format!("Коды восстановления: {}", recovery.login))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                for line in &recovery.sheet.lines {
+                    ui.label(egui::RichText::new(line).monospace());
+                }
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("💾 TXT").clicked() {
+                        let path = format!("recovery_{}.txt", recovery.login);
+                        recovery.message = match TextEncoding::Auto.write(&path, &recovery.sheet.to_text()) {
+                            Ok(()) => format!("Сохранено: {}", path),
+                            Err(e) => format!("Ошибка сохранения: {}", e),
+                        };
+                    }
+                    if ui.button("💾 PDF").clicked() {
+                        let path = format!("recovery_{}.pdf", recovery.login);
+                        recovery.message = match recovery.sheet.to_pdf(&sheet_font()) {
+                            Ok(pdf) => match fs::write(&path, pdf) {
+                                Ok(()) => format!("Сохранено: {}", path),
+                                Err(e) => format!("Ошибка сохранения: {}", e),
+                            },
+                            Err(e) => e,
+                        };
+                    }
+                    close = ui.button("Закрыть").clicked();
+                });
+
+                if !recovery.message.is_empty() {
+                    ui.label(egui::RichText::new(&recovery.message).small());
+                }
+            });
+
+        // Закрытое окно стирает открытые коды из памяти программы
+        if !open || close {
+            self.recovery_sheet = None;
+        }
+    }
+}
+
+// Моноширинный шрифт egui содержит кириллицу и встраивается в PDF
+fn sheet_font() -> Vec<u8> {
+    egui::FontDefinitions::default()
+        .font_data
+        .get("Hack")
+        .map(|data| data.font.to_vec())
+        .unwrap_or_default()
+}
+
+// Новые ключи должны быть стойкими: слабые, полуслабые и совпадающие с чужим
+// ключом (без учёта битов чётности) отклоняются
+fn check_key_strength(bs2: &str, algorithm: Algorithm, index: Option<usize>, users: &[User]) -> Option<String> {
+    match key_weakness(algorithm, bs2) {
+        Some(KeyWeakness::Weak) => return Some(format!("Слабый ключ {}, выберите другой PIN", algorithm.title())),
+        Some(KeyWeakness::SemiWeak) => return Some(format!("Полуслабый ключ {}, выберите другой PIN", algorithm.title())),
+        None => {}
+    }
+
+    let key = effective_key(algorithm, bs2);
+    users
+        .iter()
+        .enumerate()
+        .find(|(i, u)| Some(*i) != index && u.algorithm == algorithm && effective_key(u.algorithm, &u.bs2) == key)
+        .map(|(_, u)| format!("Ключ совпадает с ключом пользователя {} (без учёта битов чётности)", u.login))
+}
+
+fn format_selector(ui: &mut egui::Ui, format: &mut OutputFormat) {
+    egui::ComboBox::from_id_source("user_format")
+        .selected_text(format.title())
+        .show_ui(ui, |ui| {
+            for option in OutputFormat::ALL {
+                let selected = std::mem::discriminant(&option) == std::mem::discriminant(format);
+                if ui.selectable_label(selected, option.title()).clicked() && !selected {
+                    *format = option;
+                }
+            }
+        });
+
+    if let OutputFormat::Decimal(digits) = format {
+        ui.add(egui::DragValue::new(digits).clamp_range(MIN_DIGITS..=MAX_DIGITS).suffix(" цифр"));
+    }
+}
+
+// Собственная граница окна пользователя; без флажка действует группа
+fn window_override(ui: &mut egui::Ui, label: &str, value: &mut Option<u64>) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, label).changed() {
+            *value = enabled.then_some(20);
+        }
+        if let Some(seconds) = value {
+            ui.add(egui::DragValue::new(seconds).clamp_range(0..=MAX_WINDOW).suffix(" с"));
+        }
+    });
+}
+
+fn field(ui: &mut egui::Ui, label: &str, value: &mut String, hint: &str, error: &Option<String>) {
+    secret_field(ui, label, value, hint, error, false);
+}
+
+// masked - ввод скрыт точками, как пароль; виден, пока нажата кнопка 👁
+fn secret_field(ui: &mut egui::Ui, label: &str, value: &mut String, hint: &str, error: &Option<String>, masked: bool) {
+    let reveal = ui.horizontal(|ui| {
+        ui.label(label);
+        masked && ui.small_button("👁").on_hover_text("Удерживайте, чтобы показать").is_pointer_button_down_on()
+    }).inner;
+    ui.add(
+        egui::TextEdit::singleline(value)
+            .hint_text(hint)
+            .font(egui::TextStyle::Monospace)
+            .password(masked && !reveal)
+    );
+    if let Some(error) = error {
+        ui.label(egui::RichText::new(error).color(egui::Color32::from_rgb(114, 28, 36)).small());
+    }
+    ui.add_space(5.0);
+}
+
+// Маска той же длины: ширина столбца не выдаёт ничего, кроме алгоритма
+fn mask(secret: &str) -> String {
+    "•".repeat(secret.chars().count())
+}
@@ -0,0 +1,167 @@
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::fmt;
+
+use otp_core::cipher::Algorithm;
+use otp_core::mode::AuthMode;
+use otp_core::text::{unencodable, TextEncoding};
+use otp_core::weak_keys::{effective_key, key_weakness, KeyWeakness};
+
+use crate::config::Config;
+use crate::database::{parse_datetime, parse_user, validate_bs2, validate_suite};
+
+// Ниже этого номера пора инициализировать цепочку S/KEY заново
+const CHAIN_LOW_WATER: u64 = 10;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+pub struct Issue {
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "ошибка",
+            Severity::Warning => "предупреждение",
+        };
+        write!(f, "строка {}: {}: {}", self.line, severity, self.message)
+    }
+}
+
+pub fn validate_database(text: &str, now: NaiveDateTime, config: &Config) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut logins: HashMap<String, usize> = HashMap::new();
+    let mut keys: HashMap<(Algorithm, Vec<u8>), (usize, String)> = HashMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut report = |severity: Severity, message: String| {
+            issues.push(Issue { line: line_no, severity, message });
+        };
+
+        if name_contains_space(line) {
+            report(Severity::Error, "ФИО содержит пробел, используйте _ вместо пробела".to_string());
+            continue;
+        }
+
+        let user = match parse_user(line) {
+            Ok(user) => user,
+            Err(reason) => {
+                report(Severity::Error, reason);
+                continue;
+            }
+        };
+
+        // Сообщения показываются и при скрытых секретах: BS2 в них не входит,
+        // пользователь назван логином
+        match validate_bs2(&user.bs2, user.algorithm) {
+            Ok(()) => match key_weakness(user.algorithm, &user.bs2) {
+                Some(KeyWeakness::Weak) => report(Severity::Error, format!(
+                    "BS2 пользователя {} - слабый ключ {}", user.login, user.algorithm.title()
+                )),
+                Some(KeyWeakness::SemiWeak) => report(Severity::Warning, format!(
+                    "BS2 пользователя {} - полуслабый ключ {}", user.login, user.algorithm.title()
+                )),
+                None => {}
+            },
+            Err(e) => report(Severity::Error, e),
+        }
+
+        if let Some(key) = effective_key(user.algorithm, &user.bs2) {
+            let key = (user.algorithm, key);
+            match keys.get(&key) {
+                Some((first, login)) => report(Severity::Error, format!(
+                    "BS2 совпадает с ключом пользователя {} (строка {}) с точностью до битов чётности",
+                    login, first
+                )),
+                None => {
+                    keys.insert(key, (line_no, user.login.clone()));
+                }
+            }
+        }
+
+        if user.mode == AuthMode::Ocra {
+            if let Err(e) = validate_suite(&user.suite) {
+                report(Severity::Error, e);
+            }
+        }
+
+        if user.mode == AuthMode::HashChain && user.chain.sequence < CHAIN_LOW_WATER {
+            report(Severity::Warning, match user.chain.sequence {
+                0 => "цепочка S/KEY исчерпана, задайте новый seed и номер".to_string(),
+                left => format!("в цепочке S/KEY осталось паролей: {}", left),
+            });
+        }
+
+        if let Some(group) = user.group.as_ref().filter(|group| !config.groups.contains_key(*group)) {
+            report(Severity::Warning, format!(
+                "группа {} не описана в настройках сервера, действует общее окно", group
+            ));
+        }
+
+        match user.datetime() {
+            Ok(epoch) if epoch > now => report(Severity::Error, format!(
+                "начальная настройка {} {} в будущем", user.date, user.time
+            )),
+            Ok(_) => {}
+            Err(e) => report(Severity::Error, format!("дата {} {}: {}", user.date, user.time, e)),
+        }
+
+        if let Some(first) = logins.get(&user.login) {
+            report(Severity::Error, format!("логин {} уже используется в строке {}", user.login, first));
+        } else {
+            logins.insert(user.login.clone(), line_no);
+        }
+
+        for (field, value) in [("ФИО", &user.name), ("логин", &user.login)] {
+            let lost: String = unencodable(value).into_iter().collect();
+            if lost.is_empty() {
+                continue;
+            }
+            match config.encoding {
+                TextEncoding::Windows1251 => report(Severity::Warning, format!(
+                    "{} «{}» содержит символы вне Windows-1251 ({}): базу нельзя сохранить, выберите кодировку UTF-8",
+                    field, value, lost
+                )),
+                TextEncoding::Auto => report(Severity::Warning, format!(
+                    "{} «{}» содержит символы вне Windows-1251 ({}): база будет сохранена в UTF-8, исходный Server.exe её не прочитает",
+                    field, value, lost
+                )),
+                TextEncoding::Utf8 => {}
+            }
+        }
+    }
+
+    issues
+}
+
+// ФИО с пробелом сдвигает остальные поля: ищем BS2 и дату правее ожидаемого места
+fn name_contains_space(line: &str) -> bool {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+
+    (1..parts.len().saturating_sub(4)).any(|shift| {
+        is_hex(parts[2 + shift])
+            && parse_datetime(&format!("{} {}", parts[3 + shift], parts[4 + shift])).is_ok()
+            && !is_hex(parts[2])
+    })
+}
+
+fn is_hex(s: &str) -> bool {
+    s.len() >= 16 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn has_errors(issues: &[Issue]) -> bool {
+    issues.iter().any(|i| i.severity == Severity::Error)
+}
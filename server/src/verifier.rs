@@ -0,0 +1,292 @@
+use chrono::NaiveDateTime;
+use otp_core::mode::AuthMode;
+use otp_core::secret::{self, SecretString, Zeroize};
+use otp_core::skey;
+use otp_core::text::TextEncoding;
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
+
+use crate::auth::{authenticate, authenticate_admin, issue_challenge, Challenge, Denial, DenialPolicy, Outcome, Session};
+use crate::config::Config;
+use crate::database::{format_database, parse_database, User};
+
+// Проверка пароля по файлам для модуля PAM и демона: база и состояние
+// читаются заново при каждой проверке под блокировкой файла, поэтому
+// процессы sshd, sudo и демон видят одни и те же счётчики, повторы и
+// блокировки.
+
+// Ответ внешнему клиенту: вид результата и текст по политике отказов
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Granted(String),
+    // Счётчик рассинхронизирован, нужен следующий пароль
+    Next(String),
+    Locked(String),
+    Unknown(String),
+    Denied(String),
+    Error(String),
+}
+
+impl Reply {
+    // При общей политике неизвестный и заблокированный логин не отличаются
+    // от остальных отказов и по виду ответа
+    pub fn from_outcome(outcome: &Outcome, policy: DenialPolicy) -> Self {
+        let text = outcome.reply(policy);
+        match (outcome, policy) {
+            (Outcome::Granted { .. }, _) => Reply::Granted(text),
+            (Outcome::Denied(Denial::CounterResync), _) => Reply::Next(text),
+            (Outcome::Denied(_), DenialPolicy::Generic) => Reply::Denied(text),
+            (Outcome::Denied(Denial::LockedOut), _) => Reply::Locked(text),
+            (Outcome::Denied(Denial::UnknownUser), _) => Reply::Unknown(text),
+            (Outcome::Denied(_), _) => Reply::Denied(text),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            Reply::Granted(text)
+            | Reply::Next(text)
+            | Reply::Locked(text)
+            | Reply::Unknown(text)
+            | Reply::Denied(text)
+            | Reply::Error(text) => text,
+        }
+    }
+
+    // Строка протокола демона: ключевое слово и текст
+    pub fn to_line(&self) -> String {
+        let keyword = match self {
+            Reply::Granted(_) => "OK",
+            Reply::Next(_) => "NEXT",
+            Reply::Locked(_) => "LOCKED",
+            Reply::Unknown(_) => "UNKNOWN",
+            Reply::Denied(_) => "DENY",
+            Reply::Error(_) => "ERROR",
+        };
+        format!("{} {}", keyword, self.text().replace(['\r', '\n'], " "))
+    }
+
+    pub fn parse(line: &str) -> Self {
+        let (keyword, text) = line.trim_end().split_once(' ').unwrap_or((line.trim_end(), ""));
+        let text = text.to_string();
+        match keyword {
+            "OK" => Reply::Granted(text),
+            "NEXT" => Reply::Next(text),
+            "LOCKED" => Reply::Locked(text),
+            "UNKNOWN" => Reply::Unknown(text),
+            "DENY" => Reply::Denied(text),
+            _ => Reply::Error(format!("неверный ответ демона: {}", line.trim_end())),
+        }
+    }
+}
+
+// Текст запроса, который пользователь видит перед вводом пароля (S/KEY,
+// запрос-ответ, OCRA), и выданный запрос, который нужно передать в verify
+pub fn challenge(config: &Config, login: &str) -> Result<(Option<String>, Option<Challenge>), String> {
+    let Some(user) = find_user(config, login)? else {
+        return Ok((None, None));
+    };
+
+    match user.mode {
+        AuthMode::HashChain => Ok((Some(skey::challenge(user.chain.hash, user.chain.sequence, &user.chain.seed)), None)),
+        AuthMode::Challenge | AuthMode::Ocra => {
+            let challenge = issue_challenge(&user, "")?;
+            Ok((Some(challenge.value.clone()), Some(challenge)))
+        }
+        AuthMode::Time | AuthMode::Counter => Ok((None, None)),
+    }
+}
+
+// Действующая запись пользователя (без блокировки: только для чтения
+// параметров, которые нужны до проверки)
+pub fn find_user(config: &Config, login: &str) -> Result<Option<User>, String> {
+    Ok(users(config)?.into_iter().find(|u| u.login == login && !u.disabled))
+}
+
+// Все записи базы, включая отключённые
+pub fn users(config: &Config) -> Result<Vec<User>, String> {
+    read_database(config).map(|(users, _, _)| users)
+}
+
+pub fn verify(
+    config: &Config,
+    challenge: Option<Challenge>,
+    login: &str,
+    password: &str,
+    now: NaiveDateTime,
+) -> Result<Outcome, String> {
+    verify_with(config, |users, session| {
+        if let Some(challenge) = challenge {
+            session.challenges.insert(challenge.login.clone(), challenge);
+        }
+        authenticate(users, session, login, password, config, now)
+    })
+}
+
+// Вход в интерфейс сервера: admin_password - результат проверки пароля
+// администратора, неудача считается блокировкой логина
+pub fn verify_admin(config: &Config, login: &str, admin_password: bool, otp: &str, now: NaiveDateTime) -> Result<Outcome, String> {
+    verify_with(config, |users, session| authenticate_admin(users, session, login, admin_password, otp, config, now))
+}
+
+fn verify_with(config: &Config, check: impl FnOnce(&mut [User], &mut Session) -> Outcome) -> Result<Outcome, String> {
+    let _lock = lock(config)?;
+    let (mut users, invalid, encoding) = read_database(config)?;
+    let mut session = load_state(config)?;
+
+    let outcome = check(&mut users, &mut session);
+
+    if let Outcome::Granted { changed: true, .. } = outcome {
+        let text = format_database(&users, &invalid);
+        config
+            .encoding
+            .keep(encoding)
+            .resolve(&text)
+            .write(&config.database.path, &text)
+            .map_err(|e| format!("{}: {}", config.database.path, e))?;
+    }
+    save_state(config, &session)?;
+
+    Ok(outcome)
+}
+
+// Изменение записей извне (служба gRPC) под той же блокировкой, что и
+// проверка; None от change - ничего не изменено, база не записывается
+pub fn update_users<T>(config: &Config, change: impl FnOnce(&mut Vec<User>) -> Option<T>) -> Result<Option<T>, String> {
+    let _lock = lock(config)?;
+    let (mut users, invalid, encoding) = read_database(config)?;
+    let Some(result) = change(&mut users) else {
+        return Ok(None);
+    };
+
+    let text = format_database(&users, &invalid);
+    config
+        .encoding
+        .keep(encoding)
+        .resolve(&text)
+        .write(&config.database.path, &text)
+        .map_err(|e| format!("{}: {}", config.database.path, e))?;
+    Ok(Some(result))
+}
+
+// Запись текста базы из интерфейса сервера под той же блокировкой. Текст
+// правился по копии, прочитанной раньше (loaded): счётчик, цепочку S/KEY,
+// коды восстановления и отключение, которые интерфейс не менял, проверки и
+// служба gRPC могли изменить после загрузки - для них остаются значения из
+// файла. read_as - кодировка, в которой текст был прочитан. Возвращает
+// кодировку записи и записанный текст.
+pub fn save_database(
+    config: &Config,
+    text: &str,
+    read_as: TextEncoding,
+    loaded: &[User],
+) -> Result<(TextEncoding, SecretString), String> {
+    let _lock = lock(config)?;
+    let stored = match Path::new(&config.database.path).exists() {
+        true => read_database(config)?.0,
+        false => Vec::new(),
+    };
+
+    let (mut users, invalid) = parse_database(text);
+    let mut merged = false;
+    for user in &mut users {
+        let find = |users: &[User]| users.iter().position(|u| u.login == user.login);
+        if let (Some(before), Some(now)) = (find(loaded), find(&stored)) {
+            merged |= keep_stored(user, &loaded[before], &stored[now]);
+        }
+    }
+    let text = if merged { format_database(&users, &invalid) } else { secret::copy(text) };
+
+    let encoding = config.encoding.keep(read_as).resolve(&text);
+    encoding
+        .write(&config.database.path, &text)
+        .map_err(|e| format!("{}: {}", config.database.path, e))?;
+    Ok((encoding, text))
+}
+
+// После проверки в интерфейсе: поля, которые проверка изменила в файле,
+// переносятся в запись login таблицы (edited), если администратор их не
+// правил, и в копию, прочитанную из файла (loaded). Остальные несохранённые
+// правки таблицы остаются.
+pub fn refresh_user(config: &Config, login: &str, edited: &mut [User], loaded: &mut [User]) -> Result<(), String> {
+    let Some(stored) = users(config)?.into_iter().find(|user| user.login == login) else {
+        return Ok(());
+    };
+    let Some(loaded) = loaded.iter_mut().find(|user| user.login == login) else {
+        return Ok(());
+    };
+
+    let original = loaded.clone();
+    keep_stored(loaded, &original, &stored);
+    if let Some(user) = edited.iter_mut().find(|user| user.login == login) {
+        keep_stored(user, &original, &stored);
+    }
+    Ok(())
+}
+
+// Поля, которые меняют проверки, из файла, если интерфейс их не трогал
+fn keep_stored(user: &mut User, loaded: &User, stored: &User) -> bool {
+    fn field<T: PartialEq + Clone>(edited: &mut T, loaded: &T, stored: &T) -> bool {
+        let take = edited == loaded && edited != stored;
+        if take {
+            *edited = stored.clone();
+        }
+        take
+    }
+
+    field(&mut user.counter, &loaded.counter, &stored.counter)
+        | field(&mut user.chain, &loaded.chain, &stored.chain)
+        | field(&mut user.recovery, &loaded.recovery, &stored.recovery)
+        | field(&mut user.disabled, &loaded.disabled, &stored.disabled)
+}
+
+pub fn load_state(config: &Config) -> Result<Session, String> {
+    let path = &config.database.state;
+    if path.is_empty() {
+        return Ok(Session::default());
+    }
+
+    match fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Session::default()),
+        Err(e) => Err(format!("{}: {}", path, e)),
+    }
+}
+
+// Запись через временный файл: прерванная запись не портит состояние
+pub fn save_state(config: &Config, session: &Session) -> Result<(), String> {
+    let path = &config.database.state;
+    if path.is_empty() {
+        return Ok(());
+    }
+
+    let text = toml::to_string(session).map_err(|e| e.to_string())?;
+    let temp = format!("{}.tmp", path);
+    fs::write(&temp, text)
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+fn read_database(config: &Config) -> Result<(Vec<User>, Vec<String>, TextEncoding), String> {
+    let mut decoded = config
+        .encoding
+        .read(&config.database.path)
+        .map_err(|e| format!("{}: {}", config.database.path, e))?;
+    let (users, invalid) = parse_database(&decoded.text);
+    // Текст базы со всеми BS2 не остаётся в памяти после разбора
+    decoded.text.zeroize();
+    Ok((users, invalid, decoded.encoding))
+}
+
+// Блокировка на время чтения, проверки и записи; снимается при закрытии файла
+fn lock(config: &Config) -> Result<File, String> {
+    let path = format!("{}.lock", config.database.path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    file.lock().map_err(|e| format!("{}: {}", path, e))?;
+    Ok(file)
+}
@@ -0,0 +1,101 @@
+// Вход в интерфейс сервера: пароль администратора и одноразовый пароль
+use std::fs;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use otp_core::cipher::{encrypt, time_block, Algorithm};
+use otp_core::format::{format_password, OutputFormat};
+use otp_server::admins::{self, AdminStore, Role};
+use otp_server::config::Config;
+
+const BS2: &str = "AAAAE2D76510BF24";
+const EPOCH: &str = "06.05.2007 21:24:30";
+
+fn otp(now: NaiveDateTime) -> String {
+    let epoch = NaiveDateTime::parse_from_str(EPOCH, "%d.%m.%Y %H:%M:%S").unwrap();
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, (now - epoch).num_seconds() as u64), BS2).unwrap();
+    format_password(OutputFormat::Hex, &hex).unwrap()
+}
+
+#[test]
+fn enrolls_first_admin_and_requires_both_passwords() {
+    let dir = std::env::temp_dir().join(format!("otp_admins_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let database = format!("Лапин_Е.В. Johnny {bs2} {epoch}\nПетров_П.П. Petrov {bs2} {epoch}\n", bs2 = BS2, epoch = EPOCH);
+    fs::write(dir.join("database.txt"), database).unwrap();
+    let mut config = Config::default();
+    config.relative_to(&dir);
+    otp_server::audit::configure(&config);
+
+    // Каждая проверка - новый интервал: пароль по времени принимается один раз
+    let mut now = Local::now().naive_local();
+    let mut next = || {
+        now += Duration::seconds(1);
+        (now, otp(now))
+    };
+
+    let (time, code) = next();
+    assert!(admins::enroll(&config, "Johnny", "кратко", &code, time).is_err());
+    assert!(admins::enroll(&config, "Johnny", "достаточно длинный", "0000000000000000", time).is_err());
+    admins::enroll(&config, "Johnny", "достаточно длинный", &code, time).unwrap();
+    let (time, code) = next();
+    assert!(admins::enroll(&config, "Petrov", "достаточно длинный", &code, time).is_err());
+
+    let mut store = AdminStore::load(&config.gui.admins).unwrap();
+    assert!(!fs::read_to_string(&config.gui.admins).unwrap().contains("достаточно"));
+    store.set("Petrov", Role::Auditor, "пароль аудитора").unwrap();
+    assert!(store.set("Johnny", Role::Operator, "достаточно длинный").is_err());
+    assert!(store.remove("Johnny").is_err());
+
+    let (time, code) = next();
+    assert_eq!(admins::login(&config, &store, "Petrov", "пароль аудитора", &code, time), Ok(Role::Auditor));
+    // Тот же одноразовый пароль второй раз не принимается
+    assert!(admins::login(&config, &store, "Petrov", "пароль аудитора", &code, time).is_err());
+    let (time, code) = next();
+    assert!(admins::login(&config, &store, "Petrov", "неверный пароль", &code, time).is_err());
+    let (time, code) = next();
+    assert_eq!(admins::login(&config, &store, "Johnny", "достаточно длинный", &code, time), Ok(Role::Admin));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn admin_password_failures_lock_out_and_recovery_codes_are_refused() {
+    let dir = std::env::temp_dir().join(format!("otp_admins_lockout_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let code = otp_server::recovery::generate_codes().remove(0);
+    let hash = otp_server::recovery::hash_code(&code).unwrap();
+    let database = format!("Лапин_Е.В. Johnny {bs2} {epoch} rc={hash}\n", bs2 = BS2, epoch = EPOCH, hash = hash);
+    fs::write(dir.join("database.txt"), database).unwrap();
+    let mut config = Config::default();
+    config.relative_to(&dir);
+    config.lockout.attempts = 3;
+    otp_server::audit::configure(&config);
+
+    let mut now = Local::now().naive_local();
+    now += Duration::seconds(1);
+    admins::enroll(&config, "Johnny", "достаточно длинный", &otp(now), now).unwrap();
+    let store = AdminStore::load(&config.gui.admins).unwrap();
+
+    // Код восстановления не заменяет одноразовый пароль и не тратится
+    assert!(admins::login(&config, &store, "Johnny", "достаточно длинный", &code, now).is_err());
+    let users = otp_server::verifier::users(&config).unwrap();
+    assert_eq!(users[0].recovery.len(), 1);
+
+    // Неверный пароль администратора не тратит одноразовый пароль
+    now += Duration::seconds(1);
+    let password = otp(now);
+    assert!(admins::login(&config, &store, "Johnny", "неверный пароль", &password, now).is_err());
+    assert_eq!(admins::login(&config, &store, "Johnny", "достаточно длинный", &password, now), Ok(Role::Admin));
+
+    // Три неудачи подряд блокируют логин и для верной пары паролей
+    for _ in 0..3 {
+        now += Duration::seconds(1);
+        assert!(admins::login(&config, &store, "Johnny", "неверный пароль", &otp(now), now).is_err());
+    }
+    now += Duration::seconds(1);
+    assert!(admins::login(&config, &store, "Johnny", "достаточно длинный", &otp(now), now).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
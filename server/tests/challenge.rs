@@ -0,0 +1,23 @@
+// Ответ OCRA с параметром T: допускается расхождение часов на один шаг
+use otp_server::auth::verify_challenge;
+use otp_server::database::parse_user;
+
+const SEED64: &str = "31323334353637383930313233343536373839303132333435363738393031323334353637383930313233343536373839303132333435363738393031323334";
+
+// RFC 6287: OCRA-1:HOTP-SHA512-8:QN08-T1M, Q=00000000, T=132d0b6 минут
+const TIME: u64 = 0x132d0b6 * 60;
+const RESPONSE: &str = "95209754";
+
+#[test]
+fn accepts_one_time_step_either_way() {
+    let record = format!("Лапин_Е.В. Johnny {} 06.05.2007 21:24:30 mode=ocra suite=OCRA-1:HOTP-SHA512-8:QN08-T1M", SEED64);
+    let user = parse_user(&record).unwrap();
+
+    for time in [TIME, TIME + 59, TIME - 60, TIME + 60, TIME + 119] {
+        assert!(verify_challenge(&user, RESPONSE, "00000000", time), "{}", time);
+    }
+    for time in [TIME - 61, TIME + 120] {
+        assert!(!verify_challenge(&user, RESPONSE, "00000000", time), "{}", time);
+    }
+    assert!(!verify_challenge(&user, RESPONSE, "11111111", TIME));
+}
@@ -0,0 +1,225 @@
+// Общее для тестов прокси LDAP: локальный заменитель каталога принимает
+// простую привязку для известных DN и паролей, отвечает на поиск пустым
+// результатом и запоминает пароли, которые до него дошли.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use otp_core::cipher::{encrypt, time_block, Algorithm};
+use otp_core::format::{format_password, OutputFormat};
+use otp_server::config::Config;
+use otp_server::ldap::{
+    bind_request, ldap_result, parse_message, read_message, result_code, BindRequest, BIND_REQUEST, BIND_RESPONSE,
+    EXTENDED_RESPONSE, INVALID_CREDENTIALS, MAX_REQUEST, MAX_RESPONSE, PROTOCOL_ERROR, SUCCESS,
+};
+
+pub const BS2: &str = "AAAAE2D76510BF24";
+pub const EPOCH: &str = "06.05.2007 21:24:30";
+pub const JOHNNY: &str = "uid=Johnny,ou=people,dc=example,dc=org";
+pub const PETROV: &str = "uid=Petrov,ou=people,dc=example,dc=org";
+
+pub const SEARCH_REQUEST: u8 = 0x63;
+pub const SEARCH_DONE: u8 = 0x65;
+pub const UNBIND_REQUEST: u8 = 0x42;
+
+pub type Seen = Arc<Mutex<Vec<(String, String)>>>;
+
+// Заменитель каталога LDAP на случайном порту
+pub fn directory() -> (String, Seen) {
+    let accounts: HashMap<&str, &str> = [(JOHNNY, "secret"), (PETROV, "пароль")].into();
+    let seen: Seen = Arc::default();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let log = Arc::clone(&seen);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let accounts = accounts.clone();
+            let log = Arc::clone(&log);
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                while let Ok(Some(message)) = read_message(&mut reader, MAX_REQUEST) {
+                    // Неразобранное тоже отмечается: до каталога оно дойти не должно
+                    let Some(envelope) = parse_message(&message) else {
+                        log.lock().unwrap().push(("?".to_string(), String::new()));
+                        break;
+                    };
+                    let reply = match envelope.op {
+                        BIND_REQUEST => {
+                            let bind = BindRequest::parse(envelope.body).unwrap();
+                            let password = String::from_utf8(bind.password.unwrap()).unwrap();
+                            log.lock().unwrap().push((bind.name.clone(), password.clone()));
+                            let known = bind.name.is_empty() || accounts.get(bind.name.as_str()) == Some(&password.as_str());
+                            let code = if known { SUCCESS } else { INVALID_CREDENTIALS };
+                            ldap_result(envelope.id, BIND_RESPONSE, code, "")
+                        }
+                        SEARCH_REQUEST => ldap_result(envelope.id, SEARCH_DONE, SUCCESS, ""),
+                        UNBIND_REQUEST => break,
+                        _ => ldap_result(envelope.id, EXTENDED_RESPONSE, PROTOCOL_ERROR, ""),
+                    };
+                    stream.write_all(&reply).unwrap();
+                }
+            });
+        }
+    });
+
+    (address, seen)
+}
+
+pub struct Proxy {
+    pub address: String,
+    pub seen: Seen,
+    pub dir: PathBuf,
+}
+
+impl Proxy {
+    pub fn start(name: &str) -> Self {
+        Self::start_with(name, |_, _| {})
+    }
+
+    // setup меняет настройки до запуска прокси (dir - каталог базы)
+    pub fn start_with(name: &str, setup: impl FnOnce(&mut Config, &Path)) -> Self {
+        let dir = std::env::temp_dir().join(format!("otp_ldap_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let database = format!(
+            "Лапин_Е.В. Johnny {bs2} {epoch}\nПетров_П.П. Petrov {bs2} {epoch} fmt=dec6\n",
+            bs2 = BS2,
+            epoch = EPOCH
+        );
+        fs::write(dir.join("database.txt"), database).unwrap();
+
+        let (upstream, seen) = directory();
+        let mut config = Config::default();
+        config.relative_to(&dir);
+        config.ldap.listen = free_address();
+        config.ldap.upstream = upstream;
+        setup(&mut config, &dir);
+        otp_server::audit::configure(&config);
+
+        let address = config.ldap.listen.clone();
+        thread::spawn(move || otp_server::ldap::serve(config));
+        for _ in 0..50 {
+            if TcpStream::connect(&address).is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        Proxy { address, seen, dir }
+    }
+
+    pub fn connect(&self) -> Client {
+        Client::new(self.tcp())
+    }
+
+    pub fn tcp(&self) -> TcpStream {
+        let stream = TcpStream::connect(&self.address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream
+    }
+
+    pub fn seen(&self) -> Vec<(String, String)> {
+        self.seen.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+pub trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+// Клиент LDAP поверх открытого сокета или TLS
+pub struct Client {
+    stream: Box<dyn Stream>,
+    id: i64,
+}
+
+impl Client {
+    pub fn new(stream: impl Stream + 'static) -> Self {
+        Client { stream: Box::new(stream), id: 0 }
+    }
+
+    // Код результата и тег ответа; None - прокси закрыл соединение
+    pub fn send(&mut self, message: impl FnOnce(i64) -> Vec<u8>) -> Option<(u8, u8)> {
+        self.id += 1;
+        self.stream.write_all(&message(self.id)).ok()?;
+        let reply = read_message(&mut self.stream, MAX_RESPONSE).ok()??;
+        let envelope = parse_message(&reply).unwrap();
+        assert_eq!(envelope.id, self.id);
+        Some((envelope.op, result_code(envelope.body).unwrap()))
+    }
+
+    pub fn bind(&mut self, name: &str, password: &str) -> Option<u8> {
+        let (op, code) = self.send(|id| bind_request(id, name, password.as_bytes()))?;
+        assert_eq!(op, BIND_RESPONSE);
+        Some(code)
+    }
+
+    pub fn closed(&mut self) -> bool {
+        matches!(read_message(&mut self.stream, MAX_RESPONSE), Ok(None) | Err(_))
+    }
+}
+
+pub fn free_address() -> String {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+}
+
+pub fn otp(format: OutputFormat) -> String {
+    let epoch = chrono::NaiveDateTime::parse_from_str(EPOCH, "%d.%m.%Y %H:%M:%S").unwrap();
+    let elapsed = (Local::now().naive_local() - epoch).num_seconds() as u64;
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, elapsed), BS2).unwrap();
+    format_password(format, &hex).unwrap()
+}
+
+
+// Учебный центр сертификации: свой у каждого теста
+pub struct Authority {
+    issuer: rcgen::CertifiedIssuer<'static, rcgen::KeyPair>,
+}
+
+impl Authority {
+    pub fn new(name: &str) -> Self {
+        let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        params.distinguished_name.push(rcgen::DnType::CommonName, name);
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let key = rcgen::KeyPair::generate().unwrap();
+        Authority {
+            issuer: rcgen::CertifiedIssuer::self_signed(params, key).unwrap(),
+        }
+    }
+
+    pub fn pem(&self) -> String {
+        self.issuer.pem()
+    }
+
+    // Сертификат и ключ в PEM для имени localhost
+    pub fn issue(&self, name: &str) -> (String, String) {
+        let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        params.distinguished_name.push(rcgen::DnType::CommonName, name);
+        let key = rcgen::KeyPair::generate().unwrap();
+        let certificate = params.signed_by(&key, &self.issuer).unwrap();
+        (certificate.pem(), key.serialize_pem())
+    }
+
+    // Сертификат сервера в файлы dir/server.crt и dir/server.key
+    pub fn install(&self, dir: &Path) {
+        let (certificate, key) = self.issue("server");
+        fs::write(dir.join("server.crt"), certificate).unwrap();
+        fs::write(dir.join("server.key"), key).unwrap();
+    }
+}
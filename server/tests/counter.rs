@@ -0,0 +1,60 @@
+// Пароли по счётчику: окно просмотра вперёд, повтор, синхронизация и
+// верхняя граница u64
+use otp_core::cipher::{encrypt, time_block, Algorithm};
+use otp_core::format::{format_password, OutputFormat};
+use otp_server::auth::{verify_counter, CounterResult, COUNTER_RESYNC_WINDOW};
+use otp_server::database::{parse_user, User};
+
+const BS2: &str = "AAAAE2D76510BF24";
+
+fn user(counter: u64) -> User {
+    parse_user(&format!("Лапин_Е.В. Johnny {} 06.05.2007 21:24:30 mode=counter counter={}", BS2, counter)).unwrap()
+}
+
+fn password(counter: u64) -> String {
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, counter), BS2).unwrap();
+    format_password(OutputFormat::Hex, &hex).unwrap()
+}
+
+#[test]
+fn accepts_within_window_and_rejects_replay() {
+    let user = user(10);
+    assert!(matches!(verify_counter(&user, &password(10), 5, None), CounterResult::Accepted(11)));
+    assert!(matches!(verify_counter(&user, &password(15), 5, None), CounterResult::Accepted(16)));
+
+    // Пароль до текущего значения счётчика уже потрачен
+    assert!(matches!(verify_counter(&user, &password(9), 5, None), CounterResult::Rejected));
+    assert!(matches!(verify_counter(&user, "0000000000000000", 5, None), CounterResult::Rejected));
+
+    // После принятия пароль 10 снова не проходит
+    let user = self::user(11);
+    assert!(matches!(verify_counter(&user, &password(10), 5, None), CounterResult::Rejected));
+}
+
+#[test]
+fn resyncs_only_with_the_next_password() {
+    let user = user(10);
+    let far = 10 + COUNTER_RESYNC_WINDOW;
+    let CounterResult::NeedsNext(pending) = verify_counter(&user, &password(far), 5, None) else {
+        panic!("ожидался запрос следующего пароля");
+    };
+    assert_eq!(pending, far);
+    assert!(matches!(verify_counter(&user, &password(far + 1), 5, Some(pending)), CounterResult::Accepted(next) if next == far + 2));
+    assert!(matches!(verify_counter(&user, &password(far + 2), 5, Some(pending)), CounterResult::Rejected));
+
+    // За пределами окна синхронизации пароль не принимается вовсе
+    assert!(matches!(verify_counter(&user, &password(far + 1), 5, None), CounterResult::Rejected));
+}
+
+#[test]
+fn stops_at_the_top_of_u64() {
+    let user = user(u64::MAX - 1);
+    assert!(matches!(verify_counter(&user, &password(u64::MAX - 1), 5, None), CounterResult::Accepted(u64::MAX)));
+
+    // Последнее значение счётчика следующего не имеет: отказ без паники
+    assert!(matches!(verify_counter(&user, &password(u64::MAX), 5, None), CounterResult::Rejected));
+    let user = self::user(u64::MAX);
+    assert!(matches!(verify_counter(&user, &password(u64::MAX), 5, None), CounterResult::Rejected));
+    assert!(matches!(verify_counter(&user, &password(u64::MAX), 5, Some(u64::MAX - 1)), CounterResult::Rejected));
+    assert!(matches!(verify_counter(&user, &password(0), 5, Some(u64::MAX)), CounterResult::Rejected));
+}
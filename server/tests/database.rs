@@ -0,0 +1,103 @@
+// Сохранение базы из интерфейса сервера поверх изменений, которые проверки
+// внесли после загрузки
+use std::fs;
+
+use chrono::Local;
+use otp_core::cipher::{encrypt, time_block, Algorithm};
+use otp_core::format::{format_password, OutputFormat};
+use otp_core::text::TextEncoding;
+use otp_server::auth::Outcome;
+use otp_server::config::Config;
+use otp_server::database::parse_database;
+use otp_server::verifier;
+
+const BS2: &str = "AAAAE2D76510BF24";
+const EPOCH: &str = "06.05.2007 21:24:30";
+
+fn password(block: u64) -> String {
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, block), BS2).unwrap();
+    format_password(OutputFormat::Hex, &hex).unwrap()
+}
+
+#[test]
+fn keeps_counters_spent_after_loading() {
+    let dir = std::env::temp_dir().join(format!("otp_database_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let database = format!(
+        "Счётчик Alice {bs2} {epoch} mode=counter counter=0\nСчётчик Carol {bs2} {epoch} mode=counter counter=0\n",
+        bs2 = BS2,
+        epoch = EPOCH
+    );
+    fs::write(dir.join("database.txt"), &database).unwrap();
+    let mut config = Config::default();
+    config.relative_to(&dir);
+    otp_server::audit::configure(&config);
+
+    // Интерфейс загрузил базу, затем модуль PAM принял пароли 0 и 1
+    let (loaded, _) = parse_database(&database);
+    let now = Local::now().naive_local();
+    for (login, block) in [("Alice", 0), ("Carol", 1)] {
+        let outcome = verifier::verify(&config, None, login, &password(block), now).unwrap();
+        assert!(matches!(outcome, Outcome::Granted { changed: true, .. }));
+    }
+
+    // Администратор переименовал Alice и задал счётчик Carol
+    let edited = format!(
+        "Иванова_А.А. Alice {bs2} {epoch} mode=counter counter=0\nСчётчик Carol {bs2} {epoch} mode=counter counter=7\n",
+        bs2 = BS2,
+        epoch = EPOCH
+    );
+    let (_, text) = verifier::save_database(&config, &edited, TextEncoding::Utf8, &loaded).unwrap();
+
+    let (users, _) = parse_database(&text);
+    assert_eq!((users[0].name.as_str(), users[0].counter), ("Иванова_А.А.", 1));
+    assert_eq!(users[1].counter, 7);
+    assert_eq!(text.as_str(), fs::read_to_string(dir.join("database.txt")).unwrap());
+
+    // Потраченный пароль Alice после сохранения не принимается снова
+    let outcome = verifier::verify(&config, None, "Alice", &password(0), now).unwrap();
+    assert!(matches!(outcome, Outcome::Denied(_)));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn refreshes_only_verifier_fields_after_check() {
+    let dir = std::env::temp_dir().join(format!("otp_refresh_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let database = format!(
+        "Счётчик Alice {bs2} {epoch} mode=counter counter=0\nСчётчик Carol {bs2} {epoch} mode=counter counter=0\n",
+        bs2 = BS2,
+        epoch = EPOCH
+    );
+    fs::write(dir.join("database.txt"), &database).unwrap();
+    let mut config = Config::default();
+    config.relative_to(&dir);
+    otp_server::audit::configure(&config);
+
+    // Несохранённые правки: ФИО Alice и счётчик Carol
+    let (mut loaded, _) = parse_database(&database);
+    let mut edited = loaded.clone();
+    edited[0].name = "Иванова_А.А.".to_string();
+    edited[1].counter = 7;
+
+    let now = Local::now().naive_local();
+    for (login, block) in [("Alice", 0), ("Carol", 0)] {
+        verifier::verify(&config, None, login, &password(block), now).unwrap();
+        verifier::refresh_user(&config, login, &mut edited, &mut loaded).unwrap();
+    }
+
+    assert_eq!((edited[0].name.as_str(), edited[0].counter), ("Иванова_А.А.", 1));
+    assert_eq!(edited[1].counter, 7);
+    assert_eq!((loaded[0].counter, loaded[1].counter), (1, 1));
+
+    // Сохранение после этого не возвращает потраченный счётчик Alice
+    let text = otp_server::database::format_database(&edited, &[]);
+    let (_, saved) = verifier::save_database(&config, &text, TextEncoding::Utf8, &loaded).unwrap();
+    let (users, _) = parse_database(&saved);
+    assert_eq!((users[0].counter, users[1].counter), (1, 7));
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,38 @@
+// Цепочка S/KEY: пароль принимается один раз, назад по цепочке не идут
+use otp_core::skey::{self, ChainHash};
+use otp_server::auth::verify_hash_chain;
+use otp_server::database::parse_user;
+
+const BS2: &str = "AAAAE2D76510BF24";
+
+fn password(sequence: u64) -> u64 {
+    skey::otp(ChainHash::Md5, "test", BS2, sequence)
+}
+
+#[test]
+fn rejects_replayed_and_older_passwords() {
+    let record = format!("Лапин_Е.В. Johnny {} 06.05.2007 21:24:30 mode=skey hash=md5 seed=test seq=5", BS2);
+    let mut user = parse_user(&record).unwrap();
+
+    // Ожидается пароль 5; ответ словами и в HEX
+    assert_eq!(verify_hash_chain(&user, &skey::to_words(password(5)), 3), Some((4, password(5))));
+    assert_eq!(verify_hash_chain(&user, &skey::to_hex(password(5)), 3), Some((4, password(5))));
+    user.chain.sequence = 4;
+    user.chain.last = Some(password(5));
+
+    // Повтор принятого пароля и пароли с большим номером отвергаются
+    assert_eq!(verify_hash_chain(&user, &skey::to_words(password(5)), 3), None);
+    assert_eq!(verify_hash_chain(&user, &skey::to_words(password(6)), 3), None);
+
+    // Пропущенные пароли - в пределах просмотра вперёд
+    assert_eq!(verify_hash_chain(&user, &skey::to_words(password(2)), 3), Some((1, password(2))));
+    assert_eq!(verify_hash_chain(&user, &skey::to_words(password(0)), 3), None);
+
+    // seq и last записываются в базу и читаются обратно: повтор после
+    // перезапуска тоже отвергается
+    user.chain.sequence = 1;
+    user.chain.last = Some(password(2));
+    let user = parse_user(&user.to_line()).unwrap();
+    assert_eq!(verify_hash_chain(&user, &skey::to_words(password(2)), 3), None);
+    assert_eq!(verify_hash_chain(&user, &skey::to_words(password(1)), 3), Some((0, password(1))));
+}
@@ -0,0 +1,158 @@
+// Прокси LDAP против локального заменителя каталога (tests/common)
+mod common;
+
+use common::*;
+use otp_core::format::OutputFormat;
+use otp_server::ldap::{
+    bind_request, ldap_result, login_from_dn, parse_message, read_message, result_code, AUTH_METHOD_NOT_SUPPORTED,
+    BIND_REQUEST, BIND_RESPONSE, EXTENDED_RESPONSE, INVALID_CREDENTIALS, MAX_REQUEST, MAX_RESPONSE, PROTOCOL_ERROR,
+    SUCCESS,
+};
+use std::io::Write;
+
+#[test]
+fn strips_otp_and_forwards_bind() {
+    let proxy = Proxy::start("forward");
+    let mut client = proxy.connect();
+
+    let password = format!("secret{}", otp(OutputFormat::Hex));
+    assert_eq!(client.bind(JOHNNY, &password), Some(SUCCESS));
+    assert_eq!(proxy.seen(), [(JOHNNY.to_string(), "secret".to_string())]);
+
+    // После привязки остальные операции идут к каталогу (заменитель смотрит
+    // только на тег операции)
+    let search = |id| ldap_result(id, SEARCH_REQUEST, SUCCESS, "");
+    assert_eq!(client.send(search), Some((SEARCH_DONE, SUCCESS)));
+}
+
+#[test]
+fn rejects_wrong_otp_without_asking_directory() {
+    let proxy = Proxy::start("wrong");
+    let mut client = proxy.connect();
+
+    assert_eq!(client.bind(JOHNNY, "secret0000000000000000"), Some(INVALID_CREDENTIALS));
+    assert!(client.closed());
+    assert!(proxy.seen().is_empty());
+
+    // Без одноразового пароля тоже отказ
+    let mut client = proxy.connect();
+    assert_eq!(client.bind(JOHNNY, "secret"), Some(INVALID_CREDENTIALS));
+    assert!(proxy.seen().is_empty());
+}
+
+#[test]
+fn relays_directory_refusal_and_blocks_replay() {
+    let proxy = Proxy::start("replay");
+    let code = otp(OutputFormat::Hex);
+
+    // Одноразовый пароль верный, пароль каталога нет
+    let mut client = proxy.connect();
+    assert_eq!(client.bind(JOHNNY, &format!("wrong{}", code)), Some(INVALID_CREDENTIALS));
+    assert_eq!(proxy.seen(), [(JOHNNY.to_string(), "wrong".to_string())]);
+
+    // Тот же одноразовый пароль второй раз не принимается
+    let mut client = proxy.connect();
+    assert_eq!(client.bind(JOHNNY, &format!("secret{}", code)), Some(INVALID_CREDENTIALS));
+    assert_eq!(proxy.seen().len(), 1);
+}
+
+#[test]
+fn uses_length_of_user_format() {
+    let proxy = Proxy::start("format");
+    let mut client = proxy.connect();
+
+    let password = format!("пароль{}", otp(OutputFormat::Decimal(6)));
+    assert_eq!(client.bind(PETROV, &password), Some(SUCCESS));
+    assert_eq!(proxy.seen(), [(PETROV.to_string(), "пароль".to_string())]);
+}
+
+#[test]
+fn passes_anonymous_and_refuses_sasl() {
+    let proxy = Proxy::start("sasl");
+    let mut client = proxy.connect();
+    assert_eq!(client.bind("", ""), Some(SUCCESS));
+
+    let sasl = |id| {
+        // authentication: sasl [3] { mechanism "EXTERNAL" }
+        let mut body = vec![0x02, 0x01, 0x03, 0x04, JOHNNY.len() as u8];
+        body.extend(JOHNNY.as_bytes());
+        body.extend([0xA3, 0x0A, 0x04, 0x08]);
+        body.extend(b"EXTERNAL");
+        let mut message = vec![0x02, 0x01, id as u8, BIND_REQUEST, body.len() as u8];
+        message.extend(body);
+        let mut out = vec![0x30, message.len() as u8];
+        out.extend(message);
+        out
+    };
+    assert_eq!(client.send(sasl), Some((BIND_RESPONSE, AUTH_METHOD_NOT_SUPPORTED)));
+    assert!(client.closed());
+}
+
+#[test]
+fn drops_messages_it_cannot_parse() {
+    let proxy = Proxy::start("malformed");
+
+    // Привязка с номером сообщения из 9 байт: каталог мог бы её понять
+    let plain = bind_request(1, JOHNNY, b"secret");
+    let mut content = vec![0x02, 0x09, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    content.extend(&plain[5..]);
+    let mut oversized = vec![0x30, content.len() as u8];
+    oversized.extend(content);
+
+    // Неизвестная операция с тегом привязки другого класса
+    let mut unknown = bind_request(1, JOHNNY, b"secret");
+    unknown[5] = 0x20;
+
+    for message in [oversized, unknown] {
+        let mut stream = proxy.tcp();
+        stream.write_all(&message).unwrap();
+        let reply = read_message(&mut stream, MAX_RESPONSE).unwrap().unwrap();
+        let envelope = parse_message(&reply).unwrap();
+        assert_eq!((envelope.id, envelope.op), (0, EXTENDED_RESPONSE));
+        assert_eq!(result_code(envelope.body), Some(PROTOCOL_ERROR));
+        assert!(matches!(read_message(&mut stream, MAX_RESPONSE), Ok(None) | Err(_)));
+    }
+    assert!(proxy.seen().is_empty());
+}
+
+#[test]
+fn login_is_taken_from_dn() {
+    let login = |dn, attribute| login_from_dn(dn, attribute).ok();
+    assert_eq!(login(JOHNNY, "uid").as_deref(), Some("Johnny"));
+    assert_eq!(login("CN = Лапин , dc=example", "cn").as_deref(), Some("Лапин"));
+    assert_eq!(login("Johnny", "uid").as_deref(), Some("Johnny"));
+    assert_eq!(login(JOHNNY, "").as_deref(), Some(JOHNNY));
+
+    // Экранирование RFC 4514 и RDN из нескольких пар
+    assert_eq!(login("cn=Лапин\\, Е.В.+uid=Johnny,dc=example", "cn").as_deref(), Some("Лапин, Е.В."));
+    assert_eq!(login("cn=Лапин\\, Е.В.+uid=Johnny,dc=example", "uid").as_deref(), Some("Johnny"));
+    assert_eq!(login("uid=John\\2Cny\\20,dc=example", "uid").as_deref(), Some("John,ny "));
+    assert_eq!(login("uid=\\D0\\9B,dc=example", "uid").as_deref(), Some("Л"));
+
+    // Повтор атрибута, кавычки, #BER и оборванное экранирование
+    for dn in [
+        "uid=Johnny+uid=Petrov,dc=example",
+        "uid=Petrov,uid=Johnny,dc=example",
+        "uid=\"Johnny\",dc=example",
+        "uid=#04064A6F686E6E79,dc=example",
+        "uid=Johnny\\",
+        "uid=John\\zny",
+        "uid=\\FF,dc=example",
+    ] {
+        assert!(login_from_dn(dn, "uid").is_err(), "{}", dn);
+    }
+}
+
+#[test]
+fn refuses_oversized_requests_before_reading_them() {
+    // Заголовок обещает сообщение сверх предела: отказ без чтения содержимого
+    let mut header: &[u8] = &[0x30, 0x84, 0x00, 0x01, 0x00, 0x00];
+    assert!(read_message(&mut header, MAX_REQUEST).is_err());
+
+    // Обрыв посреди содержимого - ошибка, а не неполное сообщение
+    let message = bind_request(1, JOHNNY, b"secret");
+    let mut cut = &message[..message.len() - 1];
+    assert!(read_message(&mut cut, MAX_REQUEST).is_err());
+    let mut whole = &message[..];
+    assert_eq!(read_message(&mut whole, MAX_REQUEST).unwrap(), Some(message.clone()));
+}
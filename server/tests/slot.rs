@@ -0,0 +1,49 @@
+// Пароль из печатного списка (slot=) принимается только в своём интервале
+use chrono::{Duration, NaiveDateTime};
+use otp_core::cipher::{encrypt, time_block, Algorithm};
+use otp_core::format::{format_password, OutputFormat};
+use otp_server::auth::verify_time;
+use otp_server::config::Window;
+use otp_server::database::parse_user;
+
+const BS2: &str = "AAAAE2D76510BF24";
+const EPOCH: &str = "06.05.2007 21:24:30";
+
+fn time(text: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(text, "%d.%m.%Y %H:%M:%S").unwrap()
+}
+
+// Пароль на начало интервала, как его печатает клиент
+fn password(at: NaiveDateTime) -> String {
+    let elapsed = (at - time(EPOCH)).num_seconds() as u64;
+    let hex = encrypt(Algorithm::Des, &time_block(Algorithm::Des, elapsed), BS2).unwrap();
+    format_password(OutputFormat::Hex, &hex).unwrap()
+}
+
+#[test]
+fn accepts_slot_password_only_inside_its_slot() {
+    let user = parse_user(&format!("Лапин_Е.В. Johnny {} {} slot=300", BS2, EPOCH)).unwrap();
+    let start = time(EPOCH);
+    let slot = time("01.01.2024 12:00:00");
+    let printed = password(slot);
+    let exact = Window { past: 0, future: 0 };
+    let at = |seconds| slot + Duration::seconds(seconds);
+
+    for seconds in [0, 1, 150, 299] {
+        assert_eq!(verify_time(&user, &printed, start, at(seconds), exact), Some(slot.and_utc().timestamp()), "+{}", seconds);
+    }
+    for seconds in [-1, 300, 600] {
+        assert_eq!(verify_time(&user, &printed, start, at(seconds), exact), None, "{:+}", seconds);
+    }
+
+    // Окно сервера расширяет интервал на своё значение
+    let window = Window { past: 30, future: 30 };
+    assert!(verify_time(&user, &printed, start, at(329), window).is_some());
+    assert!(verify_time(&user, &printed, start, at(-30), window).is_some());
+    assert!(verify_time(&user, &printed, start, at(331), window).is_none());
+
+    // Без slot= пароль начала интервала - обычный пароль одной секунды
+    let plain = parse_user(&format!("Лапин_Е.В. Johnny {} {}", BS2, EPOCH)).unwrap();
+    assert!(verify_time(&plain, &printed, start, at(0), exact).is_some());
+    assert!(verify_time(&plain, &printed, start, at(150), exact).is_none());
+}
@@ -0,0 +1,107 @@
+// Прокси LDAP поверх TLS с сертификатами от локальных центров (tests/common)
+mod common;
+
+use std::fs;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use common::*;
+use otp_core::format::OutputFormat;
+use otp_server::ldap::{INVALID_CREDENTIALS, SUCCESS};
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+// Клиент доверяет только центру trusted; identity - свой сертификат и ключ
+fn connect(proxy: &Proxy, trusted: &Authority, identity: Option<(String, String)>) -> Client {
+    let mut roots = RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_slice(trusted.pem().as_bytes()).unwrap()).unwrap();
+    let builder = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots);
+    let config = match identity {
+        Some((certificate, key)) => {
+            let chain = vec![CertificateDer::from_pem_slice(certificate.as_bytes()).unwrap()];
+            let key = PrivateKeyDer::from_pem_slice(key.as_bytes()).unwrap();
+            builder.with_client_auth_cert(chain, key).unwrap()
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let name = ServerName::try_from("localhost").unwrap();
+    let connection = ClientConnection::new(Arc::new(config), name).unwrap();
+    Client::new(StreamOwned::new(connection, proxy.tcp()))
+}
+
+fn password() -> String {
+    format!("secret{}", otp(OutputFormat::Hex))
+}
+
+#[test]
+fn proxy_accepts_ldaps() {
+    let authority = Authority::new("Учебный центр");
+    let proxy = Proxy::start_with("tls", |config, dir| {
+        authority.install(dir);
+        config.tls.certificate = dir.join("server.crt").to_string_lossy().into_owned();
+        config.tls.key = dir.join("server.key").to_string_lossy().into_owned();
+    });
+
+    let mut client = connect(&proxy, &authority, None);
+    assert_eq!(client.bind(JOHNNY, &password()), Some(SUCCESS));
+    assert_eq!(proxy.seen(), [(JOHNNY.to_string(), "secret".to_string())]);
+
+    // Открытый LDAP на том же адресе не проходит
+    let mut plain = proxy.connect();
+    assert_eq!(plain.bind(JOHNNY, "secret"), None);
+
+    // Клиент, не доверяющий центру, отключается на рукопожатии
+    let stranger = Authority::new("Чужой центр");
+    assert_eq!(connect(&proxy, &stranger, None).bind(JOHNNY, "secret"), None);
+    assert_eq!(proxy.seen().len(), 1);
+}
+
+#[test]
+fn client_certificate_is_required() {
+    let authority = Authority::new("Учебный центр");
+    let clients = Authority::new("Центр клиентов");
+    let proxy = Proxy::start_with("mtls", |config, dir| {
+        authority.install(dir);
+        fs::write(dir.join("clients.crt"), clients.pem()).unwrap();
+        config.tls.certificate = dir.join("server.crt").to_string_lossy().into_owned();
+        config.tls.key = dir.join("server.key").to_string_lossy().into_owned();
+        config.tls.client_ca = dir.join("clients.crt").to_string_lossy().into_owned();
+    });
+
+    assert_eq!(connect(&proxy, &authority, None).bind(JOHNNY, "secret"), None);
+    let foreign = authority.issue("сервис");
+    assert_eq!(connect(&proxy, &authority, Some(foreign)).bind(JOHNNY, "secret"), None);
+    assert!(proxy.seen().is_empty());
+
+    let mut client = connect(&proxy, &authority, Some(clients.issue("сервис")));
+    assert_eq!(client.bind(JOHNNY, &password()), Some(SUCCESS));
+}
+
+#[test]
+fn certificate_is_reloaded() {
+    let old = Authority::new("Старый центр");
+    let proxy = Proxy::start_with("reload", |config, dir| {
+        old.install(dir);
+        config.tls.certificate = dir.join("server.crt").to_string_lossy().into_owned();
+        config.tls.key = dir.join("server.key").to_string_lossy().into_owned();
+    });
+    let mut open = connect(&proxy, &old, None);
+    assert_eq!(open.bind("", ""), Some(SUCCESS));
+
+    // Время изменения файла у некоторых систем меняется раз в несколько мс
+    thread::sleep(Duration::from_millis(50));
+    let new = Authority::new("Новый центр");
+    new.install(&proxy.dir);
+
+    assert_eq!(connect(&proxy, &old, None).bind("", ""), None);
+    assert_eq!(connect(&proxy, &new, None).bind(JOHNNY, &password()), Some(SUCCESS));
+
+    // Открытое соединение остаётся на старом сертификате
+    assert_eq!(open.bind(JOHNNY, "secret"), Some(INVALID_CREDENTIALS));
+}
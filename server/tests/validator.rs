@@ -0,0 +1,107 @@
+// Проверка базы: по одному случаю на каждый вид замечания
+use chrono::NaiveDateTime;
+use otp_core::text::TextEncoding;
+use otp_server::config::{Config, Window};
+use otp_server::validator::{has_errors, validate_database, Issue, Severity};
+
+const EPOCH: &str = "06.05.2007 21:24:30";
+
+fn issues(database: &str, encoding: TextEncoding) -> Vec<Issue> {
+    let mut config = Config { encoding, ..Default::default() };
+    config.groups.insert("admins".to_string(), Window { past: 60, future: 60 });
+    let now = NaiveDateTime::parse_from_str("01.01.2024 00:00:00", "%d.%m.%Y %H:%M:%S").unwrap();
+    validate_database(database, now, &config)
+}
+
+// Единственное замечание: строка, важность и часть текста
+fn single(database: &str, encoding: TextEncoding) -> (usize, bool, String) {
+    let issues = issues(database, encoding);
+    assert_eq!(issues.len(), 1, "{:?}", issues.iter().map(ToString::to_string).collect::<Vec<_>>());
+    let issue = &issues[0];
+    (issue.line, issue.severity == Severity::Error, issue.message.clone())
+}
+
+#[test]
+fn clean_database_has_no_issues() {
+    let database = format!("Лапин_Е.В. Johnny AAAAE2D76510BF24 {0}\nПетров_П.П. Petrov 0200E2D76510BF24 {0} group=admins\n", EPOCH);
+    assert!(issues(&database, TextEncoding::Windows1251).is_empty());
+}
+
+#[test]
+fn duplicate_key() {
+    // Ключи отличаются только битами чётности
+    let database = format!("Лапин_Е.В. Johnny AAAAE2D76510BF24 {0}\nПетров_П.П. Petrov ABABE3D76411BE25 {0}\n", EPOCH);
+    let (line, error, message) = single(&database, TextEncoding::Utf8);
+    assert_eq!((line, error), (2, true));
+    assert!(message.contains("Johnny") && message.contains("строка 1"), "{}", message);
+}
+
+#[test]
+fn weak_and_semi_weak_keys() {
+    let (line, error, message) = single(&format!("Лапин_Е.В. Johnny 0101010101010101 {}", EPOCH), TextEncoding::Utf8);
+    assert_eq!((line, error), (1, true));
+    assert!(message.contains("слабый ключ"), "{}", message);
+
+    let (_, error, message) = single(&format!("Лапин_Е.В. Johnny 01FE01FE01FE01FE {}", EPOCH), TextEncoding::Utf8);
+    assert!(!error);
+    assert!(message.contains("полуслабый"), "{}", message);
+    // BS2 в текст замечания не попадает
+    assert!(!message.contains("01FE01FE01FE01FE"));
+}
+
+#[test]
+fn unknown_group() {
+    let (_, error, message) = single(&format!("Лапин_Е.В. Johnny AAAAE2D76510BF24 {} group=operators", EPOCH), TextEncoding::Utf8);
+    assert!(!error);
+    assert!(message.contains("operators"), "{}", message);
+}
+
+#[test]
+fn future_epoch() {
+    let (_, error, message) = single("Лапин_Е.В. Johnny AAAAE2D76510BF24 01.01.2030 00:00:00", TextEncoding::Utf8);
+    assert!(error);
+    assert!(message.contains("в будущем"), "{}", message);
+    assert!(has_errors(&issues("Лапин_Е.В. Johnny AAAAE2D76510BF24 01.01.2030 00:00:00", TextEncoding::Utf8)));
+}
+
+#[test]
+fn duplicate_login() {
+    let database = format!("Лапин_Е.В. Johnny AAAAE2D76510BF24 {0}\nЛапин_Е.В. Johnny 0200E2D76510BF24 {0}\n", EPOCH);
+    let (line, error, message) = single(&database, TextEncoding::Utf8);
+    assert_eq!((line, error), (2, true));
+    assert!(message.contains("уже используется в строке 1"), "{}", message);
+}
+
+#[test]
+fn unencodable_character() {
+    let database = format!("Łapin_E.W. Johnny AAAAE2D76510BF24 {}", EPOCH);
+    let (_, error, message) = single(&database, TextEncoding::Windows1251);
+    assert!(!error);
+    assert!(message.contains("Ł") && message.contains("UTF-8"), "{}", message);
+    let (_, _, message) = single(&database, TextEncoding::Auto);
+    assert!(message.contains("Server.exe"), "{}", message);
+    assert!(issues(&database, TextEncoding::Utf8).is_empty());
+}
+
+// Группы, изменённые в настройках, проходят ту же проверку, что и файл
+#[test]
+fn group_settings_are_validated_on_save() {
+    let dir = std::env::temp_dir().join(format!("otp_groups_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("server.toml");
+
+    let mut config = Config::default();
+    config.groups.insert("field".to_string(), Window { past: 300, future: 60 });
+    config.save(&path).unwrap();
+    assert_eq!(Config::load(&path).unwrap().groups["field"], Window { past: 300, future: 60 });
+
+    config.groups.insert("полевые работы".to_string(), Window { past: 0, future: 0 });
+    assert!(config.save(&path).is_err());
+    config.groups.clear();
+    config.groups.insert("field".to_string(), Window { past: 3601, future: 0 });
+    assert!(config.save(&path).is_err());
+    assert_eq!(Config::load(&path).unwrap().groups["field"].past, 300);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}